@@ -6,7 +6,11 @@
 use std::path::PathBuf;
 
 use criterion::{BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
-use tx_engine_rs::{AccountRecord, Error, TransactionRecord, process, process_parallel};
+use tx_engine_rs::{
+    AccountRecord, ChannelCapacity, Error, ProcessConfig, TransactionRecord, parse_records,
+    parse_records_binary, process, process_parallel, process_with_config,
+    write_transactions_binary,
+};
 
 const CHANNEL_CAPACITY: usize = 256;
 
@@ -17,13 +21,19 @@ fn fixture_path() -> PathBuf {
         .join("benchmark.csv")
 }
 
-fn bench_process(c: &mut Criterion) {
+/// The bytes of the benchmark fixture and its number of rows
+fn load_fixture() -> (Vec<u8>, usize) {
     let csv_bytes = std::fs::read(fixture_path()).expect(
         "benchmark fixture not found — run: \
          cargo nextest run --run-ignored only generate_benchmark_fixture'",
     );
 
     let row_count = csv_bytes.iter().filter(|&&b| b == b'\n').count() - 1; // minus header
+    (csv_bytes, row_count)
+}
+
+fn bench_process(c: &mut Criterion) {
+    let (csv_bytes, row_count) = load_fixture();
 
     let num_workers = std::thread::available_parallelism()
         .map(|n| n.get().saturating_sub(1).max(1))
@@ -62,8 +72,106 @@ fn bench_process(c: &mut Criterion) {
         },
     );
 
+    // the heuristic capacity derived from the input size, compared against the fixed one above
+    let auto_capacity = ChannelCapacity::Auto {
+        estimated_rows: row_count,
+    };
+    group.bench_function(
+        BenchmarkId::new(
+            format!(
+                "parallel_{num_workers}w_auto_capacity_{}",
+                auto_capacity.resolve(num_workers)
+            ),
+            row_count,
+        ),
+        |b| {
+            b.iter(|| {
+                let config = ProcessConfig::default()
+                    .workers(num_workers)
+                    .channel_capacity(auto_capacity);
+                let accounts: Vec<AccountRecord> = process_with_config(
+                    csv_bytes.as_slice(),
+                    config,
+                    |_: Error| {},
+                    |_: TransactionRecord| {},
+                )
+                .collect();
+                criterion::black_box(accounts);
+            });
+        },
+    );
+
+    group.finish();
+}
+
+/// The parsing stage on its own, so that throughput changes can be attributed to the parser or the engine
+fn bench_parse(c: &mut Criterion) {
+    let (csv_bytes, row_count) = load_fixture();
+
+    let mut group = c.benchmark_group("parse");
+    group.throughput(Throughput::Elements(row_count as u64));
+
+    group.bench_function(BenchmarkId::new("parse_records", row_count), |b| {
+        b.iter(|| criterion::black_box(parse_records(csv_bytes.as_slice()).count()));
+    });
+
+    // the valid rows of the fixture, pre-encoded
+    let mut binary_bytes = Vec::new();
+    write_transactions_binary(
+        parse_records(csv_bytes.as_slice()).flatten(),
+        &mut binary_bytes,
+    )
+    .expect("the fixture can be encoded");
+    group.bench_function(BenchmarkId::new("parse_records_binary", row_count), |b| {
+        b.iter(|| criterion::black_box(parse_records_binary(binary_bytes.as_slice()).count()));
+    });
+
+    group.finish();
+}
+
+/// Many dispute/resolve cycles over few accounts, which accumulate scale in the balances unless they are normalized
+fn dispute_cycles_input(cycles: usize) -> String {
+    let mut input = String::from("type, client, tx, amount\n");
+    for cycle in 0..cycles {
+        let client = cycle % 4 + 1;
+        input += &format!("deposit, {client}, {cycle}, 1.{:04}\n", cycle % 10_000);
+        input += &format!("dispute, {client}, {cycle},\nresolve, {client}, {cycle},\n");
+    }
+    input
+}
+
+fn bench_normalize_scale(c: &mut Criterion) {
+    const CYCLES: usize = 100_000;
+    let input = dispute_cycles_input(CYCLES);
+
+    let mut group = c.benchmark_group("normalize_scale");
+    group.throughput(Throughput::Elements(3 * CYCLES as u64));
+
+    for scale in [None, Some(4)] {
+        let name = scale.map_or_else(
+            || "as_computed".to_owned(),
+            |scale| format!("scale_{scale}"),
+        );
+        group.bench_function(BenchmarkId::new(name, CYCLES), |b| {
+            b.iter(|| {
+                let mut config = ProcessConfig::default();
+                if let Some(scale) = scale {
+                    config = config.normalize_scale(scale);
+                }
+                let accounts: Vec<AccountRecord> = process_with_config(
+                    input.as_bytes(),
+                    config,
+                    |_: Error| {},
+                    |_: TransactionRecord| {},
+                )
+                .collect();
+                criterion::black_box(accounts);
+            });
+        });
+    }
+
     group.finish();
 }
 
-criterion_group!(benches, bench_process);
+criterion_group!(benches, bench_process, bench_parse, bench_normalize_scale);
 criterion_main!(benches);
@@ -0,0 +1,578 @@
+//! Module defining the configuration of a processing run
+
+use std::ops::RangeInclusive;
+use std::sync::{Mutex, PoisonError};
+
+use rust_decimal::Decimal;
+
+use crate::{
+    AccountRecord, FundsMoved, Mutation, ProcessStats, Warning,
+    domain::{DisputePolicy, Policy},
+    input::ParseOptions,
+};
+
+/// An optional caller-provided callback. Wrapped in a `Mutex` so that it can be shared
+/// between the worker threads of the parallel engine.
+pub(crate) type Hook<'a, T> = Option<Mutex<Box<dyn FnMut(T) + Send + 'a>>>;
+
+/// Capacity of the bounded channels used by the parallel engine, unless configured otherwise
+pub const DEFAULT_CHANNEL_CAPACITY: usize = 256;
+
+/// The capacity of the bounded channels connecting the threads of the parallel engine, see
+/// [`ProcessConfig::channel_capacity`]. A plain `usize` converts into a [`Fixed`](Self::Fixed) capacity.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChannelCapacity {
+    /// Each channel holds the given number of transactions
+    Fixed(usize),
+    /// The capacity is derived from the estimated number of input rows and the number of workers:
+    /// `max(64, estimated_rows / workers / 8)`, limited to 65536, so that each worker can buffer about an eighth of
+    /// its share of the input without the channels growing unboundedly for very large inputs.
+    Auto { estimated_rows: usize },
+}
+
+impl ChannelCapacity {
+    const AUTO_MIN: usize = 64;
+    const AUTO_MAX: usize = 64 * 1024;
+
+    /// The capacity of each channel when processing with the given number of workers
+    pub fn resolve(self, num_workers: usize) -> usize {
+        match self {
+            ChannelCapacity::Fixed(capacity) => capacity,
+            ChannelCapacity::Auto { estimated_rows } => {
+                (estimated_rows / num_workers.max(1) / 8).clamp(Self::AUTO_MIN, Self::AUTO_MAX)
+            }
+        }
+    }
+}
+
+impl Default for ChannelCapacity {
+    fn default() -> Self {
+        ChannelCapacity::Fixed(DEFAULT_CHANNEL_CAPACITY)
+    }
+}
+
+impl From<usize> for ChannelCapacity {
+    fn from(capacity: usize) -> Self {
+        ChannelCapacity::Fixed(capacity)
+    }
+}
+
+/// Configuration of a processing run, passed to [`process_with_config()`](crate::process_with_config).
+///
+/// [`ProcessConfig::default()`] reproduces the behavior of [`process()`](crate::process);
+/// the chainable setters adjust individual aspects of it.
+///
+/// ```
+/// use rust_decimal_macros::dec;
+/// use tx_engine_rs::ProcessConfig;
+///
+/// let config = ProcessConfig::default()
+///     .workers(4)
+///     .channel_capacity(1024)
+///     .overdraft_limit(dec!(100))
+///     .rounding(2);
+/// ```
+#[derive(Default)]
+pub struct ProcessConfig<'a> {
+    pub(crate) workers: Option<usize>,
+    pub(crate) clamp_workers: bool,
+    pub(crate) min_rows_per_worker: Option<usize>,
+    pub(crate) channel_capacity: ChannelCapacity,
+    pub(crate) rounding: Option<u32>,
+    pub(crate) parse: ParseOptions,
+    pub(crate) policy: Policy,
+    pub(crate) settle_open_disputes: bool,
+    pub(crate) stream_worker_output: bool,
+    pub(crate) sort_by_client: bool,
+    pub(crate) include_num_transactions: bool,
+    pub(crate) max_rows: Option<usize>,
+    pub(crate) progress_interval: u64,
+    pub(crate) deterministic: bool,
+    pub(crate) verify_conservation: bool,
+    pub(crate) hooks: Hooks<'a>,
+}
+
+impl<'a> ProcessConfig<'a> {
+    /// Processes the transactions with the parallel, client-sharded engine using `num_workers`
+    /// worker threads (see [`process_parallel()`](crate::process_parallel)). By default, the
+    /// transactions are processed sequentially on the calling thread.
+    pub fn workers(mut self, num_workers: usize) -> Self {
+        self.workers = Some(num_workers);
+        self
+    }
+
+    /// Limits the number of worker threads to the available parallelism of the machine, since oversubscribing
+    /// the cores tends to hurt the throughput. By default, the configured number of workers is used as is.
+    pub fn clamp_workers(mut self, enabled: bool) -> Self {
+        self.clamp_workers = enabled;
+        self
+    }
+
+    /// Avoids spawning idle workers for a small input. Since the transactions are sharded by client, a run can use at
+    /// most one worker per client, which is only known once the input was read. The parallel engine therefore reads up
+    /// to `min_rows` rows per worker ahead; if the input ends within them, it uses only one worker per `min_rows` rows
+    /// (at least one) and per distinct client. The rows read ahead are buffered in memory. By default, the configured
+    /// number of workers is always spawned, even if most of them receive no transactions.
+    pub fn min_rows_per_worker(mut self, min_rows: usize) -> Self {
+        self.min_rows_per_worker = Some(min_rows);
+        self
+    }
+
+    /// The number of worker threads the parallel engine actually uses, or `None` for sequential processing.
+    /// A configured number of zero is raised to one and, if [`clamp_workers`](Self::clamp_workers) is enabled,
+    /// the number is limited to the available parallelism. For a small input, fewer workers may be used if
+    /// [`min_rows_per_worker`](Self::min_rows_per_worker) is set.
+    pub fn effective_workers(&self) -> Option<usize> {
+        self.workers.map(|requested| {
+            let workers = requested.max(1);
+            if self.clamp_workers {
+                let available = std::thread::available_parallelism().map_or(1, |n| n.get());
+                workers.min(available)
+            } else {
+                workers
+            }
+        })
+    }
+
+    /// Sets the capacity of the bounded channels connecting the threads of the parallel engine, either as a fixed
+    /// number of transactions or derived from the size of the input ([`ChannelCapacity::Auto`]). Defaults to
+    /// [`DEFAULT_CHANNEL_CAPACITY`]. Has no effect on sequential processing.
+    pub fn channel_capacity(mut self, capacity: impl Into<ChannelCapacity>) -> Self {
+        self.channel_capacity = capacity.into();
+        self
+    }
+
+    /// Pins the scheduling of the parallel engine for testing, e.g., so that a failing property test reproduces
+    /// identically: the dispatcher hands the transactions to the workers one at a time, waiting for each to be
+    /// handled before dispatching the next one. The callbacks are then invoked in input order (as by the sequential
+    /// engine), the dispatcher never waits for a full channel, and the shards of
+    /// [`stream_worker_output`](Self::stream_worker_output) are ordered by worker. Since the workers no longer run
+    /// concurrently, this is much slower. A break of [`process_until()`](crate::process_until) still takes effect
+    /// depending on the timing of the callback thread. Has no effect on sequential processing.
+    pub fn deterministic(mut self, enabled: bool) -> Self {
+        self.deterministic = enabled;
+        self
+    }
+
+    /// Outputs the accounts of each worker of the parallel engine as one contiguous group, in the order in which the
+    /// workers finished, instead of first merging the shards of all workers into a single map. This saves the cost of
+    /// the merge, and each shard is released as soon as its records are consumed. Note that the output order then
+    /// depends on the sharding and on the timing of the workers, so sort the records if a stable order is needed.
+    /// Has no effect on sequential processing.
+    pub fn stream_worker_output(mut self, enabled: bool) -> Self {
+        self.stream_worker_output = enabled;
+        self
+    }
+
+    /// Outputs the account records in ascending order of the client ids. By default, the order is unspecified and
+    /// may differ between runs, as well as between the sequential and the parallel engine. Sorted output is identical
+    /// for both engines, so that, e.g., their serialized outputs can be compared byte by byte. Takes precedence over
+    /// [`stream_worker_output`](Self::stream_worker_output).
+    pub fn sort_by_client(mut self, enabled: bool) -> Self {
+        self.sort_by_client = enabled;
+        self
+    }
+
+    /// Adds the number of accepted transactions applied to each account (e.g., for risk scoring) to the output records
+    /// as [`AccountRecord::num_transactions`], which is serialized as an additional column. Rejected transactions are
+    /// not counted. By default, the number is omitted, leaving the output unchanged.
+    pub fn include_num_transactions(mut self, enabled: bool) -> Self {
+        self.include_num_transactions = enabled;
+        self
+    }
+
+    /// Caps the number of input rows (including malformed and rejected ones) which are processed, e.g., for uploads
+    /// of untrusted users. If the input has more rows, the remaining ones are skipped and a single
+    /// [`Error::Truncated`](crate::Error::Truncated) is reported to `on_error` (and flagged in the
+    /// [`ProcessStats`](crate::ProcessStats)). Unlimited by default.
+    pub fn max_rows(mut self, max_rows: usize) -> Self {
+        self.max_rows = Some(max_rows);
+        self
+    }
+
+    /// Rounds the monetary fields of the output records to the given number of decimal places
+    /// (using banker's rounding). By default, the output carries the full precision.
+    pub fn rounding(mut self, decimal_places: u32) -> Self {
+        self.rounding = Some(decimal_places);
+        self
+    }
+
+    /// Sets the field delimiter of the input, e.g., `b'\t'` for tab-separated or `b';'` for
+    /// semicolon-separated input. Defaults to `b','`.
+    pub fn delimiter(mut self, delimiter: u8) -> Self {
+        self.parse.delimiter = delimiter;
+        self
+    }
+
+    /// Declares whether the input starts with a header row. Headerless input is mapped by column
+    /// position: type, client, tx, amount. Defaults to `true`, in which case the first row is
+    /// always consumed as the header.
+    pub fn has_headers(mut self, has_headers: bool) -> Self {
+        self.parse.has_headers = has_headers;
+        self
+    }
+
+    /// Skips input lines starting with the given byte, e.g., `b'#'` for annotated fixtures. By default, no lines are
+    /// treated as comments, so that a line starting with `#` is rejected as an invalid transaction.
+    pub fn comment_char(mut self, comment: u8) -> Self {
+        self.parse.comment = Some(comment);
+        self
+    }
+
+    /// Tolerates rows with additional trailing columns (e.g., a timestamp added upstream) beyond the ones of the header,
+    /// which are ignored. Additional columns named in the header are always ignored. Defaults to `false`, in which
+    /// case rows with more columns than the header are rejected.
+    pub fn flexible_columns(mut self, flexible: bool) -> Self {
+        self.parse.flexible = flexible;
+        self
+    }
+
+    /// Enforces chronological ordering of the input: each row must carry an integer `timestamp` column (e.g., unix
+    /// epoch seconds; the fifth column of headerless input), and rows with a timestamp preceding the one of the latest
+    /// accepted row are rejected with a validation error. Defaults to `false`, in which case a `timestamp` column
+    /// is ignored.
+    ///
+    /// The check runs while parsing, which happens on a single thread for both the sequential and the parallel
+    /// engine, so the ordering is enforced globally rather than per client in either case.
+    pub fn enforce_ordering(mut self, enforce: bool) -> Self {
+        self.parse.enforce_ordering = enforce;
+        self
+    }
+
+    /// Checks up front that the header of the input names the columns `type`, `client`, `tx`, and `amount` (in any
+    /// order, possibly among others). A header lacking one of them (e.g., with a mistyped `txid`) is reported as a
+    /// single [`Error::Header`](crate::Error::Header), and no rows are parsed. Enabled by default; when disabled, every
+    /// row of such input fails with a CSV error instead. Has no effect on headerless input.
+    pub fn check_header(mut self, enabled: bool) -> Self {
+        self.parse.check_header = enabled;
+        self
+    }
+
+    /// Reports a row which could not be parsed or failed the validation as an [`Error::Malformed`](crate::Error::Malformed)
+    /// carrying the row itself (e.g., for a dead-letter file), instead of the bare CSV or validation error. Rows which
+    /// cannot be read at all (e.g., with a different number of columns than the header, unless the input is flexible)
+    /// are still reported as CSV errors. Disabled by default.
+    pub fn capture_malformed_rows(mut self, enabled: bool) -> Self {
+        self.parse.capture_malformed_rows = enabled;
+        self
+    }
+
+    /// Sets the client ids which are reserved, e.g., for sentinel values of an upstream system. Transactions
+    /// referencing a reserved client are rejected with a validation error before they reach any account.
+    /// Defaults to `0..=0`; pass an empty range (e.g., `1..=0`) to accept every client id.
+    pub fn reserved_clients(mut self, clients: RangeInclusive<u16>) -> Self {
+        self.parse.reserved_clients = clients;
+        self
+    }
+
+    /// Accepts deposits and withdrawals with a zero amount (e.g., "ping" deposits keeping an account active) as
+    /// balance-neutral transactions instead of rejecting them as invalid. A zero deposit opens the account of the
+    /// client like any other deposit. Negative amounts are invalid (but see
+    /// [`negative_as_reversal`](Self::negative_as_reversal)). Disabled by default.
+    pub fn allow_zero_amount(mut self, allow: bool) -> Self {
+        self.parse.allow_zero_amount = allow;
+        self
+    }
+
+    /// Applies a deposit with a negative amount (e.g., a refund encoded as "reverse a prior credit") as a withdrawal
+    /// of the absolute amount instead of rejecting it as invalid: it reduces the available funds and is rejected as
+    /// [`InsufficientFunds`](crate::ProcessingKind::InsufficientFunds) if they do not suffice, regardless of the
+    /// [`overdraft_limit`](Self::overdraft_limit): a reversal never takes the balance below zero. The transaction is
+    /// reported as a withdrawal to the callbacks. Negative withdrawals are still invalid. Disabled by default.
+    pub fn negative_as_reversal(mut self, enabled: bool) -> Self {
+        self.parse.negative_as_reversal = enabled;
+        self
+    }
+
+    /// Accepts an amount with resolves and chargebacks, settling only part of a disputed deposit, e.g., for clearing
+    /// houses resolving part of a dispute and charging back the rest. A partial resolve releases the amount from the
+    /// held funds and leaves the dispute open with the remainder held. A partial chargeback reverses the amount and
+    /// locks the account, also leaving the remainder held; the rest of this dispute can still be settled by a resolve
+    /// or chargeback of the locked account. A settlement exceeding the held funds, or a partial settlement of a
+    /// disputed withdrawal, is rejected as [`InvalidSettlement`](crate::ProcessingKind::InvalidSettlement). Disabled
+    /// by default, i.e., an amount with a resolve or chargeback is invalid.
+    pub fn partial_settlements(mut self, enabled: bool) -> Self {
+        self.parse.partial_settlements = enabled;
+        self
+    }
+
+    /// Enables disputes on withdrawals. A disputed withdrawal holds the withdrawn amount until it
+    /// is resolved (the withdrawal stands) or charged back (the withdrawal is reversed and the
+    /// account is locked). By default, only deposits can be disputed.
+    pub fn dispute_withdrawals(mut self, enabled: bool) -> Self {
+        self.policy.dispute_withdrawals = enabled;
+        self
+    }
+
+    /// Declares whether a transaction whose dispute was resolved can be disputed again. If not, as required by some
+    /// clearing systems, a resolve finalizes the transaction and a further dispute is rejected as
+    /// [`AlreadyResolved`](crate::ProcessingKind::AlreadyResolved) ("transaction already resolved"). Defaults to
+    /// `true`.
+    pub fn allow_redispute(mut self, allow: bool) -> Self {
+        self.policy.forbid_redispute = !allow;
+        self
+    }
+
+    /// Rejects a dispute, resolve, or chargeback referencing a transaction of another client as
+    /// [`ForeignTransaction`](crate::ProcessingKind::ForeignTransaction) ("transaction does not belong to client")
+    /// instead of as a reference to an unknown transaction, e.g., for upstreams using globally unique tx ids. The
+    /// client's own transaction takes precedence if the ids collide. A reference the client's account does not know is
+    /// looked up in all other accounts, i.e., in time linear in their number. The parallel engine only sees the
+    /// accounts of a worker's shard, so that a transaction of a client handled by another worker is still reported as
+    /// unknown. Disabled by default for these reasons, so that both engines agree on the cause; without the check, a
+    /// foreign reference is rejected as unknown and has no effect on either account.
+    pub fn reject_foreign_transactions(mut self, enabled: bool) -> Self {
+        self.policy.reject_foreign_transactions = enabled;
+        self
+    }
+
+    /// Accepts a retried dispute, resolve, or chargeback as a no-op (without a balance change) instead of rejecting
+    /// it, for upstreams which deliver messages at least once and resend them on retry: a dispute of a transaction
+    /// already under dispute, a resolve of a transaction whose dispute was already resolved, and a chargeback of a
+    /// transaction already charged back. The retry is reported to `on_success` like the original. Defaults to `false`,
+    /// i.e., the retries are rejected as [`AlreadyDisputed`](crate::ProcessingKind::AlreadyDisputed),
+    /// [`AlreadyResolved`](crate::ProcessingKind::AlreadyResolved), and
+    /// [`AlreadyChargedBack`](crate::ProcessingKind::AlreadyChargedBack).
+    pub fn idempotent_disputes(mut self, enabled: bool) -> Self {
+        self.policy.idempotent_disputes = enabled;
+        self
+    }
+
+    /// Sets how a dispute is handled if the funds of the disputed deposit were (partly) withdrawn already. By default,
+    /// such a dispute is rejected ([`DisputePolicy::StrictFullHold`]).
+    pub fn dispute_policy(mut self, policy: DisputePolicy) -> Self {
+        self.policy.dispute_policy = policy;
+        self
+    }
+
+    /// Sets the credit line of each account: withdrawals are accepted as long as `available`
+    /// does not drop below `-limit`. The default of zero rejects any withdrawal exceeding the available funds.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `limit` is negative.
+    pub fn overdraft_limit(mut self, limit: Decimal) -> Self {
+        assert!(
+            limit >= Decimal::ZERO,
+            "the overdraft limit must not be negative"
+        );
+        self.policy.overdraft_limit = limit;
+        self
+    }
+
+    /// Sets the ceiling of the total funds (`available + held`) of each account, e.g., for account types with a
+    /// regulatory limit. A deposit exceeding it is rejected as
+    /// [`BalanceCapExceeded`](crate::ProcessingKind::BalanceCapExceeded), unless
+    /// [`clamp_to_max_balance`](Self::clamp_to_max_balance) is enabled. A deposit reaching the ceiling exactly is
+    /// accepted. Unbounded by default.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `limit` is negative.
+    pub fn max_balance(mut self, limit: Decimal) -> Self {
+        assert!(
+            limit >= Decimal::ZERO,
+            "the maximum balance must not be negative"
+        );
+        self.policy.max_balance = Some(limit);
+        self
+    }
+
+    /// Credits a deposit exceeding the [`max_balance`](Self::max_balance) up to the ceiling instead of rejecting it.
+    /// Only the credited part can be disputed later; `on_success` still reports the deposit with its full amount. A
+    /// deposit to an account at (or above) the ceiling is rejected regardless. Disabled by default.
+    pub fn clamp_to_max_balance(mut self, enabled: bool) -> Self {
+        self.policy.clamp_to_max_balance = enabled;
+        self
+    }
+
+    /// Caps the number of undisputed deposits retained per account for future disputes, bounding the memory an
+    /// account can occupy. Once the cap is exceeded, the oldest deposit is dropped: it stays credited, but
+    /// disputes referencing it are rejected as referencing an unknown transaction. A resolved deposit counts
+    /// as the most recent one again. Transactions whose dispute was resolved or charged back count against the same
+    /// cap, as they are retained to report repeated resolves and chargebacks; once dropped, these are reported as
+    /// referencing an unknown transaction. By default, every deposit is retained.
+    pub fn max_disputable_deposits(mut self, max: usize) -> Self {
+        self.policy.max_disputable_deposits = Some(max);
+        self
+    }
+
+    /// Creates an empty account for a client without account when a withdrawal, dispute, resolve, or chargeback
+    /// references it, instead of rejecting the transaction as [`NoAccount`](crate::ProcessingKind::NoAccount).
+    /// The transaction is then applied to the empty account, e.g., a dispute is rejected as referencing an unknown
+    /// transaction. The created accounts are part of the output, even if all transactions on them were rejected.
+    /// By default, only deposits create accounts.
+    pub fn auto_create_accounts(mut self, enabled: bool) -> Self {
+        self.policy.auto_create_accounts = enabled;
+        self
+    }
+
+    /// Enables `unlock` transactions (e.g., `unlock, 1, 42,`), which lift the lock of the client's account, e.g., to
+    /// reinstate a client after a chargeback turned out to be fraudulent. The balances and the dispute state of the
+    /// account are left as they are, and unlocking an account which is not locked has no effect. Since unlocking is a
+    /// privileged operation, it is disabled by default and unlocks are rejected as
+    /// [`UnlockNotAllowed`](crate::ProcessingKind::UnlockNotAllowed).
+    pub fn allow_admin_unlock(mut self, allow: bool) -> Self {
+        self.policy.allow_admin_unlock = allow;
+        self
+    }
+
+    /// Rescales the balances of the accounts to the given number of decimal places after every mutation, keeping
+    /// their representation compact over long runs of disputes and resolves. Since the amounts of the input carry at
+    /// most four decimal places, a scale of at least 4 leaves the values unchanged; only their representation (and
+    /// thus the trailing zeros of the output, e.g., `1.5000`) changes. A lower scale rounds the balances (half away
+    /// from zero). By default, the balances keep the scale they are computed with.
+    pub fn normalize_scale(mut self, scale: u32) -> Self {
+        self.policy.normalize_scale = Some(scale);
+        self
+    }
+
+    /// Enables checking the balance invariants of an account after every applied transaction: the available funds
+    /// must not drop below the (negated) overdraft limit and the held funds must not be negative. Since the engine
+    /// maintains these invariants by construction, a violation indicates an internal logic error: it panics in debug
+    /// builds and is reported as [`ProcessingKind::InvariantViolation`](crate::ProcessingKind::InvariantViolation)
+    /// in release builds, in which case the transaction is rolled back and has no effect on the account. The account
+    /// is copied before every transaction to this end. Disabled by default.
+    pub fn check_invariants(mut self, enabled: bool) -> Self {
+        self.policy.check_invariants = enabled;
+        self
+    }
+
+    /// Enables verifying at the end of a run that the funds are conserved: the total funds (`available + held`) of all
+    /// accounts must equal the funds moved by the applied transactions, i.e., the credited deposits minus the
+    /// withdrawals minus the funds removed by chargebacks. Since disputes and resolves only move funds within an
+    /// account, a mismatch indicates an internal logic error; it is reported once as
+    /// [`Error::NotConserved`](crate::Error::NotConserved) (also after a break of
+    /// [`process_until()`](crate::process_until)). Rescaling the balances to fewer than four decimal places via
+    /// [`normalize_scale`](Self::normalize_scale) rounds them, which the check reports as well. Not verified by the
+    /// [`TransactionEngine`](crate::TransactionEngine). Disabled by default.
+    pub fn verify_conservation(mut self, enabled: bool) -> Self {
+        self.verify_conservation = enabled;
+        self
+    }
+
+    /// Registers a callback invoked with a snapshot of an account at the moment it is locked by a chargeback.
+    /// Since a locked account rejects all further transactions, the callback is invoked exactly once per locked
+    /// account (unless it is unlocked again, see [`allow_admin_unlock`](Self::allow_admin_unlock)), e.g., for
+    /// real-time fraud alerting; the client id is available as [`AccountRecord::client`].
+    ///
+    /// In the parallel engine, the callback is invoked from the worker thread which applied the chargeback.
+    pub fn on_locked_account(mut self, callback: impl FnMut(AccountRecord) + Send + 'a) -> Self {
+        self.hooks.on_locked_account = Some(Mutex::new(Box::new(callback)));
+        self
+    }
+
+    /// Registers a callback invoked after every applied transaction with the balances of the affected account before
+    /// and after it, e.g., to log the exact sequence of mutations when tracking down a discrepancy with another
+    /// ledger. Rejected transactions are not reported. The balances are only captured if the callback is set.
+    ///
+    /// In the parallel engine, the callback is invoked from the worker thread which applied the transaction, so that
+    /// the mutations of a client are reported in order, while those of different clients may interleave.
+    pub fn on_mutation(mut self, callback: impl FnMut(Mutation) + Send + 'a) -> Self {
+        self.hooks.on_mutation = Some(Mutex::new(Box::new(callback)));
+        self
+    }
+
+    /// Registers a callback invoked with the [`Warning`]s raised by heuristics for suspicious but valid patterns in
+    /// the transactions of a client (e.g., a client disputing every deposit). The warnings do not affect the
+    /// processing, and the heuristics are only evaluated while the callback is set.
+    ///
+    /// In the parallel engine, the callback is invoked from the worker thread which applied the transaction.
+    pub fn on_warning(mut self, callback: impl FnMut(Warning) + Send + 'a) -> Self {
+        self.hooks.on_warning = Some(Mutex::new(Box::new(callback)));
+        self
+    }
+
+    /// Registers a callback invoked whenever a dispute moves funds from the available to the held balance of an
+    /// account, or a resolve moves them back, with the exact moved amount, e.g., for a fraud model keying off these
+    /// movements. Disputes and resolves of withdrawals (see [`dispute_withdrawals`](Self::dispute_withdrawals)) do
+    /// not move funds between the balances and are not reported; nor are chargebacks, which remove the held funds.
+    ///
+    /// In the parallel engine, the callback is invoked from the worker thread which applied the transaction.
+    pub fn on_funds_moved(mut self, callback: impl FnMut(FundsMoved) + Send + 'a) -> Self {
+        self.hooks.on_funds_moved = Some(Mutex::new(Box::new(callback)));
+        self
+    }
+
+    /// Enables the end-of-period settlement pass: funds still held by unresolved disputes are
+    /// moved back to `available`, i.e., open disputes are settled in the client's favor. The
+    /// held funds of a disputed withdrawal are released like by a resolve: the withdrawal stands.
+    /// The settled records are output instead of the raw ones.
+    pub fn settle_open_disputes(mut self, settle: bool) -> Self {
+        self.settle_open_disputes = settle;
+        self
+    }
+
+    /// Registers a callback invoked with the settled record of each account (see
+    /// [`settle_open_disputes`](Self::settle_open_disputes)) while the raw records are output.
+    /// This makes it possible to produce the settled output alongside the raw one.
+    pub fn on_settled_account(mut self, callback: impl FnMut(AccountRecord) + Send + 'a) -> Self {
+        self.hooks.on_settled_account = Some(Mutex::new(Box::new(callback)));
+        self
+    }
+
+    /// Registers a callback invoked with the (unrounded) record of each account whose monetary fields would change when
+    /// rounded to the output precision — the configured [`rounding`](Self::rounding), or else the four decimal places
+    /// of the input amounts. This makes it visible when a sub-cent residue is dropped from the output instead of
+    /// leaving the consumer to guess whether a value was rounded; the callback may, e.g., log a warning or flag the
+    /// run as failed. The records are output unchanged.
+    pub fn on_precision_loss(mut self, callback: impl FnMut(AccountRecord) + Send + 'a) -> Self {
+        self.hooks.on_precision_loss = Some(Mutex::new(Box::new(callback)));
+        self
+    }
+
+    /// Registers a callback invoked at the end of a parallel run with, per worker (indexed by the worker), the number of
+    /// times the dispatcher found the worker's channel full and had to wait. Useful for tuning
+    /// [`workers`](Self::workers) and [`channel_capacity`](Self::channel_capacity): high counts for single workers
+    /// point to hot clients, high counts across all workers to a capacity that is too small. Not invoked for sequential
+    /// processing.
+    pub fn on_backpressure(mut self, callback: impl FnMut(Vec<u64>) + Send + 'a) -> Self {
+        self.hooks.on_backpressure = Some(Mutex::new(Box::new(callback)));
+        self
+    }
+
+    /// Registers a callback invoked with the number of processed input rows (including rejected ones) whenever
+    /// another `interval` rows were processed, e.g., for a progress bar. The reported counts are the multiples of the
+    /// interval (an interval of zero is treated as one).
+    ///
+    /// In the parallel engine, the rows processed by the workers are counted together, and the callback is invoked
+    /// from the dispatching thread, so that a count may be reported somewhat after it was reached.
+    pub fn on_progress(mut self, interval: u64, callback: impl FnMut(u64) + Send + 'a) -> Self {
+        self.progress_interval = interval.max(1);
+        self.hooks.on_progress = Some(Mutex::new(Box::new(callback)));
+        self
+    }
+
+    /// Registers a callback invoked once with the [`ProcessStats`] of the run after the last transaction was processed
+    /// — with the parallel engine, after the workers finished — and before the first account record is produced, e.g.,
+    /// to flush the batches of an external sink fed by `on_success`. Also invoked for empty input. Not invoked by the
+    /// async `process_stream()`.
+    pub fn on_complete(mut self, callback: impl FnMut(ProcessStats) + Send + 'a) -> Self {
+        self.hooks.on_complete = Some(Mutex::new(Box::new(callback)));
+        self
+    }
+}
+
+/// The optional callbacks notified during a processing run
+#[derive(Default)]
+pub(crate) struct Hooks<'a> {
+    pub(crate) on_locked_account: Hook<'a, AccountRecord>,
+    pub(crate) on_settled_account: Hook<'a, AccountRecord>,
+    pub(crate) on_backpressure: Hook<'a, Vec<u64>>,
+    pub(crate) on_mutation: Hook<'a, Mutation>,
+    pub(crate) on_precision_loss: Hook<'a, AccountRecord>,
+    pub(crate) on_progress: Hook<'a, u64>,
+    pub(crate) on_funds_moved: Hook<'a, FundsMoved>,
+    pub(crate) on_warning: Hook<'a, Warning>,
+    pub(crate) on_complete: Hook<'a, ProcessStats>,
+}
+
+/// Invokes the hook (if set) with the event created by `event`. The event is only created when the hook is set.
+///
+/// A hook which panicked before (taking down the worker thread invoking it) is still invoked by the other workers.
+pub(crate) fn fire<T>(hook: &Hook<'_, T>, event: impl FnOnce() -> T) {
+    if let Some(hook) = hook {
+        let mut callback = hook.lock().unwrap_or_else(PoisonError::into_inner);
+        callback(event());
+    }
+}
@@ -0,0 +1,98 @@
+//! Module defining the log of all accepted deposits of a processing run, for answering audit queries after the run
+
+use std::collections::HashMap;
+
+use crate::TransactionRecord;
+use crate::domain::Money;
+
+/// The state of an accepted deposit at the end of a run
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DepositStatus {
+    /// Never disputed
+    Accepted,
+    /// Under dispute at the end of the run
+    Disputed,
+    /// The last dispute was resolved
+    Resolved,
+    /// Charged back (which locked the account)
+    ChargedBack,
+}
+
+/// An accepted deposit, as recorded in the [`DepositLog`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DepositEntry {
+    pub client: u16,
+    pub amount: Money,
+    pub status: DepositStatus,
+}
+
+/// Every deposit accepted during a run with its final status, returned by
+/// [`process_with_deposit_log()`](crate::process_with_deposit_log). Unlike the account states, which forget the
+/// deposits which can no longer be disputed (e.g., charged-back ones), the log retains all of them, so that its memory
+/// grows with the number of deposits.
+///
+/// Should a transaction id be reused by several clients, the log holds the latest deposit with the id.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct DepositLog {
+    deposits: HashMap<u64, DepositEntry>,
+}
+
+impl DepositLog {
+    /// The deposit with the given transaction id, or `None` if no deposit with the id was accepted
+    pub fn get(&self, tx: u64) -> Option<DepositEntry> {
+        self.deposits.get(&tx).copied()
+    }
+
+    /// The accepted deposits with their transaction ids, in arbitrary order
+    pub fn iter(&self) -> impl Iterator<Item = (u64, DepositEntry)> + '_ {
+        self.deposits.iter().map(|(tx, entry)| (*tx, *entry))
+    }
+
+    /// The number of accepted deposits
+    pub fn len(&self) -> usize {
+        self.deposits.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.deposits.is_empty()
+    }
+
+    /// Records a successfully applied transaction. A dispute, resolve, or chargeback updates the status of the
+    /// referenced deposit of the same client; one referencing a withdrawal is ignored. A partial resolve leaves the
+    /// deposit under dispute, as the remainder stays held.
+    pub(crate) fn record(&mut self, tx: &TransactionRecord) {
+        let (client, tx, status) = match *tx {
+            TransactionRecord::Deposit { client, tx, amount } => {
+                self.deposits.insert(
+                    tx,
+                    DepositEntry {
+                        client,
+                        amount,
+                        status: DepositStatus::Accepted,
+                    },
+                );
+                return;
+            }
+            TransactionRecord::Dispute { client, tx } => (client, tx, DepositStatus::Disputed),
+            TransactionRecord::Resolve {
+                client,
+                tx,
+                amount: None,
+            } => (client, tx, DepositStatus::Resolved),
+            TransactionRecord::Chargeback { client, tx, .. } => {
+                (client, tx, DepositStatus::ChargedBack)
+            }
+            TransactionRecord::Withdrawal { .. }
+            | TransactionRecord::Resolve { .. }
+            | TransactionRecord::Open { .. }
+            | TransactionRecord::Unlock { .. } => return,
+        };
+        if let Some(entry) = self
+            .deposits
+            .get_mut(&tx)
+            .filter(|entry| entry.client == client)
+        {
+            entry.status = status;
+        }
+    }
+}
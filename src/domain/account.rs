@@ -1,114 +1,646 @@
 //! Module defining the domain types related to the representation of the client account
 
-use std::collections::HashMap;
+use alloc::{collections::VecDeque, format, string::String};
 
-use crate::domain::{Deposit, Money, TxId};
+use crate::domain::{Deposit, DisputePolicy, Map, Money, Policy, Set, TxId, Withdrawal};
+
+/// The reason why an account rejects a transaction
+#[derive(Debug, PartialEq, Eq)]
+pub struct Rejection {
+    pub kind: ProcessingKind,
+    pub message: String,
+}
+
+/// The cause of a transaction conflicting with the current account state
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProcessingKind {
+    /// A withdrawal exceeds the available funds (plus the overdraft limit)
+    InsufficientFunds,
+    /// A dispute references a transaction which is unknown for the client (or no longer disputable)
+    UnknownTransaction,
+    /// A dispute references a transaction which is already under dispute
+    AlreadyDisputed,
+    /// A resolve references a transaction which is unknown or not under dispute
+    UndisputedResolve,
+    /// A chargeback references a transaction which is unknown or not under dispute
+    UndisputedChargeback,
+    /// A resolve or chargeback references a transaction whose dispute was already resolved (and not disputed anew), or
+    /// a dispute references such a transaction while re-disputes are not allowed
+    AlreadyResolved,
+    /// A resolve or chargeback references a transaction which was already charged back
+    AlreadyChargedBack,
+    /// A dispute references a deposit whose funds were already withdrawn
+    DisputeFundsWithdrawn,
+    /// The account is locked (reported as [`Error::AccountLocked`](crate::Error::AccountLocked))
+    AccountLocked,
+    /// A transaction other than a deposit references a client without account
+    NoAccount,
+    /// Applying the transaction would overflow the balances of the account, e.g., for amounts close to the maximal
+    /// representable one
+    ArithmeticOverflow,
+    /// A deposit would push the total funds of the account above the configured
+    /// [`max_balance`](crate::ProcessConfig::max_balance)
+    BalanceCapExceeded,
+    /// Applying the transaction left the account in an invalid state, e.g., with negative available funds. Only
+    /// reported if enabled via [`ProcessConfig::check_invariants`](crate::ProcessConfig::check_invariants); indicates
+    /// an internal logic error.
+    InvariantViolation,
+    /// An unlock was received while admin unlocks are disabled (see
+    /// [`allow_admin_unlock`](crate::ProcessConfig::allow_admin_unlock))
+    UnlockNotAllowed,
+    /// A dispute, resolve, or chargeback references a transaction of another client. Only reported if enabled via
+    /// [`reject_foreign_transactions`](crate::ProcessConfig::reject_foreign_transactions); in the parallel engine,
+    /// only the transactions of the clients sharing the worker are recognized as such, the others are reported as
+    /// unknown.
+    ForeignTransaction,
+    /// A partial resolve or chargeback settles more than the funds held by the dispute, or references a disputed
+    /// withdrawal, which can only be settled in full (see
+    /// [`partial_settlements`](crate::ProcessConfig::partial_settlements))
+    InvalidSettlement,
+}
+
+impl Rejection {
+    pub(crate) fn new(kind: ProcessingKind, message: impl Into<String>) -> Self {
+        Self {
+            kind,
+            message: message.into(),
+        }
+    }
+}
+
+/// The reason why an account is locked
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize)]
+#[cfg_attr(feature = "snapshot", derive(serde::Deserialize))]
+#[serde(tag = "reason", rename_all = "snake_case")]
+pub enum LockReason {
+    /// Locked by the chargeback of the transaction with the given id
+    Chargeback { tx_id: u64 },
+}
+
+/// A deposit under dispute. Under [`DisputePolicy::PartialHold`], less than the deposited amount may be held.
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "snapshot", derive(serde::Serialize, serde::Deserialize))]
+struct DisputedDeposit {
+    amount: Money,
+    held: Money,
+}
 
 /// The account state of a client
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone)]
+#[cfg_attr(feature = "snapshot", derive(serde::Serialize, serde::Deserialize))]
 pub(crate) struct AccountState {
-    accepted_deposits: HashMap<TxId, Money>,
-    disputed_deposits: HashMap<TxId, Money>,
+    accepted_deposits: Map<TxId, Money>,
+    disputed_deposits: Map<TxId, DisputedDeposit>,
+    // only populated if withdrawals can be disputed
+    accepted_withdrawals: Map<TxId, Money>,
+    disputed_withdrawals: Map<TxId, Money>,
+    // finalized transactions, to report repeated resolves/chargebacks specifically. A resolved transaction is removed
+    // again if it is disputed anew.
+    resolved: Set<TxId>,
+    charged_back: Set<TxId>,
+    // order in which the accepted deposits became disputable and the disputes were settled, oldest first; only
+    // populated if the number of retained transactions is capped
+    #[cfg_attr(feature = "snapshot", serde(alias = "deposit_order"))]
+    retained_order: VecDeque<TxId>,
 
     available: Money,
     held: Money,
-    locked: bool,
+    // the account is locked if a reason is set
+    lock_reason: Option<LockReason>,
+    // the number of accepted transactions applied to the account
+    #[cfg_attr(feature = "snapshot", serde(default))]
+    num_transactions: u64,
+}
+
+/// The part of the held funds settled by a resolve or chargeback: all of them, unless a partial amount is given, which
+/// must not exceed them
+fn settled_part(settled: Option<Money>, held: Money) -> Result<Money, Rejection> {
+    match settled {
+        None => Ok(held),
+        Some(amount) if amount <= held => Ok(amount),
+        Some(amount) => Err(Rejection::new(
+            ProcessingKind::InvalidSettlement,
+            format!("settlement of {amount} exceeds the held funds of {held}"),
+        )),
+    }
+}
+
+/// Rejects a partial settlement of a disputed withdrawal
+fn ensure_full_settlement(settled: Option<Money>) -> Result<(), Rejection> {
+    match settled {
+        None => Ok(()),
+        Some(_) => Err(Rejection::new(
+            ProcessingKind::InvalidSettlement,
+            "a disputed withdrawal can only be settled in full",
+        )),
+    }
+}
+
+fn overflow() -> Rejection {
+    Rejection::new(
+        ProcessingKind::ArithmeticOverflow,
+        "balance arithmetic overflow",
+    )
 }
 
 impl AccountState {
+    /// Creates an account with the given balances; a locked account is locked as if by the chargeback of transaction 0
     #[cfg(test)]
     pub(crate) fn new(available: Money, held: Money, locked: bool) -> Self {
         Self {
-            accepted_deposits: HashMap::new(),
-            disputed_deposits: HashMap::new(),
+            accepted_deposits: Map::new(),
+            disputed_deposits: Map::new(),
+            accepted_withdrawals: Map::new(),
+            disputed_withdrawals: Map::new(),
+            resolved: Set::new(),
+            charged_back: Set::new(),
+            retained_order: VecDeque::new(),
             available,
             held,
-            locked,
+            lock_reason: locked.then_some(LockReason::Chargeback { tx_id: 0 }),
+            num_transactions: 0,
         }
     }
 
-    pub(crate) fn deposit(&mut self, deposit: Deposit) -> Result<(), String> {
+    pub(crate) fn deposit(&mut self, deposit: Deposit, policy: &Policy) -> Result<(), Rejection> {
         self.ensure_not_locked()?;
 
-        self.available += deposit.amount();
-        self.accepted_deposits
-            .insert(deposit.tx_id(), deposit.amount());
+        let amount = self.capped_deposit_amount(deposit.amount(), policy)?;
+        self.set_balances(policy, self.available.checked_add(amount), Some(self.held))?;
+        self.accepted_deposits.insert(deposit.tx_id(), amount);
+        self.retain(deposit.tx_id(), policy);
         Ok(())
     }
 
-    pub(crate) fn withdraw(&mut self, amount: Money) -> Result<(), String> {
+    pub(crate) fn withdraw(
+        &mut self,
+        withdrawal: Withdrawal,
+        policy: &Policy,
+    ) -> Result<(), Rejection> {
         self.ensure_not_locked()?;
 
-        if self.available >= amount {
-            self.available -= amount;
+        let amount = withdrawal.amount();
+        let remaining = self.available.checked_sub(amount);
+        // a reversal of a prior credit never overdraws the account
+        let floor = if withdrawal.is_reversal() {
+            Money::ZERO
+        } else {
+            -policy.overdraft_limit
+        };
+        if remaining.is_none_or(|remaining| remaining >= floor) {
+            self.set_balances(policy, remaining, Some(self.held))?;
+            if policy.dispute_withdrawals {
+                self.accepted_withdrawals.insert(withdrawal.tx_id(), amount);
+            }
             Ok(())
         } else {
-            Err(format!("insufficient funds to withdraw {amount}"))
+            Err(Rejection::new(
+                ProcessingKind::InsufficientFunds,
+                format!("insufficient funds to withdraw {amount}"),
+            ))
         }
     }
 
-    pub(crate) fn dispute(&mut self, disputed_tx: TxId) -> Result<(), String> {
+    pub(crate) fn dispute(&mut self, disputed_tx: TxId, policy: &Policy) -> Result<(), Rejection> {
         self.ensure_not_locked()?;
+        if policy.forbid_redispute && self.resolved.contains(&disputed_tx) {
+            return Err(Rejection::new(
+                ProcessingKind::AlreadyResolved,
+                "transaction already resolved",
+            ));
+        }
 
-        if let Some(deposit_amount) = self.accepted_deposits.get(&disputed_tx) {
-            if self.available >= *deposit_amount {
-                let disputed_amount = self
-                    .accepted_deposits
-                    .remove(&disputed_tx)
-                    .expect("presence checked above");
-                self.available -= disputed_amount;
-                self.held += disputed_amount;
-                self.disputed_deposits.insert(disputed_tx, disputed_amount);
+        if let Some(&amount) = self.accepted_deposits.get(&disputed_tx) {
+            let held = if self.available >= amount {
+                Some(amount)
+            } else {
+                match policy.dispute_policy {
+                    DisputePolicy::StrictFullHold => None,
+                    // hold what is left of the deposit, if anything
+                    DisputePolicy::PartialHold => Some(self.available.max(Money::ZERO)),
+                }
+            };
+            if let Some(held) = held {
+                self.set_balances(
+                    policy,
+                    self.available.checked_sub(held),
+                    self.held.checked_add(held),
+                )?;
+                self.accepted_deposits.remove(&disputed_tx);
+                self.disputed_deposits
+                    .insert(disputed_tx, DisputedDeposit { amount, held });
+                self.resolved.remove(&disputed_tx);
+                self.forget_retained(disputed_tx);
                 Ok(())
             } else {
-                Err("the funds of the disputed deposit were already withdrawn".to_string())
+                Err(Rejection::new(
+                    ProcessingKind::DisputeFundsWithdrawn,
+                    "the funds of the disputed deposit were already withdrawn",
+                ))
+            }
+        } else if let Some(&withdrawn_amount) = self.accepted_withdrawals.get(&disputed_tx) {
+            // the withdrawn funds are held until the dispute is settled; they are only
+            // returned to the client if the withdrawal is charged back
+            self.set_balances(
+                policy,
+                Some(self.available),
+                self.held.checked_add(withdrawn_amount),
+            )?;
+            self.accepted_withdrawals.remove(&disputed_tx);
+            self.disputed_withdrawals
+                .insert(disputed_tx, withdrawn_amount);
+            self.resolved.remove(&disputed_tx);
+            self.forget_retained(disputed_tx);
+            Ok(())
+        } else if self.disputed_deposits.contains_key(&disputed_tx)
+            || self.disputed_withdrawals.contains_key(&disputed_tx)
+        {
+            if policy.idempotent_disputes {
+                // a retry of the dispute
+                return Ok(());
             }
+            Err(Rejection::new(
+                ProcessingKind::AlreadyDisputed,
+                "dispute referencing a transaction already under dispute",
+            ))
         } else {
-            Err("dispute referencing unknown transaction".to_string())
+            Err(Rejection::new(
+                ProcessingKind::UnknownTransaction,
+                "dispute referencing unknown transaction",
+            ))
         }
     }
 
-    pub(crate) fn resolve(&mut self, resolved_tx: TxId) -> Result<(), String> {
-        self.ensure_not_locked()?;
+    /// Settles the dispute of the transaction in favor of the client. A partial resolve (with the `settled` amount)
+    /// releases only that part of the held funds and leaves the dispute open.
+    pub(crate) fn resolve(
+        &mut self,
+        resolved_tx: TxId,
+        settled: Option<Money>,
+        policy: &Policy,
+    ) -> Result<(), Rejection> {
+        self.ensure_not_charged_back(resolved_tx)?;
+        self.ensure_settleable(resolved_tx)?;
 
-        if let Some(resolved_amount) = self.disputed_deposits.remove(&resolved_tx) {
+        if let Some(&DisputedDeposit { amount, held }) = self.disputed_deposits.get(&resolved_tx) {
+            debug_assert!(
+                self.held_funds() >= held,
+                "internal logic error: held funds too low during resolve"
+            );
+            let released = settled_part(settled, held)?;
+            self.set_balances(
+                policy,
+                self.available.checked_add(released),
+                self.held.checked_sub(released),
+            )?;
+            if released < held {
+                // the remainder stays held until the dispute is settled
+                self.disputed_deposits.insert(
+                    resolved_tx,
+                    DisputedDeposit {
+                        amount,
+                        held: held - released,
+                    },
+                );
+                return Ok(());
+            }
+            // only the held part is released; the deposit is disputable again with its full amount
+            self.disputed_deposits.remove(&resolved_tx);
+            self.accepted_deposits.insert(resolved_tx, amount);
+            self.retain(resolved_tx, policy);
+            self.resolved.insert(resolved_tx);
+            Ok(())
+        } else if let Some(&resolved_amount) = self.disputed_withdrawals.get(&resolved_tx) {
+            ensure_full_settlement(settled)?;
             debug_assert!(
                 self.held_funds() >= resolved_amount,
                 "internal logic error: held funds too low during resolve"
             );
-            self.held -= resolved_amount;
-            self.available += resolved_amount;
-            self.accepted_deposits.insert(resolved_tx, resolved_amount);
+            // the withdrawal stands
+            self.set_balances(
+                policy,
+                Some(self.available),
+                self.held.checked_sub(resolved_amount),
+            )?;
+            self.disputed_withdrawals.remove(&resolved_tx);
+            self.accepted_withdrawals
+                .insert(resolved_tx, resolved_amount);
+            self.resolved.insert(resolved_tx);
+            self.retain(resolved_tx, policy);
             Ok(())
+        } else if self.resolved.contains(&resolved_tx) {
+            if policy.idempotent_disputes {
+                // a retry of the resolve
+                return Ok(());
+            }
+            Err(Rejection::new(
+                ProcessingKind::AlreadyResolved,
+                "resolve referencing a transaction whose dispute was already resolved",
+            ))
         } else {
-            Err("resolve referencing unknown/undisputed transaction".to_string())
+            Err(Rejection::new(
+                ProcessingKind::UndisputedResolve,
+                "resolve referencing unknown/undisputed transaction",
+            ))
         }
     }
 
-    pub(crate) fn chargeback(&mut self, reverted_tx: TxId) -> Result<(), String> {
-        self.ensure_not_locked()?;
+    /// Settles the dispute of the transaction by reversing it, which locks the account. A partial chargeback (with
+    /// the `settled` amount) reverses only that part of the held funds and leaves the dispute open.
+    pub(crate) fn chargeback(
+        &mut self,
+        reverted_tx: TxId,
+        settled: Option<Money>,
+        policy: &Policy,
+    ) -> Result<(), Rejection> {
+        if policy.idempotent_disputes && self.charged_back.contains(&reverted_tx) {
+            // a retry of the chargeback, which already locked the account
+            return Ok(());
+        }
+        self.ensure_not_charged_back(reverted_tx)?;
+        self.ensure_settleable(reverted_tx)?;
 
-        if let Some(reverted_amount) = self.disputed_deposits.remove(&reverted_tx) {
+        if let Some(&DisputedDeposit { amount, held }) = self.disputed_deposits.get(&reverted_tx) {
+            debug_assert!(
+                self.held_funds() >= held,
+                "internal logic error: held funds too low during chargeback"
+            );
+            // only the held part is reversed; the rest of the deposit was withdrawn before the dispute
+            let reversed = settled_part(settled, held)?;
+            self.set_balances(
+                policy,
+                Some(self.available),
+                self.held.checked_sub(reversed),
+            )?;
+            self.lock_by_chargeback(reverted_tx);
+            if reversed < held {
+                // the remainder stays held until the dispute is settled
+                self.disputed_deposits.insert(
+                    reverted_tx,
+                    DisputedDeposit {
+                        amount,
+                        held: held - reversed,
+                    },
+                );
+                return Ok(());
+            }
+            self.disputed_deposits.remove(&reverted_tx);
+            self.charged_back.insert(reverted_tx);
+            self.retain(reverted_tx, policy);
+            Ok(())
+        } else if let Some(&reverted_amount) = self.disputed_withdrawals.get(&reverted_tx) {
+            ensure_full_settlement(settled)?;
             debug_assert!(
                 self.held_funds() >= reverted_amount,
                 "internal logic error: held funds too low during chargeback"
             );
-            self.held -= reverted_amount;
-            self.locked = true;
+            // the withdrawal is reversed: the held funds are credited back to the client
+            self.set_balances(
+                policy,
+                self.available.checked_add(reverted_amount),
+                self.held.checked_sub(reverted_amount),
+            )?;
+            self.disputed_withdrawals.remove(&reverted_tx);
+            self.lock_by_chargeback(reverted_tx);
+            self.charged_back.insert(reverted_tx);
+            self.retain(reverted_tx, policy);
+            Ok(())
+        } else if self.resolved.contains(&reverted_tx) {
+            Err(Rejection::new(
+                ProcessingKind::AlreadyResolved,
+                "chargeback referencing a transaction whose dispute was already resolved",
+            ))
+        } else {
+            Err(Rejection::new(
+                ProcessingKind::UndisputedChargeback,
+                "chargeback referencing unknown/undisputed transaction",
+            ))
+        }
+    }
+
+    /// The funds a deposit of the amount credits to the account: less than the amount if it is clamped to the maximum
+    /// balance
+    pub(crate) fn credited_amount(&self, amount: Money, policy: &Policy) -> Money {
+        self.capped_deposit_amount(amount, policy).unwrap_or(amount)
+    }
+
+    /// The funds a chargeback of the transaction removes from the account: the held part of a disputed deposit (or the
+    /// `settled` part of it), or the negated amount of a disputed withdrawal (whose funds are returned); zero for any
+    /// other transaction
+    pub(crate) fn charged_back_funds(&self, tx_id: TxId, settled: Option<Money>) -> Money {
+        if let Some(deposit) = self.disputed_deposits.get(&tx_id) {
+            settled.unwrap_or(deposit.held)
+        } else if let Some(&withdrawn_amount) = self.disputed_withdrawals.get(&tx_id) {
+            -withdrawn_amount
+        } else {
+            Money::ZERO
+        }
+    }
+
+    /// The funds a dispute of the transaction holds in addition to the available funds: the amount of a disputable
+    /// withdrawal; zero for any other transaction (a deposit with the same id takes precedence)
+    pub(crate) fn disputable_withdrawal_funds(&self, tx_id: TxId) -> Money {
+        if self.accepted_deposits.contains_key(&tx_id) {
+            return Money::ZERO;
+        }
+        self.accepted_withdrawals
+            .get(&tx_id)
+            .copied()
+            .unwrap_or(Money::ZERO)
+    }
+
+    /// The funds held by the dispute of the transaction in addition to the available funds: the amount of a disputed
+    /// withdrawal; zero for any other transaction (a deposit with the same id takes precedence)
+    pub(crate) fn disputed_withdrawal_funds(&self, tx_id: TxId) -> Money {
+        if self.disputed_deposits.contains_key(&tx_id) {
+            return Money::ZERO;
+        }
+        self.disputed_withdrawals
+            .get(&tx_id)
+            .copied()
+            .unwrap_or(Money::ZERO)
+    }
+
+    /// The amount of a deposit to credit given the maximum balance: the full amount if the cap is not exceeded, the
+    /// remaining headroom if clamping is configured (and there is headroom left), and a rejection otherwise
+    fn capped_deposit_amount(&self, amount: Money, policy: &Policy) -> Result<Money, Rejection> {
+        let Some(max_balance) = policy.max_balance else {
+            return Ok(amount);
+        };
+
+        let headroom = self
+            .available
+            .checked_add(self.held)
+            .and_then(|total| max_balance.checked_sub(total))
+            .ok_or_else(overflow)?;
+        if amount <= headroom {
+            Ok(amount)
+        } else if policy.clamp_to_max_balance && headroom > Money::ZERO {
+            Ok(headroom)
+        } else {
+            Err(Rejection::new(
+                ProcessingKind::BalanceCapExceeded,
+                format!("deposit of {amount} exceeds the maximum balance of {max_balance}"),
+            ))
+        }
+    }
+
+    /// Lifts the lock of the account, if admin unlocks are enabled by the policy. The balances and the dispute state
+    /// are left as they are; unlocking an account which is not locked has no effect.
+    pub(crate) fn unlock(&mut self, policy: &Policy) -> Result<(), Rejection> {
+        if !policy.allow_admin_unlock {
+            return Err(Rejection::new(
+                ProcessingKind::UnlockNotAllowed,
+                "unlock received while admin unlocks are disabled",
+            ));
+        }
+        self.lock_reason = None;
+        Ok(())
+    }
+
+    /// Updates the balances, unless an operation computing them overflowed (`None`) or their total is not
+    /// representable. In that case, the transaction is rejected and the account state is left as is, so that
+    /// pathological inputs (e.g., amounts close to [`Money::MAX`]) cannot panic. The balances are rescaled to the scale
+    /// of the policy, if any.
+    fn set_balances(
+        &mut self,
+        policy: &Policy,
+        available: Option<Money>,
+        held: Option<Money>,
+    ) -> Result<(), Rejection> {
+        match (available, held) {
+            (Some(mut available), Some(mut held)) if available.checked_add(held).is_some() => {
+                if let Some(scale) = policy.normalize_scale {
+                    available.rescale(scale);
+                    held.rescale(scale);
+                }
+                self.available = available;
+                self.held = held;
+                Ok(())
+            }
+            _ => Err(overflow()),
+        }
+    }
+
+    fn lock_by_chargeback(&mut self, reverted_tx: TxId) {
+        self.lock_reason = Some(LockReason::Chargeback {
+            tx_id: reverted_tx.into(),
+        });
+    }
+
+    /// Rejects a repeated resolve/chargeback of a charged-back transaction. Takes precedence over the lock check (the
+    /// account is locked by the chargeback), so that the more specific cause is reported.
+    fn ensure_not_charged_back(&self, tx_id: TxId) -> Result<(), Rejection> {
+        if self.charged_back.contains(&tx_id) {
+            Err(Rejection::new(
+                ProcessingKind::AlreadyChargedBack,
+                "transaction was already charged back",
+            ))
+        } else {
             Ok(())
+        }
+    }
+
+    /// Records the transaction as the most recently retained one, i.e., a deposit which became disputable or a
+    /// transaction whose dispute was settled, and, if the number of retained transactions is capped, drops the oldest
+    /// ones exceeding the cap. Dropped deposits can no longer be disputed, and dropped settled transactions are no
+    /// longer recognized as resolved or charged back; the balances are unaffected.
+    fn retain(&mut self, tx_id: TxId, policy: &Policy) {
+        let Some(max_retained) = policy.max_disputable_deposits else {
+            return;
+        };
+
+        self.retained_order.push_back(tx_id);
+        while self.retained_order.len() > max_retained {
+            let oldest = self
+                .retained_order
+                .pop_front()
+                .expect("the order is longer than the cap");
+            self.accepted_deposits.remove(&oldest);
+            self.resolved.remove(&oldest);
+            self.charged_back.remove(&oldest);
+        }
+    }
+
+    /// Removes the transaction from the retained ones once it is disputed (anew), as it is tracked by the dispute
+    /// until settled. Linear in the capped number of retained transactions; a no-op if the number is not capped.
+    fn forget_retained(&mut self, tx_id: TxId) {
+        self.retained_order.retain(|retained| *retained != tx_id);
+    }
+
+    /// Checks the invariants every account state must satisfy, independent of the processed transactions: the
+    /// available funds do not drop below the overdraft limit, and the held funds are not negative. A violation
+    /// indicates an internal logic error rather than an invalid transaction.
+    pub(crate) fn check_invariants(&self, policy: &Policy) -> Result<(), Rejection> {
+        if self.available < -policy.overdraft_limit {
+            Err(Rejection::new(
+                ProcessingKind::InvariantViolation,
+                format!(
+                    "invariant violated: available funds {} below the overdraft limit {}",
+                    self.available, policy.overdraft_limit
+                ),
+            ))
+        } else if self.held < Money::ZERO {
+            Err(Rejection::new(
+                ProcessingKind::InvariantViolation,
+                format!("invariant violated: negative held funds {}", self.held),
+            ))
         } else {
-            Err("chargeback referencing unknown/undisputed transaction".to_string())
+            Ok(())
         }
     }
 
-    fn ensure_not_locked(&self) -> Result<(), String> {
-        if self.locked {
-            Err("account locked: transaction rejected".to_string())
+    /// Rejects a resolve or chargeback of a locked account, unless it settles the rest of the dispute whose partial
+    /// chargeback locked the account
+    fn ensure_settleable(&self, tx_id: TxId) -> Result<(), Rejection> {
+        let locked_by_dispute = self.lock_reason
+            == Some(LockReason::Chargeback {
+                tx_id: tx_id.into(),
+            });
+        if locked_by_dispute && self.disputed_deposits.contains_key(&tx_id) {
+            Ok(())
+        } else {
+            self.ensure_not_locked()
+        }
+    }
+
+    fn ensure_not_locked(&self) -> Result<(), Rejection> {
+        if self.is_locked() {
+            Err(Rejection::new(
+                ProcessingKind::AccountLocked,
+                "account locked: transaction rejected",
+            ))
         } else {
             Ok(())
         }
     }
 
+    /// Whether the transaction was applied to the account and is still tracked for disputes, or was finalized by
+    /// a resolve or chargeback
+    pub(crate) fn references(&self, tx_id: TxId) -> bool {
+        self.accepted_deposits.contains_key(&tx_id)
+            || self.disputed_deposits.contains_key(&tx_id)
+            || self.accepted_withdrawals.contains_key(&tx_id)
+            || self.disputed_withdrawals.contains_key(&tx_id)
+            || self.resolved.contains(&tx_id)
+            || self.charged_back.contains(&tx_id)
+    }
+
+    /// The available funds after settling all open disputes in the client's favor: the held funds of disputed deposits
+    /// are released, while the ones of disputed withdrawals are dropped, as the withdrawals stand
+    pub(crate) fn settled_available_funds(&self) -> Money {
+        self.disputed_deposits
+            .values()
+            .fold(self.available, |available, deposit| {
+                available + deposit.held
+            })
+    }
+
+    /// The numbers of deposits under dispute and of deposits which can still be disputed
+    pub(crate) fn deposit_dispute_counts(&self) -> (usize, usize) {
+        (self.disputed_deposits.len(), self.accepted_deposits.len())
+    }
+
     pub(crate) fn available_funds(&self) -> Money {
         self.available
     }
@@ -116,6 +648,16 @@ impl AccountState {
         self.held
     }
     pub(crate) fn is_locked(&self) -> bool {
-        self.locked
+        self.lock_reason.is_some()
+    }
+    pub(crate) fn lock_reason(&self) -> Option<LockReason> {
+        self.lock_reason
+    }
+    pub(crate) fn num_transactions(&self) -> u64 {
+        self.num_transactions
+    }
+    /// Counts an accepted transaction applied to the account
+    pub(crate) fn count_transaction(&mut self) {
+        self.num_transactions += 1;
     }
 }
@@ -0,0 +1,85 @@
+//! Module defining the operations the account state machine needs from a monetary amount, so that it can be
+//! instantiated with other representations than [`Money`] (i.e., `Decimal`) in the future
+
+use core::fmt;
+
+use crate::domain::Money;
+
+/// The operations on monetary amounts the account state machine relies on: checked addition and subtraction,
+/// comparison, zero, parsing from the decimal notation of the input, and writing it (via [`Display`](fmt::Display)).
+///
+/// The engine currently works with [`Money`], which implements the trait with the behavior of the engine. A
+/// fixed-point type counting minor units (e.g., cents in an `i64`) can implement it as well.
+///
+/// # Example
+///
+/// ```
+/// use core::fmt;
+/// use tx_engine_rs::ledger::Amount;
+///
+/// #[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+/// struct Cents(i64);
+///
+/// impl fmt::Display for Cents {
+///     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+///         let sign = if self.0 < 0 { "-" } else { "" };
+///         write!(f, "{sign}{}.{:02}", (self.0 / 100).abs(), (self.0 % 100).abs())
+///     }
+/// }
+///
+/// impl Amount for Cents {
+///     const ZERO: Self = Cents(0);
+///
+///     fn checked_add(self, other: Self) -> Option<Self> {
+///         self.0.checked_add(other.0).map(Cents)
+///     }
+///
+///     fn checked_sub(self, other: Self) -> Option<Self> {
+///         self.0.checked_sub(other.0).map(Cents)
+///     }
+///
+///     // non-negative amounts with at most two decimal places only
+///     fn parse(s: &str) -> Option<Self> {
+///         let (units, cents) = s.split_once('.').unwrap_or((s, "0"));
+///         let cents: i64 = match cents.len() {
+///             1 => cents.parse::<u8>().ok()? as i64 * 10,
+///             2 => cents.parse::<u8>().ok()? as i64,
+///             _ => return None,
+///         };
+///         i64::from(units.parse::<u32>().ok()?).checked_mul(100)?.checked_add(cents).map(Cents)
+///     }
+/// }
+///
+/// let balance = Cents::parse("10.5").unwrap().checked_sub(Cents::parse("0.75").unwrap()).unwrap();
+/// assert_eq!(balance.to_string(), "9.75");
+/// assert_eq!(Cents::parse("0.755"), None);
+/// ```
+pub trait Amount: Copy + Ord + Default + fmt::Debug + fmt::Display {
+    /// The amount of zero, the balance of a new account
+    const ZERO: Self;
+
+    /// `self + other`, or `None` if the sum cannot be represented
+    fn checked_add(self, other: Self) -> Option<Self>;
+
+    /// `self - other`, or `None` if the difference cannot be represented
+    fn checked_sub(self, other: Self) -> Option<Self>;
+
+    /// Parses an amount from its decimal notation (e.g., `1.5`), or `None` if it cannot be represented exactly
+    fn parse(s: &str) -> Option<Self>;
+}
+
+impl Amount for Money {
+    const ZERO: Self = Money::ZERO;
+
+    fn checked_add(self, other: Self) -> Option<Self> {
+        Money::checked_add(self, other)
+    }
+
+    fn checked_sub(self, other: Self) -> Option<Self> {
+        Money::checked_sub(self, other)
+    }
+
+    fn parse(s: &str) -> Option<Self> {
+        Money::from_str_exact(s).ok()
+    }
+}
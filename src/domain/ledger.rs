@@ -0,0 +1,189 @@
+//! Module defining how transactions are applied to the accounts of the clients, independent of the parsing, output,
+//! and threading layers of the engine
+
+use alloc::format;
+
+use crate::domain::{
+    AccountState, ClientId, LockReason, Map, Money, Policy, ProcessingKind, Rejection,
+    TYPE_KW_CHARGEBACK, TYPE_KW_DISPUTE, TYPE_KW_RESOLVE, TYPE_KW_UNLOCK, TYPE_KW_WITHDRAWAL,
+    Transaction, TxId,
+};
+
+/// The account states of the clients
+pub(crate) type Accounts = Map<ClientId, AccountState>;
+
+/// Applies the transaction to the account of its client. Accepted deposits and opens create the account if needed;
+/// the other transactions require an existing account, unless accounts are created implicitly by the policy. A
+/// rejected transaction leaves the accounts as they are; an accepted one is counted by the account.
+pub(crate) fn apply(
+    tx: &Transaction,
+    accounts: &mut Accounts,
+    policy: &Policy,
+) -> Result<(), Rejection> {
+    let client_id = tx.client_id();
+    apply_to_account(tx, accounts, policy)?;
+    if let Some(account) = accounts.get_mut(&client_id) {
+        account.count_transaction();
+    }
+    Ok(())
+}
+
+fn apply_to_account(
+    tx: &Transaction,
+    accounts: &mut Accounts,
+    policy: &Policy,
+) -> Result<(), Rejection> {
+    let client_id = tx.client_id();
+    match tx {
+        Transaction::Deposit(deposit) => match accounts.get_mut(&client_id) {
+            Some(account) => account.deposit(*deposit, policy),
+            None => {
+                // a rejected first deposit leaves no empty account behind
+                let mut account = AccountState::default();
+                account.deposit(*deposit, policy)?;
+                accounts.insert(client_id, account);
+                Ok(())
+            }
+        },
+        Transaction::Withdrawal(withdrawal) => {
+            known_account(client_id, TYPE_KW_WITHDRAWAL, accounts, policy)?
+                .withdraw(*withdrawal, policy)
+        }
+        Transaction::Dispute(dispute) => {
+            ensure_not_foreign(client_id, dispute.disputed_tx_id(), accounts, policy)?;
+            known_account(client_id, TYPE_KW_DISPUTE, accounts, policy)?
+                .dispute(dispute.disputed_tx_id(), policy)
+        }
+        Transaction::Resolve(resolve) => {
+            ensure_not_foreign(client_id, resolve.resolved_tx_id(), accounts, policy)?;
+            known_account(client_id, TYPE_KW_RESOLVE, accounts, policy)?.resolve(
+                resolve.resolved_tx_id(),
+                resolve.amount(),
+                policy,
+            )
+        }
+        Transaction::Chargeback(chargeback) => {
+            ensure_not_foreign(client_id, chargeback.reverted_tx_id(), accounts, policy)?;
+            known_account(client_id, TYPE_KW_CHARGEBACK, accounts, policy)?.chargeback(
+                chargeback.reverted_tx_id(),
+                chargeback.amount(),
+                policy,
+            )
+        }
+        // an open of an existing account (even a locked one) leaves it as is
+        Transaction::Open(_) => {
+            accounts.entry(client_id).or_default();
+            Ok(())
+        }
+        Transaction::Unlock(_) => {
+            known_account(client_id, TYPE_KW_UNLOCK, accounts, policy)?.unlock(policy)
+        }
+    }
+}
+
+/// Rejects a dispute, resolve, or chargeback of a transaction which was applied to the account of another client
+/// (and not to the client's own account, which takes precedence if the ids are only unique per client), if enabled by
+/// the policy. The other accounts are only searched (in linear time) if the client's account does not reference the
+/// transaction.
+fn ensure_not_foreign(
+    client_id: ClientId,
+    tx_id: TxId,
+    accounts: &Accounts,
+    policy: &Policy,
+) -> Result<(), Rejection> {
+    if !policy.reject_foreign_transactions
+        || accounts
+            .get(&client_id)
+            .is_some_and(|account| account.references(tx_id))
+    {
+        return Ok(());
+    }
+    let foreign = accounts
+        .iter()
+        .any(|(owner, account)| *owner != client_id && account.references(tx_id));
+    if foreign {
+        Err(Rejection::new(
+            ProcessingKind::ForeignTransaction,
+            "transaction does not belong to client",
+        ))
+    } else {
+        Ok(())
+    }
+}
+
+/// Looks up the account of the client referenced by a transaction other than a deposit. If the client has no
+/// account, an empty one is created if configured, and the transaction is rejected otherwise.
+fn known_account<'a>(
+    client_id: ClientId,
+    tx_type: &'static str,
+    accounts: &'a mut Accounts,
+    policy: &Policy,
+) -> Result<&'a mut AccountState, Rejection> {
+    if policy.auto_create_accounts {
+        return Ok(accounts.entry(client_id).or_default());
+    }
+    accounts.get_mut(&client_id).ok_or_else(|| {
+        Rejection::new(
+            ProcessingKind::NoAccount,
+            format!("{tx_type} from a client without account"),
+        )
+    })
+}
+
+/// The account state machine on its own, for embedding it where the parsing, output, and threading layers of the
+/// engine are not available (it builds without the `std` feature). The transactions are applied one by one with the
+/// default rules, just like the engine applies them.
+///
+/// # Example
+///
+/// ```
+/// use rust_decimal_macros::dec;
+/// use tx_engine_rs::ledger::{ClientId, Deposit, Ledger, ProcessingKind, Transaction, TxId, Withdrawal};
+///
+/// let client = ClientId::new(1);
+/// let mut ledger = Ledger::new();
+/// ledger
+///     .apply(&Transaction::Deposit(Deposit::new(client, TxId::new(1), dec!(2.0)).unwrap()))
+///     .unwrap();
+///
+/// let withdrawal = Withdrawal::new(client, TxId::new(2), dec!(5.0)).unwrap();
+/// let rejection = ledger.apply(&Transaction::Withdrawal(withdrawal)).unwrap_err();
+///
+/// assert_eq!(rejection.kind, ProcessingKind::InsufficientFunds);
+/// assert_eq!(ledger.available(client), Some(dec!(2.0)));
+/// ```
+#[derive(Debug, Default)]
+pub struct Ledger {
+    accounts: Accounts,
+    policy: Policy,
+}
+
+impl Ledger {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Applies the transaction to the account of its client. A rejected transaction leaves the ledger as it is.
+    pub fn apply(&mut self, tx: &Transaction) -> Result<(), Rejection> {
+        apply(tx, &mut self.accounts, &self.policy)
+    }
+
+    /// The available funds of the client, or `None` if the client has no account
+    pub fn available(&self, client: ClientId) -> Option<Money> {
+        self.accounts
+            .get(&client)
+            .map(AccountState::available_funds)
+    }
+
+    /// The held funds of the client, or `None` if the client has no account
+    pub fn held(&self, client: ClientId) -> Option<Money> {
+        self.accounts.get(&client).map(AccountState::held_funds)
+    }
+
+    /// Why the account of the client is locked; `None` if it is not locked or the client has no account
+    pub fn lock_reason(&self, client: ClientId) -> Option<LockReason> {
+        self.accounts
+            .get(&client)
+            .and_then(AccountState::lock_reason)
+    }
+}
@@ -3,19 +3,74 @@
 use rust_decimal::Decimal;
 
 mod account;
+mod amount;
+mod ledger;
+mod policy;
 mod transaction;
 
+#[cfg(test)]
+mod tests;
+
 pub(crate) use account::AccountState;
-pub(crate) use transaction::{Chargeback, Deposit, Dispute, Resolve, Transaction, Withdrawal};
+pub use account::{LockReason, ProcessingKind, Rejection};
+pub use amount::Amount;
+pub use ledger::Ledger;
+pub(crate) use ledger::{Accounts, apply};
+pub use policy::DisputePolicy;
+pub(crate) use policy::Policy;
+// public for the `model` feature and the `ledger` module, which re-export the transactions
+pub use transaction::{
+    Chargeback, Deposit, Dispute, Open, Resolve, Transaction, Unlock, Withdrawal,
+};
+pub(crate) use transaction::{
+    TYPE_KW_CHARGEBACK, TYPE_KW_DEPOSIT, TYPE_KW_DISPUTE, TYPE_KW_OPEN, TYPE_KW_RESOLVE,
+    TYPE_KW_UNLOCK, TYPE_KW_WITHDRAWAL,
+};
+
+// The state machine only relies on the operations common to the hashed and the ordered collections, so that it builds
+// without `std` (which provides the hashed ones)
+#[cfg(not(feature = "std"))]
+pub(crate) use alloc::collections::{BTreeMap as Map, BTreeSet as Set};
+#[cfg(feature = "std")]
+pub(crate) use std::collections::{HashMap as Map, HashSet as Set};
 
-pub(crate) type Money = Decimal;
+/// Monetary amounts, with up to four decimal places in the input
+pub type Money = Decimal;
 
 /// Id identifying the client issuing the transaction.
-#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
-pub(crate) struct ClientId(u16);
+///
+/// The id can be used to key data structures by the same type as the engine, e.g., to look up the output records of
+/// an account. Its representation is not exposed; it is constructed from and converted into a `u16` only.
+///
+/// # Example
+///
+/// ```
+/// use std::collections::BTreeMap;
+/// use tx_engine_rs::ClientId;
+///
+/// let mut balances = BTreeMap::new();
+/// balances.insert(ClientId::new(2), "2.0");
+/// balances.insert(ClientId::from(1), "1.0");
+///
+/// let clients: Vec<u16> = balances.keys().map(|&client| client.into()).collect();
+/// assert_eq!(clients, vec![1, 2]);
+/// ```
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy)]
+#[cfg_attr(
+    feature = "snapshot",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(transparent)
+)]
+pub struct ClientId(u16);
 
 impl ClientId {
-    pub(crate) fn new(id: u16) -> Self {
+    pub fn new(id: u16) -> Self {
+        Self(id)
+    }
+}
+
+impl From<u16> for ClientId {
+    fn from(id: u16) -> Self {
         Self(id)
     }
 }
@@ -27,16 +82,39 @@ impl From<ClientId> for u16 {
 }
 
 /// The unique ID of a transaction. Used to reference transactions for disputes, resolves, and chargebacks
-#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
-pub(crate) struct TxId(u32);
+///
+/// Like [`ClientId`], it is constructed from and converted into its integer (`u64`) only.
+///
+/// # Example
+///
+/// ```
+/// use tx_engine_rs::TxId;
+///
+/// let tx = TxId::new(7);
+/// assert!(tx < TxId::from(8));
+/// assert_eq!(u64::from(tx), 7);
+/// ```
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Hash)]
+#[cfg_attr(
+    feature = "snapshot",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(transparent)
+)]
+pub struct TxId(u64);
 
 impl TxId {
-    pub(crate) fn new(id: u32) -> Self {
+    pub fn new(id: u64) -> Self {
+        Self(id)
+    }
+}
+
+impl From<u64> for TxId {
+    fn from(id: u64) -> Self {
         Self(id)
     }
 }
 
-impl From<TxId> for u32 {
+impl From<TxId> for u64 {
     fn from(value: TxId) -> Self {
         value.0
     }
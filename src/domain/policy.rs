@@ -0,0 +1,48 @@
+//! Module defining the configurable rules applied when transactions are processed against an account
+
+use crate::domain::Money;
+
+/// The rules an account applies when processing transactions. The default reproduces the standard behavior.
+#[derive(Debug, Default, Clone, Copy)]
+pub(crate) struct Policy {
+    /// Whether withdrawals can be disputed, in addition to deposits
+    pub(crate) dispute_withdrawals: bool,
+    /// How far a withdrawal may push the available funds below zero
+    pub(crate) overdraft_limit: Money,
+    /// How a dispute of a deposit whose funds were partly withdrawn is handled
+    pub(crate) dispute_policy: DisputePolicy,
+    /// How many undisputed deposits are retained per account for disputes; unbounded if `None`
+    pub(crate) max_disputable_deposits: Option<usize>,
+    /// Whether transactions referencing a client without account implicitly create an empty account
+    pub(crate) auto_create_accounts: bool,
+    /// Whether the balance invariants are checked after every applied transaction
+    pub(crate) check_invariants: bool,
+    /// The ceiling of the total funds (`available + held`) a deposit may lead to; unbounded if `None`
+    pub(crate) max_balance: Option<Money>,
+    /// Whether a deposit exceeding the ceiling is credited up to the ceiling instead of being rejected
+    pub(crate) clamp_to_max_balance: bool,
+    /// Whether `unlock` transactions lift the lock of an account
+    pub(crate) allow_admin_unlock: bool,
+    /// Whether a transaction whose dispute was resolved is final, i.e., cannot be disputed again
+    pub(crate) forbid_redispute: bool,
+    /// Whether a retried dispute, resolve, or chargeback (of a transaction already in the resulting state) is accepted
+    /// as a no-op instead of being rejected
+    pub(crate) idempotent_disputes: bool,
+    /// Whether a dispute, resolve, or chargeback of a transaction of another client is rejected as such, instead of as
+    /// an unknown transaction
+    pub(crate) reject_foreign_transactions: bool,
+    /// The scale the balances are rescaled to after every mutation; kept as computed if `None`
+    pub(crate) normalize_scale: Option<u32>,
+}
+
+/// How a dispute is handled if the available funds are lower than the disputed deposit, i.e., if (some of) its
+/// funds were already withdrawn
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum DisputePolicy {
+    /// The dispute is rejected ([`DisputeFundsWithdrawn`](crate::ProcessingKind::DisputeFundsWithdrawn))
+    #[default]
+    StrictFullHold,
+    /// The remaining available funds are held (none if they are not positive). A later resolve releases, and a
+    /// chargeback reverses, only the held part.
+    PartialHold,
+}
@@ -0,0 +1,64 @@
+use super::*;
+use rust_decimal_macros::dec;
+
+#[test]
+fn consistent_account_satisfies_invariants() {
+    let account = AccountState::new(dec!(0), dec!(0), false);
+    assert!(account.check_invariants(&Policy::default()).is_ok());
+}
+
+#[test]
+fn negative_available_funds_violate_invariants() {
+    let account = AccountState::new(dec!(-0.0001), dec!(1.0), false);
+    let rejection = account
+        .check_invariants(&Policy::default())
+        .expect_err("negative available funds are detected");
+    assert_eq!(rejection.kind, crate::ProcessingKind::InvariantViolation);
+}
+
+#[test]
+fn available_funds_within_overdraft_limit_satisfy_invariants() {
+    let policy = Policy {
+        overdraft_limit: dec!(5.0),
+        ..Policy::default()
+    };
+    assert!(
+        AccountState::new(dec!(-5.0), dec!(0), false)
+            .check_invariants(&policy)
+            .is_ok()
+    );
+    assert!(
+        AccountState::new(dec!(-5.0001), dec!(0), false)
+            .check_invariants(&policy)
+            .is_err()
+    );
+}
+
+#[test]
+fn negative_held_funds_violate_invariants() {
+    let account = AccountState::new(dec!(1.0), dec!(-1.0), false);
+    assert!(account.check_invariants(&Policy::default()).is_err());
+}
+
+/// Sums up the parsed amounts generically, as the state machine would with an abstracted amount type
+fn parsed_sum<M: Amount>(amounts: &[&str]) -> Option<M> {
+    amounts
+        .iter()
+        .try_fold(M::ZERO, |sum, amount| sum.checked_add(M::parse(amount)?))
+}
+
+#[test]
+fn money_implements_amount_like_decimal_arithmetic() {
+    assert_eq!(
+        parsed_sum::<Money>(&["1.5", "2.25", "0.0001"]),
+        Some(dec!(3.7501))
+    );
+    assert_eq!(parsed_sum::<Money>(&["1.5", "x"]), None);
+    assert_eq!(<Money as Amount>::ZERO, Money::ZERO);
+    assert_eq!(Amount::checked_sub(dec!(1.0), dec!(2.5)), Some(dec!(-1.5)));
+    assert_eq!(Amount::checked_add(Money::MAX, dec!(1)), None);
+    assert_eq!(
+        <Money as Amount>::parse("2.50").unwrap().to_string(),
+        "2.50"
+    );
+}
@@ -1,40 +1,72 @@
 //! Module defining the domain types related to the representation of the transactions handled by the engine
 
+use alloc::string::{String, ToString};
+
 use rust_decimal::Decimal;
 
 use crate::domain::{ClientId, Money, TxId};
 
+// the keywords of the transaction types in the input, also used to name them in messages
+pub(crate) const TYPE_KW_DEPOSIT: &str = "deposit";
+pub(crate) const TYPE_KW_WITHDRAWAL: &str = "withdrawal";
+pub(crate) const TYPE_KW_DISPUTE: &str = "dispute";
+pub(crate) const TYPE_KW_RESOLVE: &str = "resolve";
+pub(crate) const TYPE_KW_CHARGEBACK: &str = "chargeback";
+pub(crate) const TYPE_KW_OPEN: &str = "open";
+pub(crate) const TYPE_KW_UNLOCK: &str = "unlock";
+
 /// Transactions are the orders provided to the engine.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub(crate) enum Transaction {
+pub enum Transaction {
     Deposit(Deposit),
     Withdrawal(Withdrawal),
     Dispute(Dispute),
     Resolve(Resolve),
     Chargeback(Chargeback),
+    Open(Open),
+    Unlock(Unlock),
 }
 
 impl Transaction {
-    pub(crate) fn client_id(&self) -> ClientId {
+    pub fn client_id(&self) -> ClientId {
         match self {
             Transaction::Deposit(d) => d.client_id(),
             Transaction::Withdrawal(w) => w.client_id(),
             Transaction::Dispute(d) => d.client_id(),
             Transaction::Resolve(r) => r.client_id(),
             Transaction::Chargeback(c) => c.client_id(),
+            Transaction::Open(o) => o.client_id(),
+            Transaction::Unlock(u) => u.client_id(),
+        }
+    }
+
+    /// The id of the transaction, or of the referenced transaction for disputes, resolves, and chargebacks
+    pub fn tx_id(&self) -> TxId {
+        match self {
+            Transaction::Deposit(d) => d.tx_id(),
+            Transaction::Withdrawal(w) => w.tx_id(),
+            Transaction::Dispute(d) => d.disputed_tx_id(),
+            Transaction::Resolve(r) => r.resolved_tx_id(),
+            Transaction::Chargeback(c) => c.reverted_tx_id(),
+            Transaction::Open(o) => o.tx_id(),
+            Transaction::Unlock(u) => u.tx_id(),
         }
     }
 }
 
+/// A withdrawal of funds from the client's account
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub(crate) struct Withdrawal {
+pub struct Withdrawal {
     client_id: ClientId,
     tx_id: TxId,
     amount: Money,
+    // a reversal of a prior credit, which must not overdraw the account
+    reversal: bool,
 }
 
 impl Withdrawal {
-    pub(crate) fn new(client_id: ClientId, tx_id: TxId, amount: Money) -> Result<Self, String> {
+    /// Fails if the amount is not positive
+    pub fn new(client_id: ClientId, tx_id: TxId, amount: Money) -> Result<Self, String> {
         if amount <= Decimal::ZERO {
             return Err("the withdrawn amount must be positive".to_string());
         }
@@ -42,30 +74,59 @@ impl Withdrawal {
             client_id,
             tx_id,
             amount,
+            reversal: false,
         })
     }
 
-    pub(crate) fn client_id(&self) -> ClientId {
+    /// The withdrawal as the reversal of a prior credit (see
+    /// [`negative_as_reversal`](crate::ProcessConfig::negative_as_reversal)), which may not take the available funds
+    /// below zero, regardless of the overdraft limit
+    pub(crate) fn into_reversal(self) -> Self {
+        Self {
+            reversal: true,
+            ..self
+        }
+    }
+
+    /// A balance-neutral withdrawal of zero, only accepted if configured via
+    /// [`allow_zero_amount`](crate::ProcessConfig::allow_zero_amount)
+    pub(crate) fn zero(client_id: ClientId, tx_id: TxId) -> Self {
+        Self {
+            client_id,
+            tx_id,
+            amount: Decimal::ZERO,
+            reversal: false,
+        }
+    }
+
+    pub fn client_id(&self) -> ClientId {
         self.client_id
     }
 
-    pub(crate) fn tx_id(&self) -> TxId {
+    pub fn tx_id(&self) -> TxId {
         self.tx_id
     }
 
-    pub(crate) fn amount(&self) -> Money {
+    pub fn amount(&self) -> Money {
         self.amount
     }
+
+    pub(crate) fn is_reversal(&self) -> bool {
+        self.reversal
+    }
 }
+
+/// A deposit of funds to the client's account
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub(crate) struct Deposit {
+pub struct Deposit {
     client_id: ClientId,
     tx_id: TxId,
     amount: Money,
 }
 
 impl Deposit {
-    pub(crate) fn new(client_id: ClientId, tx_id: TxId, amount: Money) -> Result<Self, String> {
+    /// Fails if the amount is not positive
+    pub fn new(client_id: ClientId, tx_id: TxId, amount: Money) -> Result<Self, String> {
         if amount <= Decimal::ZERO {
             return Err("the deposited amount must be positive".to_string());
         }
@@ -76,84 +137,180 @@ impl Deposit {
         })
     }
 
-    pub(crate) fn client_id(&self) -> ClientId {
+    /// A balance-neutral deposit of zero (e.g., a "ping" keeping the account active), only accepted if configured via
+    /// [`allow_zero_amount`](crate::ProcessConfig::allow_zero_amount)
+    pub(crate) fn zero(client_id: ClientId, tx_id: TxId) -> Self {
+        Self {
+            client_id,
+            tx_id,
+            amount: Decimal::ZERO,
+        }
+    }
+
+    pub fn client_id(&self) -> ClientId {
         self.client_id
     }
 
-    pub(crate) fn tx_id(&self) -> TxId {
+    pub fn tx_id(&self) -> TxId {
         self.tx_id
     }
 
-    pub(crate) fn amount(&self) -> Money {
+    pub fn amount(&self) -> Money {
         self.amount
     }
 }
 
+/// A claim that a transaction of the client was erroneous, holding its funds until the dispute is settled
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub(crate) struct Dispute {
+pub struct Dispute {
     client_id: ClientId,
     disputed_tx: TxId,
 }
 
 impl Dispute {
-    pub(crate) fn new(client_id: ClientId, disputed_tx: TxId) -> Self {
+    pub fn new(client_id: ClientId, disputed_tx: TxId) -> Self {
         Self {
             client_id,
             disputed_tx,
         }
     }
 
-    pub(crate) fn client_id(&self) -> ClientId {
+    pub fn client_id(&self) -> ClientId {
         self.client_id
     }
 
-    pub(crate) fn disputed_tx_id(&self) -> TxId {
+    pub fn disputed_tx_id(&self) -> TxId {
         self.disputed_tx
     }
 }
 
+/// Settles a dispute in favor of the disputed transaction, releasing the held funds. A partial resolve releases only
+/// the given part of them; the dispute stays open with the remainder held.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub(crate) struct Resolve {
+pub struct Resolve {
     client_id: ClientId,
     resolved_tx: TxId,
+    amount: Option<Money>,
 }
 
 impl Resolve {
-    pub(crate) fn new(client_id: ClientId, resolved_tx: TxId) -> Self {
+    pub fn new(client_id: ClientId, resolved_tx: TxId) -> Self {
         Self {
             client_id,
             resolved_tx,
+            amount: None,
         }
     }
 
-    pub(crate) fn client_id(&self) -> ClientId {
+    /// A resolve releasing only the given part of the held funds. Fails if the amount is not positive.
+    pub fn partial(client_id: ClientId, resolved_tx: TxId, amount: Money) -> Result<Self, String> {
+        if amount <= Decimal::ZERO {
+            return Err("the resolved amount must be positive".to_string());
+        }
+        Ok(Self {
+            client_id,
+            resolved_tx,
+            amount: Some(amount),
+        })
+    }
+
+    pub fn client_id(&self) -> ClientId {
         self.client_id
     }
 
-    pub(crate) fn resolved_tx_id(&self) -> TxId {
+    pub fn resolved_tx_id(&self) -> TxId {
         self.resolved_tx
     }
+
+    /// The part of the held funds released by a partial resolve; `None` if all of them are released
+    pub fn amount(&self) -> Option<Money> {
+        self.amount
+    }
 }
 
+/// Settles a dispute by reversing the disputed transaction, which locks the client's account. A partial chargeback
+/// reverses only the given part of the held funds; the dispute stays open with the remainder held.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub(crate) struct Chargeback {
+pub struct Chargeback {
     client_id: ClientId,
     reverted_tx: TxId,
+    amount: Option<Money>,
 }
 
 impl Chargeback {
-    pub(crate) fn new(client_id: ClientId, reverted_tx: TxId) -> Self {
+    pub fn new(client_id: ClientId, reverted_tx: TxId) -> Self {
         Self {
             client_id,
             reverted_tx,
+            amount: None,
         }
     }
 
-    pub(crate) fn client_id(&self) -> ClientId {
+    /// A chargeback reversing only the given part of the held funds. Fails if the amount is not positive.
+    pub fn partial(client_id: ClientId, reverted_tx: TxId, amount: Money) -> Result<Self, String> {
+        if amount <= Decimal::ZERO {
+            return Err("the charged back amount must be positive".to_string());
+        }
+        Ok(Self {
+            client_id,
+            reverted_tx,
+            amount: Some(amount),
+        })
+    }
+
+    pub fn client_id(&self) -> ClientId {
         self.client_id
     }
 
-    pub(crate) fn reverted_tx_id(&self) -> TxId {
+    pub fn reverted_tx_id(&self) -> TxId {
         self.reverted_tx
     }
+
+    /// The part of the held funds reversed by a partial chargeback; `None` if all of them are reversed
+    pub fn amount(&self) -> Option<Money> {
+        self.amount
+    }
+}
+
+/// Explicitly opens an account with a zero balance, e.g., for upstreams registering clients before their first deposit
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Open {
+    client_id: ClientId,
+    tx_id: TxId,
+}
+
+impl Open {
+    pub fn new(client_id: ClientId, tx_id: TxId) -> Self {
+        Self { client_id, tx_id }
+    }
+
+    pub fn client_id(&self) -> ClientId {
+        self.client_id
+    }
+
+    pub fn tx_id(&self) -> TxId {
+        self.tx_id
+    }
+}
+
+/// Lifts the lock of an account, e.g., to reinstate an account locked by a fraudulent chargeback. Privileged, and
+/// therefore only applied if enabled via [`allow_admin_unlock`](crate::ProcessConfig::allow_admin_unlock).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Unlock {
+    client_id: ClientId,
+    tx_id: TxId,
+}
+
+impl Unlock {
+    pub fn new(client_id: ClientId, tx_id: TxId) -> Self {
+        Self { client_id, tx_id }
+    }
+
+    pub fn client_id(&self) -> ClientId {
+        self.client_id
+    }
+
+    pub fn tx_id(&self) -> TxId {
+        self.tx_id
+    }
 }
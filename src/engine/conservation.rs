@@ -0,0 +1,102 @@
+//! Module focused on verifying that the funds are conserved by a run: the accounts must hold exactly the funds which
+//! the applied transactions moved into and out of them.
+
+use crate::{
+    Error,
+    domain::{AccountState, Accounts, Money, Policy, Transaction},
+};
+
+/// The funds moved into and out of the accounts by the applied transactions
+#[derive(Debug, Default, Clone, Copy)]
+pub(crate) struct Flows {
+    deposits: Money,
+    withdrawals: Money,
+    /// Negative for chargebacks of withdrawals, which return the withdrawn funds
+    chargebacks: Money,
+    /// The funds held by disputes of withdrawals, which are held in addition to the available funds (as the
+    /// withdrawn funds already left the account); negative for the releases by resolves and chargebacks
+    withdrawal_holds: Money,
+}
+
+impl Flows {
+    /// The funds the transaction moves if it is applied, given the accounts before it is applied. Opens and unlocks
+    /// move no funds into or out of an account, and neither do disputes and resolves, except for the holds of
+    /// disputed withdrawals.
+    pub(crate) fn of(tx: &Transaction, accounts: &Accounts, policy: &Policy) -> Self {
+        let account = accounts.get(&tx.client_id());
+        match tx {
+            Transaction::Deposit(deposit) => Self {
+                // a deposit creates the account if needed
+                deposits: account
+                    .unwrap_or(&AccountState::default())
+                    .credited_amount(deposit.amount(), policy),
+                ..Self::default()
+            },
+            Transaction::Withdrawal(withdrawal) => Self {
+                withdrawals: withdrawal.amount(),
+                ..Self::default()
+            },
+            Transaction::Dispute(dispute) => Self {
+                withdrawal_holds: account.map_or(Money::ZERO, |account| {
+                    account.disputable_withdrawal_funds(dispute.disputed_tx_id())
+                }),
+                ..Self::default()
+            },
+            Transaction::Resolve(resolve) => Self {
+                withdrawal_holds: account.map_or(Money::ZERO, |account| {
+                    -account.disputed_withdrawal_funds(resolve.resolved_tx_id())
+                }),
+                ..Self::default()
+            },
+            Transaction::Chargeback(chargeback) => {
+                account.map_or(Self::default(), |account| Self {
+                    chargebacks: account
+                        .charged_back_funds(chargeback.reverted_tx_id(), chargeback.amount()),
+                    withdrawal_holds: -account
+                        .disputed_withdrawal_funds(chargeback.reverted_tx_id()),
+                    ..Self::default()
+                })
+            }
+            Transaction::Open(_) | Transaction::Unlock(_) => Self::default(),
+        }
+    }
+
+    pub(crate) fn add(&mut self, other: Flows) {
+        self.deposits = self.deposits.saturating_add(other.deposits);
+        self.withdrawals = self.withdrawals.saturating_add(other.withdrawals);
+        self.chargebacks = self.chargebacks.saturating_add(other.chargebacks);
+        self.withdrawal_holds = self.withdrawal_holds.saturating_add(other.withdrawal_holds);
+    }
+
+    /// The total funds the accounts must hold: deposits minus withdrawals minus chargebacks, plus the funds held by
+    /// the open disputes of withdrawals
+    fn expected_total(&self) -> Money {
+        self.deposits
+            .saturating_sub(self.withdrawals)
+            .saturating_sub(self.chargebacks)
+            .saturating_add(self.withdrawal_holds)
+    }
+}
+
+/// Compares the total funds (`available + held`) of the accounts, possibly split across several partitions, to the
+/// funds moved by the transactions applied to them.
+pub(crate) fn verify<'a>(
+    flows: Flows,
+    partitions: impl IntoIterator<Item = &'a Accounts>,
+) -> Result<(), Error> {
+    let expected = flows.expected_total();
+    let actual = partitions
+        .into_iter()
+        .flat_map(|accounts| accounts.values())
+        .fold(Money::ZERO, |total, account| {
+            total
+                .saturating_add(account.available_funds())
+                .saturating_add(account.held_funds())
+        });
+    if actual == expected {
+        Ok(())
+    } else {
+        tracing::error!("funds not conserved: the accounts total {actual}, expected {expected}");
+        Err(Error::NotConserved { expected, actual })
+    }
+}
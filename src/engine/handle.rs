@@ -0,0 +1,150 @@
+//! Module defining a stateful engine handle which allows querying the account states while transactions are applied
+
+use std::io::Read;
+
+use rust_decimal::Decimal;
+
+use crate::{
+    AccountRecord, Error, ProcessConfig, TransactionRecord,
+    domain::{AccountState, ClientId},
+    engine::{
+        Accounts, EngineSnapshot, continuing, logic::handle_transaction, orchestration::apply,
+    },
+    input::{parse_transactions, validate_record},
+    telemetry,
+};
+
+/// A sequential engine owning the account states, which can be fed transaction by transaction (or batch by batch)
+/// and queried in between.
+///
+/// Where [`process()`](crate::process) only yields the account records once the whole input is consumed, the
+/// `TransactionEngine` makes the intermediate state observable, e.g., for monitoring the balances mid-stream.
+/// [`ProcessConfig::workers`] has no effect here.
+///
+/// # Example
+///
+/// ```
+/// use rust_decimal_macros::dec;
+/// use tx_engine_rs::{ProcessConfig, TransactionEngine, TransactionRecord};
+///
+/// let mut engine = TransactionEngine::new(ProcessConfig::default());
+///
+/// engine.feed(TransactionRecord::Deposit { client: 42, tx: 1, amount: dec!(10.0) }).unwrap();
+/// assert_eq!(engine.available(42), Some(dec!(10.0)));
+///
+/// let batch = "type, client, tx, amount\ndispute, 42, 1,\n";
+/// engine.feed_reader(batch.as_bytes(), |_| {}, |_| {});
+/// assert_eq!(engine.available(42), Some(dec!(0)));
+/// assert_eq!(engine.held(42), Some(dec!(10.0)));
+/// ```
+pub struct TransactionEngine<'a> {
+    accounts: Accounts,
+    // number of transactions fed so far, recorded in the snapshots
+    processed: u64,
+    config: ProcessConfig<'a>,
+}
+
+impl<'a> TransactionEngine<'a> {
+    /// Creates an engine without any accounts, applying the transactions as configured.
+    pub fn new(config: ProcessConfig<'a>) -> Self {
+        Self {
+            accounts: Accounts::default(),
+            processed: 0,
+            config,
+        }
+    }
+
+    /// Creates an engine starting from the account states of the snapshot. See [`EngineSnapshot`] on how to
+    /// continue with the input without applying transactions twice.
+    pub fn resume(snapshot: EngineSnapshot, config: ProcessConfig<'a>) -> Self {
+        Self {
+            accounts: snapshot.accounts,
+            processed: snapshot.processed,
+            config,
+        }
+    }
+
+    /// Takes a snapshot of the current account states, from which the processing can be resumed.
+    pub fn checkpoint(&self) -> EngineSnapshot {
+        EngineSnapshot {
+            accounts: self.accounts.clone(),
+            processed: self.processed,
+        }
+    }
+
+    /// Validates and applies a single transaction.
+    pub fn feed(&mut self, tx: TransactionRecord) -> Result<(), Error> {
+        self.apply(tx).map(|_| ())
+    }
+
+    /// Validates and applies a single transaction, returning it as applied (as it would be reported to the
+    /// `on_success` callback of [`process()`](crate::process)), e.g., for echoing it in an interactive session
+    /// together with the resulting [`snapshot`](Self::snapshot) of the account.
+    pub fn apply(&mut self, tx: TransactionRecord) -> Result<TransactionRecord, Error> {
+        self.processed += 1;
+        let result = validate_record(tx, &self.config.parse).and_then(|tx| {
+            handle_transaction(&tx, &mut self.accounts, None, &self.config)
+                .map(|()| TransactionRecord::from_domain(&tx))
+        });
+        match &result {
+            Ok(_) => telemetry::record_accepted(),
+            Err(err) => telemetry::record_rejected(err),
+        }
+        result
+    }
+
+    /// Applies the CSV-encoded transactions provided by the reader, which are parsed as configured. The callbacks
+    /// are invoked as in [`process()`](crate::process).
+    pub fn feed_reader(
+        &mut self,
+        reader: impl Read,
+        on_error: impl FnMut(Error),
+        on_success: impl FnMut(TransactionRecord),
+    ) {
+        let mut on_error = continuing(on_error);
+        let mut on_success = continuing(on_success);
+        for result in parse_transactions(reader, &self.config.parse) {
+            self.processed += 1;
+            let _ = apply(
+                result,
+                &mut self.accounts,
+                None,
+                &mut on_error,
+                &mut on_success,
+                &self.config,
+            );
+        }
+    }
+
+    /// The funds currently available to the client, or `None` if the client has no account (yet).
+    pub fn available(&self, client: u16) -> Option<Decimal> {
+        self.account(client).map(AccountState::available_funds)
+    }
+
+    /// The funds currently held for disputes of the client, or `None` if the client has no account (yet).
+    pub fn held(&self, client: u16) -> Option<Decimal> {
+        self.account(client).map(AccountState::held_funds)
+    }
+
+    /// Whether the account of the client is currently locked, or `None` if the client has no account (yet).
+    pub fn is_locked(&self, client: u16) -> Option<bool> {
+        self.account(client).map(AccountState::is_locked)
+    }
+
+    /// A snapshot of the current state of the client's account, or `None` if the client has no account (yet).
+    pub fn snapshot(&self, client: u16) -> Option<AccountRecord> {
+        let client_id = ClientId::new(client);
+        self.accounts
+            .get(&client_id)
+            .map(|account| AccountRecord::new(client_id, account))
+    }
+
+    /// Consumes the engine and returns the final account records, applying the configured settlement and rounding.
+    pub fn into_records(self) -> impl Iterator<Item = AccountRecord> + 'a {
+        crate::to_output(self.accounts, self.config)
+    }
+
+    fn account(&self, client: u16) -> Option<&AccountState> {
+        self.accounts.get(&ClientId::new(client))
+    }
+}
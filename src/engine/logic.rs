@@ -1,92 +1,161 @@
 //! Module focused on the logic of processing individual transactions.
 
 use crate::{
-    Error,
-    domain::{
-        AccountState, Chargeback, ClientId, Deposit, Dispute, Resolve, Transaction, TxId,
-        Withdrawal,
-    },
-    engine::Accounts,
-    error::processing_error,
-    input::{TYPE_KW_CHARGEBACK, TYPE_KW_DISPUTE, TYPE_KW_RESOLVE},
+    AccountRecord, Error, FundsDirection, FundsMoved, Mutation, ProcessConfig, TransactionRecord,
+    Warning,
+    config::fire,
+    domain::{self, Accounts, ClientId, Money, Transaction},
+    engine::conservation::Flows,
+    error::rejection_error,
+    telemetry,
 };
 
-pub(super) fn handle_transaction(tx: &Transaction, accounts: &mut Accounts) -> Result<(), Error> {
-    match tx {
-        Transaction::Deposit(deposit) => handle_deposit(deposit, accounts),
-        Transaction::Withdrawal(withdrawal) => handle_withdrawal(withdrawal, accounts),
-        Transaction::Dispute(dispute) => handle_dispute(dispute, accounts),
-        Transaction::Resolve(resolve) => handle_resolve(resolve, accounts),
-        Transaction::Chargeback(chargeback) => handle_chargeback(chargeback, accounts),
-    }
-}
+/// Applies the transaction to the accounts, adding the funds it moved to `flows` if given (i.e., if the conservation
+/// of the funds is verified).
+pub(super) fn handle_transaction(
+    tx: &Transaction,
+    accounts: &mut Accounts,
+    flows: Option<&mut Flows>,
+    config: &ProcessConfig<'_>,
+) -> Result<(), Error> {
+    // only captured if reported, so that the mutation log and the fund movements cost nothing by default
+    let hooks = &config.hooks;
+    let before = (hooks.on_mutation.is_some() || hooks.on_funds_moved.is_some())
+        .then(|| balances(tx.client_id(), accounts));
+    let moved = flows.map(|flows| (Flows::of(tx, accounts, &config.policy), flows));
 
-fn handle_deposit(deposit: &Deposit, accounts: &mut Accounts) -> Result<(), Error> {
-    let client_id = deposit.client_id();
-    let tx_id = deposit.tx_id();
+    apply_transaction(tx, accounts, config)?;
+    if let Some((moved, flows)) = moved {
+        flows.add(moved);
+    }
 
-    let account = accounts.entry(deposit.client_id()).or_default();
-    account
-        .deposit(*deposit)
-        .map_err(|msg| processing_error(client_id, tx_id, msg))
+    if let Some(before) = before {
+        let after = balances(tx.client_id(), accounts);
+        fire(&hooks.on_mutation, || Mutation {
+            client: tx.client_id().into(),
+            transaction: TransactionRecord::from_domain(tx),
+            before,
+            after,
+        });
+        if let Some(event) = funds_moved(tx, before, after) {
+            fire(&hooks.on_funds_moved, || event);
+        }
+    }
+    if hooks.on_warning.is_some()
+        && let Some(warning) = warning(tx, accounts)
+    {
+        fire(&hooks.on_warning, || warning);
+    }
+    Ok(())
 }
 
-fn handle_withdrawal(withdrawal: &Withdrawal, accounts: &mut Accounts) -> Result<(), Error> {
-    let client_id = withdrawal.client_id();
-    let tx_id = withdrawal.tx_id();
+/// The suspicious pattern in the client's transactions which holds after the applied transaction, if any
+fn warning(tx: &Transaction, accounts: &Accounts) -> Option<Warning> {
+    if !matches!(tx, Transaction::Dispute(_)) {
+        return None;
+    }
+    let (disputed, undisputed) = accounts.get(&tx.client_id())?.deposit_dispute_counts();
+    (undisputed == 0 && disputed >= Warning::RAPID_DISPUTE_THRESHOLD).then(|| {
+        Warning::RapidDisputeRate {
+            client: tx.client_id().into(),
+            count: disputed,
+        }
+    })
+}
 
-    let Some(account) = accounts.get_mut(&client_id) else {
-        return Err(processing_error(
-            client_id,
-            tx_id,
-            "withdrawal from a client without account",
-        ));
+/// The movement of funds between the balances by a dispute or resolve, given the `(available, held)` balances before
+/// and after it. Funds moved only if one balance changed by the negated change of the other, which is not the case
+/// for disputes and resolves of withdrawals.
+fn funds_moved(
+    tx: &Transaction,
+    (available_before, held_before): (Money, Money),
+    (available_after, held_after): (Money, Money),
+) -> Option<FundsMoved> {
+    let (tx_id, direction) = match tx {
+        Transaction::Dispute(dispute) => {
+            (dispute.disputed_tx_id(), FundsDirection::AvailableToHeld)
+        }
+        Transaction::Resolve(resolve) => {
+            (resolve.resolved_tx_id(), FundsDirection::HeldToAvailable)
+        }
+        _ => return None,
     };
-
-    account
-        .withdraw(withdrawal.amount())
-        .map_err(|msg| processing_error(client_id, tx_id, msg))
+    let held = held_after - held_before;
+    let amount = held.abs();
+    (!held.is_zero() && available_after - available_before == -held).then(|| FundsMoved {
+        client: tx.client_id().into(),
+        tx: tx_id.into(),
+        direction,
+        amount,
+    })
 }
 
-fn handle_dispute(dispute: &Dispute, accounts: &mut Accounts) -> Result<(), Error> {
-    let client_id = dispute.client_id();
-    let disputed_tx = dispute.disputed_tx_id();
-
-    let account = ensure_client_is_known(client_id, disputed_tx, TYPE_KW_DISPUTE, accounts)?;
-    account
-        .dispute(disputed_tx)
-        .map_err(|msg| processing_error(client_id, disputed_tx, msg))
+/// The `(available, held)` balances of the client's account; zero if the client has no account (yet)
+fn balances(client_id: ClientId, accounts: &Accounts) -> (Money, Money) {
+    accounts
+        .get(&client_id)
+        .map_or((Money::ZERO, Money::ZERO), |account| {
+            (account.available_funds(), account.held_funds())
+        })
 }
 
-fn handle_resolve(resolve: &Resolve, accounts: &mut Accounts) -> Result<(), Error> {
-    let client_id = resolve.client_id();
-    let resolved_tx = resolve.resolved_tx_id();
+fn apply_transaction(
+    tx: &Transaction,
+    accounts: &mut Accounts,
+    config: &ProcessConfig<'_>,
+) -> Result<(), Error> {
+    let client_id = tx.client_id();
+    let is_locked = |accounts: &Accounts| {
+        accounts
+            .get(&client_id)
+            .is_some_and(|account| account.is_locked())
+    };
+    let was_locked = is_locked(accounts);
+    // the state to roll back to if the transaction violates the invariants; only kept if they are checked
+    let previous = config
+        .policy
+        .check_invariants
+        .then(|| accounts.get(&client_id).cloned());
+    domain::apply(tx, accounts, &config.policy)
+        .map_err(|rejection| rejection_error(client_id, tx.tx_id(), rejection))?;
+    if let Some(previous) = previous
+        && let Err(error) = ensure_invariants(tx, accounts, config)
+    {
+        match previous {
+            Some(account) => accounts.insert(client_id, account),
+            None => accounts.remove(&client_id),
+        };
+        return Err(error);
+    }
 
-    let account = ensure_client_is_known(client_id, resolved_tx, TYPE_KW_RESOLVE, accounts)?;
-    account
-        .resolve(resolved_tx)
-        .map_err(|msg| processing_error(client_id, resolved_tx, msg))
+    // a successful chargeback locks the account, unless it is an idempotent retry
+    if matches!(tx, Transaction::Chargeback(_)) && !was_locked && is_locked(accounts) {
+        telemetry::record_locked();
+        fire(&config.hooks.on_locked_account, || {
+            AccountRecord::new(client_id, &accounts[&client_id])
+        });
+    }
+    Ok(())
 }
 
-fn handle_chargeback(chargeback: &Chargeback, accounts: &mut Accounts) -> Result<(), Error> {
-    let client_id = chargeback.client_id();
-    let reverted_tx = chargeback.reverted_tx_id();
+/// Checks the invariants of the account affected by the applied transaction. A violation panics in debug builds and
+/// is reported as an error in release builds, in which case the caller rolls the transaction back.
+fn ensure_invariants(
+    tx: &Transaction,
+    accounts: &Accounts,
+    config: &ProcessConfig<'_>,
+) -> Result<(), Error> {
+    let client_id = tx.client_id();
+    let Some(account) = accounts.get(&client_id) else {
+        return Ok(());
+    };
 
-    let account = ensure_client_is_known(client_id, reverted_tx, TYPE_KW_CHARGEBACK, accounts)?;
     account
-        .chargeback(reverted_tx)
-        .map_err(|msg| processing_error(client_id, reverted_tx, msg))
-}
-
-fn ensure_client_is_known<'a>(
-    client_id: ClientId,
-    tx_id: TxId,
-    tx_type: &'static str,
-    accounts: &'a mut Accounts,
-) -> Result<&'a mut AccountState, Error> {
-    accounts.get_mut(&client_id).ok_or(processing_error(
-        client_id,
-        tx_id,
-        format!("{tx_type} from a client without account"),
-    ))
+        .check_invariants(&config.policy)
+        .map_err(|rejection| {
+            if cfg!(debug_assertions) {
+                panic!("internal logic error: {}", rejection.message);
+            }
+            rejection_error(client_id, tx.tx_id(), rejection)
+        })
 }
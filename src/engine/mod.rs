@@ -1,12 +1,26 @@
 //! Module for the core logic of the engine
 
-use std::collections::HashMap;
+use std::ops::ControlFlow;
 
-use crate::domain::{AccountState, ClientId};
+use crate::domain::Accounts;
 
+mod conservation;
+mod handle;
 mod logic;
 mod orchestration;
+mod snapshot;
 
+pub use handle::TransactionEngine;
+pub use snapshot::EngineSnapshot;
+
+#[cfg(feature = "async")]
+pub(crate) use orchestration::process_transaction_stream;
 pub(crate) use orchestration::{process_transactions, process_transactions_parallel};
 
-type Accounts = HashMap<ClientId, AccountState>;
+/// Adapts a callback without a say in the control flow to the engine, which then always continues.
+pub(crate) fn continuing<T>(mut callback: impl FnMut(T)) -> impl FnMut(T) -> ControlFlow<()> {
+    move |value| {
+        callback(value);
+        ControlFlow::Continue(())
+    }
+}
@@ -1,79 +1,238 @@
 //! Module focusing on the way the transactions are orchestrated between worker threads
 
 use std::{
+    any::Any,
     collections::HashMap,
-    sync::mpsc::{SyncSender, sync_channel},
+    ops::ControlFlow,
+    panic::{self, AssertUnwindSafe},
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        mpsc::{Receiver, SendError, Sender, SyncSender, TrySendError, channel, sync_channel},
+    },
     thread::{Scope, ScopedJoinHandle},
 };
 
 use crate::{
-    Error, TransactionRecord,
+    Error, ProcessConfig, TransactionRecord,
+    config::fire,
     domain::{AccountState, ClientId, Transaction},
-    engine::{Accounts, logic::handle_transaction},
+    engine::{
+        Accounts,
+        conservation::{self, Flows},
+        logic::handle_transaction,
+    },
+    telemetry,
 };
 
 ///
 /// Processes an iterator of transactions and outputs the final state of client accounts, once the iterator is empty.
-/// Uses a single thread.
+/// Uses a single thread. Stops early once a callback returns [`ControlFlow::Break`].
 ///
 pub(crate) fn process_transactions(
     transactions: impl IntoIterator<Item = Result<Transaction, Error>>,
-    mut on_error: impl FnMut(Error),
-    mut on_success: impl FnMut(TransactionRecord),
+    mut on_error: impl FnMut(Error) -> ControlFlow<()>,
+    mut on_success: impl FnMut(TransactionRecord) -> ControlFlow<()>,
+    config: &ProcessConfig<'_>,
 ) -> HashMap<ClientId, AccountState> {
     let mut accounts = Accounts::default();
+    let mut flows = Flows::default();
+    let mut progress = Progress::new(config);
 
-    for result in transactions {
-        let tx = match result {
-            Ok(tx) => tx,
-            Err(err) => {
-                on_error(err);
-                continue;
-            }
-        };
+    for (processed, result) in (1..).zip(transactions) {
+        let flow = apply(
+            result,
+            &mut accounts,
+            config.verify_conservation.then_some(&mut flows),
+            &mut on_error,
+            &mut on_success,
+            config,
+        );
+        progress.update(processed);
+        if flow.is_break() {
+            break;
+        }
+    }
+
+    if config.verify_conservation
+        && let Err(err) = conservation::verify(flows, [&accounts])
+    {
+        let _ = on_error(err);
+    }
+    accounts
+}
 
-        match handle_transaction(&tx, &mut accounts) {
-            Ok(()) => on_success(TransactionRecord::from_domain(&tx)),
-            Err(err) => on_error(err),
+///
+/// Async variant of [`process_transactions`], pulling the transactions from a stream. The transactions are
+/// applied on the polling task as they arrive.
+///
+#[cfg(feature = "async")]
+pub(crate) async fn process_transaction_stream(
+    transactions: impl futures_util::Stream<Item = Result<Transaction, Error>>,
+    mut on_error: impl FnMut(Error) -> ControlFlow<()>,
+    mut on_success: impl FnMut(TransactionRecord) -> ControlFlow<()>,
+    config: &ProcessConfig<'_>,
+) -> HashMap<ClientId, AccountState> {
+    use futures_util::StreamExt;
+
+    let mut accounts = Accounts::default();
+    let mut flows = Flows::default();
+    let mut progress = Progress::new(config);
+
+    let mut transactions = std::pin::pin!(transactions);
+    let mut processed = 0;
+    while let Some(result) = transactions.next().await {
+        let flow = apply(
+            result,
+            &mut accounts,
+            config.verify_conservation.then_some(&mut flows),
+            &mut on_error,
+            &mut on_success,
+            config,
+        );
+        processed += 1;
+        progress.update(processed);
+        if flow.is_break() {
+            break;
         }
     }
 
+    if config.verify_conservation
+        && let Err(err) = conservation::verify(flows, [&accounts])
+    {
+        let _ = on_error(err);
+    }
     accounts
 }
 
+/// Reports the progress of a run to the `on_progress` hook, once per completed interval of processed rows
+struct Progress<'c, 'a> {
+    config: &'c ProcessConfig<'a>,
+    // the latest count reported, a multiple of the interval
+    reported: u64,
+}
+
+impl<'c, 'a> Progress<'c, 'a> {
+    fn new(config: &'c ProcessConfig<'a>) -> Self {
+        Self {
+            config,
+            reported: 0,
+        }
+    }
+
+    /// Reports every multiple of the interval up to the number of processed rows which was not reported yet
+    fn update(&mut self, processed: u64) {
+        if self.config.hooks.on_progress.is_none() {
+            return;
+        }
+        let interval = self.config.progress_interval;
+        while self.reported + interval <= processed {
+            self.reported += interval;
+            fire(&self.config.hooks.on_progress, || self.reported);
+        }
+    }
+}
+
+/// Applies a single parsing result to the accounts (recording the funds it moved in `flows`, if given), reporting the
+/// outcome to the matching callback. Returns whether the callback asks to continue.
+pub(super) fn apply(
+    result: Result<Transaction, Error>,
+    accounts: &mut Accounts,
+    flows: Option<&mut Flows>,
+    on_error: &mut impl FnMut(Error) -> ControlFlow<()>,
+    on_success: &mut impl FnMut(TransactionRecord) -> ControlFlow<()>,
+    config: &ProcessConfig<'_>,
+) -> ControlFlow<()> {
+    let tx = match result {
+        Ok(tx) => tx,
+        Err(err) => {
+            telemetry::record_rejected(&err);
+            return on_error(err);
+        }
+    };
+
+    match handle_transaction(&tx, accounts, flows, config) {
+        Ok(()) => {
+            telemetry::record_accepted();
+            on_success(TransactionRecord::from_domain(&tx))
+        }
+        Err(err) => {
+            telemetry::record_rejected(&err);
+            on_error(err)
+        }
+    }
+}
+
 ///
 /// Processes an iterator of transactions and outputs the final state of client accounts, once the iterator is empty.
 /// Uses a number of worker threads provided by the `num_workers` argument, sharding the transactions between the worker
 /// threads based on their `client_id`.
 ///
+/// The callbacks are invoked on a single callback thread. The outcomes of a client's transactions are reported in the
+/// input order of the transactions; parsing errors (which are reported by the dispatching thread) and the outcomes of
+/// different clients may interleave arbitrarily.
+///
+/// Once a callback returns [`ControlFlow::Break`], the callbacks are no longer invoked, the dispatcher stops reading
+/// the input, and the workers discard the transactions still queued for them. Transactions a worker applied before
+/// noticing the break stay applied, but are not reported.
+///
+/// A panicking worker does not take the run down: the panic is reported as [`Error::WorkerFailed`] to `on_error` (even
+/// after a break), and the accounts of its shard are missing from the result. The transaction whose handling panicked
+/// and every transaction queued for or dispatched to the shard afterwards are rejected as
+/// [`Error::ShardUnavailable`], while the other shards keep running.
+///
+/// In the deterministic mode (see [`ProcessConfig::deterministic`]), the dispatcher waits for each transaction to be
+/// handled by its worker before dispatching the next one, so that the outcomes are reported in input order.
+///
+/// Returns the account states per surviving worker (i.e., per shard), in the order in which the workers finished (by
+/// worker in the deterministic mode), and, per worker, how often the dispatcher found the worker's channel full and
+/// had to wait (backpressure).
+///
 pub(crate) fn process_transactions_parallel(
     transactions: impl IntoIterator<Item = Result<Transaction, Error>>,
-    on_error: impl FnMut(Error) + Send,
-    on_success: impl FnMut(TransactionRecord) + Send,
+    on_error: impl FnMut(Error) -> ControlFlow<()> + Send,
+    on_success: impl FnMut(TransactionRecord) -> ControlFlow<()> + Send,
     num_workers: usize,
     channel_capacity: usize,
-) -> HashMap<ClientId, AccountState> {
+    config: &ProcessConfig<'_>,
+) -> (Vec<HashMap<ClientId, AccountState>>, Vec<u64>) {
+    let shared = SharedState::default();
+    let mut progress = Progress::new(config);
+
     std::thread::scope(|s| {
-        let (success_tx, error_tx) =
-            spawn_callback_handlers(s, on_error, on_success, channel_capacity);
+        let outcome_tx =
+            spawn_callback_handler(s, on_error, on_success, channel_capacity, &shared.stop);
 
-        let (worker_senders, worker_handles) = spawn_worker_threads(
+        let (partition_tx, partition_rx) = channel();
+        let Workers {
+            senders: worker_senders,
+            acks: worker_acks,
+            handles: worker_handles,
+        } = spawn_worker_threads(
             s,
-            success_tx.clone(),
-            error_tx.clone(),
+            outcome_tx.clone(),
+            partition_tx,
             num_workers,
             channel_capacity,
+            config,
+            &shared,
         );
 
-        // Main thread keeps a clone for parse errors
-        let main_error_tx = error_tx.clone();
+        // Main thread keeps the original for parse errors — workers hold their own clones
+        let main_outcome_tx = outcome_tx;
 
-        // Drop originals — workers/callback threads hold their own clones
-        drop(success_tx);
-        drop(error_tx);
+        let mut backpressure = vec![0; num_workers];
+        let reject = |e: Error| {
+            telemetry::record_rejected(&e);
+            shared.processed.fetch_add(1, Ordering::Relaxed);
+            // Send fails only if the callback thread panicked; surfaced at the end of the scope.
+            let _ = main_outcome_tx.send(Outcome::Failure(e));
+        };
 
         // --- Main thread: parse and dispatch ---
         for result in transactions {
+            if shared.stop.load(Ordering::Relaxed) {
+                break;
+            }
             match result {
                 Ok(tx) => {
                     let client: u16 = tx.client_id().into();
@@ -81,98 +240,240 @@ pub(crate) fn process_transactions_parallel(
                     // Sharding transactions based on the client id -> all transactions of the same client sent to the same worker
                     let worker_idx = client as usize % num_workers;
 
-                    // Send fails only if the receiver was dropped (worker panicked);
-                    // the join() below will surface that panic.
-                    let _ = worker_senders[worker_idx].send(tx);
-                }
-                Err(e) => {
-                    // Send fails only if the callback thread panicked; surfaced at join().
-                    let _ = main_error_tx.send(e);
+                    let sent = match worker_senders[worker_idx].try_send(tx) {
+                        Ok(()) => Ok(()),
+                        Err(TrySendError::Full(tx)) => {
+                            backpressure[worker_idx] += 1;
+                            worker_senders[worker_idx]
+                                .send(tx)
+                                .map_err(|SendError(tx)| tx)
+                        }
+                        Err(TrySendError::Disconnected(tx)) => Err(tx),
+                    };
+                    match sent {
+                        // fails only if the worker panicked, in which case there is nothing to wait for
+                        Ok(()) => {
+                            if let Some(ack) = worker_acks.get(worker_idx) {
+                                let _ = ack.recv();
+                            }
+                        }
+                        // the receiver was dropped since the worker thread died outside of a transaction's handling
+                        // (which the join() below reports), so that the transaction is rejected instead of being lost
+                        // silently
+                        Err(tx) => reject(Error::ShardUnavailable {
+                            worker: worker_idx,
+                            client_id: client,
+                            tx_id: tx.tx_id().into(),
+                        }),
+                    }
                 }
+                Err(e) => reject(e),
             }
+            progress.update(shared.processed.load(Ordering::Relaxed));
         }
 
-        // Signal EOF: drop all senders
+        // Signal EOF to the workers: they drain and exit
         drop(worker_senders);
-        drop(main_error_tx);
-        // → workers drain and exit → drop their success_tx/error_tx clones
-        // → callback channels close → callback threads exit
 
         // --- Collect worker results ---
-        let mut all_accounts = HashMap::new();
-        for handle in worker_handles {
-            let partition = handle.join().expect("worker thread does not panic");
-            all_accounts.extend(partition);
+        for (worker, handle) in worker_handles.into_iter().enumerate() {
+            if let Err(payload) = handle.join() {
+                let message = panic_message(payload.as_ref());
+                tracing::error!("worker {worker} panicked, its shard is dropped: {message}");
+                // Send fails only if the callback thread panicked; surfaced at the end of the scope.
+                let _ =
+                    main_outcome_tx.send(Outcome::Report(Error::WorkerFailed { worker, message }));
+            }
+        }
+        // the rows processed after the dispatching ended
+        progress.update(shared.processed.load(Ordering::Relaxed));
+        // every worker sent its partition before exiting, in the order in which the workers finished
+        let mut partitions: Vec<_> = partition_rx.try_iter().collect();
+        if config.deterministic {
+            partitions.sort_unstable_by_key(|(worker, _, _)| *worker);
+        }
+        let mut flows = Flows::default();
+        let partitions: Vec<_> = partitions
+            .into_iter()
+            .map(|(_, accounts, worker_flows)| {
+                flows.add(worker_flows);
+                accounts
+            })
+            .collect();
+        // the shard of a failed worker is missing together with the funds moved by its transactions
+        if config.verify_conservation
+            && let Err(err) = conservation::verify(flows, &partitions)
+        {
+            // Send fails only if the callback thread panicked; surfaced at the end of the scope.
+            let _ = main_outcome_tx.send(Outcome::Report(err));
         }
 
-        all_accounts
+        // → all outcome senders dropped → callback channel closes → callback thread exits
+        drop(main_outcome_tx);
+        (partitions, backpressure)
     })
 }
 
-fn spawn_callback_handlers<'s, 'e>(
+/// The state shared between the threads of a parallel run
+#[derive(Default)]
+struct SharedState {
+    /// Set by the callback thread once a callback asks to stop
+    stop: AtomicBool,
+    /// The rows processed by the workers and the parse errors of the dispatcher
+    processed: AtomicU64,
+}
+
+/// The channels to and the handles of the spawned worker threads, indexed by worker
+struct Workers<'s> {
+    senders: Vec<SyncSender<Transaction>>,
+    /// Only in the deterministic mode: acknowledges each handled transaction to the dispatcher
+    acks: Vec<Receiver<()>>,
+    handles: Vec<ScopedJoinHandle<'s, ()>>,
+}
+
+/// Outcome of a transaction, sent from the workers to the callback thread
+enum Outcome {
+    Success(TransactionRecord),
+    Failure(Error),
+    /// An error of the run itself, reported even after a break
+    Report(Error),
+}
+
+/// Extracts the message of a panic payload, which is a string for panics raised with a message
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        (*message).to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "unknown panic payload".to_string()
+    }
+}
+
+fn spawn_callback_handler<'s, 'e>(
     s: &'s Scope<'s, 'e>,
-    mut on_error: impl FnMut(Error) + Send + 's,
-    mut on_success: impl FnMut(TransactionRecord) + Send + 's,
+    mut on_error: impl FnMut(Error) -> ControlFlow<()> + Send + 's,
+    mut on_success: impl FnMut(TransactionRecord) -> ControlFlow<()> + Send + 's,
     channel_capacity: usize,
-) -> (SyncSender<TransactionRecord>, SyncSender<Error>) {
-    let (success_tx, success_rx) = sync_channel::<TransactionRecord>(channel_capacity);
-    let (error_tx, error_rx) = sync_channel::<Error>(channel_capacity);
+    stop: &'e AtomicBool,
+) -> SyncSender<Outcome> {
+    let (outcome_tx, outcome_rx) = sync_channel::<Outcome>(channel_capacity);
 
     s.spawn(move || {
-        // worker processing the successful transactions
-        for record in success_rx {
-            on_success(record)
-        }
-    });
-
-    s.spawn(move || {
-        // worker processing the erroneous transactions
-        for err in error_rx {
-            on_error(err)
+        // Both callbacks are invoked from this thread in the order in which the outcomes were sent. Since all
+        // transactions of a client are handled by the same worker, the outcomes of a client keep their input order.
+        for outcome in outcome_rx {
+            // after a break, the outcomes are still received (so that no sender blocks), but no longer reported
+            if stop.load(Ordering::Relaxed) {
+                if let Outcome::Report(err) = outcome {
+                    let _ = on_error(err);
+                }
+                continue;
+            }
+            let flow = match outcome {
+                Outcome::Success(record) => on_success(record),
+                Outcome::Failure(err) | Outcome::Report(err) => on_error(err),
+            };
+            if flow.is_break() {
+                stop.store(true, Ordering::Relaxed);
+            }
         }
     });
 
-    (success_tx, error_tx)
+    outcome_tx
 }
 
 fn spawn_worker_threads<'s, 'e>(
     s: &'s Scope<'s, 'e>,
-    success_tx: SyncSender<TransactionRecord>,
-    error_tx: SyncSender<Error>,
+    outcome_tx: SyncSender<Outcome>,
+    partition_tx: Sender<(usize, Accounts, Flows)>,
     num_workers: usize,
     channel_capacity: usize,
-) -> (
-    Vec<SyncSender<Transaction>>,
-    Vec<ScopedJoinHandle<'s, Accounts>>,
-) {
+    config: &'e ProcessConfig<'_>,
+    shared: &'e SharedState,
+) -> Workers<'s> {
     let mut worker_senders = Vec::with_capacity(num_workers);
+    let mut worker_acks = Vec::new();
     let mut worker_handles = Vec::with_capacity(num_workers);
 
-    for _ in 0..num_workers {
+    for worker in 0..num_workers {
         let (tx_in, tx_out) = sync_channel::<Transaction>(channel_capacity);
-        let stx = success_tx.clone();
-        let etx = error_tx.clone();
+        let otx = outcome_tx.clone();
+        let ptx = partition_tx.clone();
+        let ack_tx = config.deterministic.then(|| {
+            let (ack_tx, ack_rx) = sync_channel::<()>(1);
+            worker_acks.push(ack_rx);
+            ack_tx
+        });
 
         let handle = s.spawn(move || {
             let mut accounts = Accounts::default();
+            let mut flows = Flows::default();
+            // the payload of the panic of a transaction's handling, after which the shard is unavailable
+            let mut failure: Option<Box<dyn Any + Send>> = None;
             for tx in tx_out {
-                match handle_transaction(&tx, &mut accounts) {
-                    Ok(()) => {
-                        // Send fails only if the callback thread panicked;
-                        // the caller's join() on worker handles will surface it.
-                        let _ = stx.send(TransactionRecord::from_domain(&tx));
-                    }
-                    Err(e) => {
-                        let _ = etx.send(e);
+                let ack = || {
+                    if let Some(ack_tx) = &ack_tx {
+                        // fails only if the dispatcher is done
+                        let _ = ack_tx.send(());
                     }
+                };
+                // after a break, the queued transactions are drained without being applied
+                if shared.stop.load(Ordering::Relaxed) {
+                    ack();
+                    continue;
                 }
+                let handled = if failure.is_none() {
+                    let flows = config.verify_conservation.then_some(&mut flows);
+                    // the state of the shard is discarded after a panic, so that it cannot be observed broken
+                    panic::catch_unwind(AssertUnwindSafe(|| {
+                        handle_transaction(&tx, &mut accounts, flows, config)
+                    }))
+                    .map_err(|payload| failure = Some(payload))
+                } else {
+                    Err(())
+                };
+                let outcome = match handled {
+                    Ok(Ok(())) => {
+                        telemetry::record_accepted();
+                        Outcome::Success(TransactionRecord::from_domain(&tx))
+                    }
+                    Ok(Err(e)) => {
+                        telemetry::record_rejected(&e);
+                        Outcome::Failure(e)
+                    }
+                    // the transactions queued for (or still dispatched to) a failed worker are rejected one by one
+                    Err(()) => {
+                        let e = Error::ShardUnavailable {
+                            worker,
+                            client_id: tx.client_id().into(),
+                            tx_id: tx.tx_id().into(),
+                        };
+                        telemetry::record_rejected(&e);
+                        Outcome::Failure(e)
+                    }
+                };
+                shared.processed.fetch_add(1, Ordering::Relaxed);
+                // Send fails only if the callback thread panicked;
+                // the end of the scope will surface it.
+                let _ = otx.send(outcome);
+                ack();
             }
-            accounts
+            if let Some(payload) = failure {
+                // reported as a failed worker by the dispatcher, without the accounts of the shard
+                panic::resume_unwind(payload);
+            }
+            // Send cannot fail: the receiver outlives the workers
+            let _ = ptx.send((worker, accounts, flows));
         });
 
         worker_senders.push(tx_in);
         worker_handles.push(handle);
     }
 
-    (worker_senders, worker_handles)
+    Workers {
+        senders: worker_senders,
+        acks: worker_acks,
+        handles: worker_handles,
+    }
 }
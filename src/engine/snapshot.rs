@@ -0,0 +1,32 @@
+//! Module defining the checkpointed state of the engine, from which processing can be resumed
+
+use crate::engine::Accounts;
+
+/// A checkpoint of the account states of a [`TransactionEngine`](crate::TransactionEngine), taken with
+/// [`checkpoint()`](crate::TransactionEngine::checkpoint) and resumed from with
+/// [`resume()`](crate::TransactionEngine::resume).
+///
+/// With the `snapshot` feature, the snapshot implements `Serialize`/`Deserialize`, so that it can be persisted,
+/// e.g., to restart a crashed batch job without reprocessing the whole input.
+///
+/// # Resuming
+///
+/// The snapshot contains the disputable deposits of every account, so that disputes, resolves, and chargebacks
+/// referencing transactions applied before the checkpoint keep working after resumption. The engine does not,
+/// however, deduplicate transactions by their id: a deposit applied before the checkpoint and replayed after
+/// resumption is credited twice. The snapshot therefore records the number of transactions fed to the engine
+/// ([`processed()`](Self::processed)), counting accepted and rejected ones alike. Resume with the input
+/// following that many transactions.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "snapshot", derive(serde::Serialize, serde::Deserialize))]
+pub struct EngineSnapshot {
+    pub(super) accounts: Accounts,
+    pub(super) processed: u64,
+}
+
+impl EngineSnapshot {
+    /// The number of transactions (accepted or rejected) fed to the engine before the checkpoint
+    pub fn processed(&self) -> u64 {
+        self.processed
+    }
+}
@@ -1,31 +1,303 @@
 //! Module defining the errors which are exposed to the users of the crate
 
+use crate::domain::{ProcessingKind, Rejection};
+
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
-    /// Invalid CSV
-    #[error("CSV error: {0}")]
-    Csv(#[from] csv::Error),
+    /// Invalid CSV. `line` is the 1-based line number of the offending row, if known.
+    #[error("CSV error{}: {source}", line.map(|l| format!(" on line {l}")).unwrap_or_default())]
+    Csv {
+        line: Option<u64>,
+        #[source]
+        source: csv::Error,
+    },
+
+    /// Invalid binary input (see [`process_binary()`](crate::process_binary)), i.e., an I/O error of the reader or an
+    /// input ending within a record. `record` is the 0-based index of the offending record. Ends the decoding.
+    #[error("binary input error at record {record}: {source}")]
+    Binary {
+        record: u64,
+        #[source]
+        source: std::io::Error,
+    },
+
+    /// A row which could not be parsed or failed the validation, together with the row itself, e.g., for writing it
+    /// to a dead-letter file for manual review. Only reported if
+    /// [`ProcessConfig::capture_malformed_rows`](crate::ProcessConfig::capture_malformed_rows) is enabled, instead of
+    /// the [`Csv`](Self::Csv) or [`Validation`](Self::Validation) error `source`. `raw` holds the fields of the row (as
+    /// trimmed by the parsing) joined by the delimiter, and `line` is the 1-based line number of the row, if known.
+    #[error("{source} (row: {raw})")]
+    Malformed {
+        line: Option<u64>,
+        raw: String,
+        #[source]
+        source: Box<Error>,
+    },
+
+    /// The header of the CSV input lacks an expected column, e.g., since it is mistyped. Reported once instead of the
+    /// rows, which are not parsed. The check can be disabled via
+    /// [`ProcessConfig::check_header`](crate::ProcessConfig::check_header). `line` is the 1-based line number of the
+    /// header (e.g., after leading comment lines), if known.
+    #[error("invalid CSV header: {message}")]
+    Header { line: Option<u64>, message: String },
 
     /// Valid CSV violating domain invariants, e.g., a deposit with a negative amount
     #[error("validation error — client: {client_id}, tx: {tx_id}: {message}")]
     Validation {
         client_id: u16,
-        tx_id: u32,
+        tx_id: u64,
         message: String,
     },
 
-    /// Valid CSV satisfying domain invariants, but inconsistent with the current state (e.g., withdrawal exceeding the available amount)
+    /// Transaction rejected since the account of the client is locked (after a chargeback)
+    #[error("account locked — client: {client_id}, tx: {tx_id}: transaction rejected")]
+    AccountLocked { client_id: u16, tx_id: u64 },
+
+    /// Valid CSV satisfying domain invariants, but inconsistent with the current state (e.g., withdrawal exceeding the available amount).
+    /// `kind` is the cause of the conflict, for matching; `message` describes it for humans.
     #[error("processing conflict — client: {client_id}, tx: {tx_id}: {message}")]
     Processing {
         client_id: u16,
-        tx_id: u32,
+        tx_id: u64,
+        kind: ProcessingKind,
         message: String,
     },
+
+    /// A worker thread of the parallel engine panicked. The transactions of its shard (the clients with
+    /// `client % workers == worker`) which were not yet applied are rejected one by one as
+    /// [`ShardUnavailable`](Self::ShardUnavailable), and the accounts of the shard are missing from the output; the
+    /// other shards are unaffected.
+    #[error("worker {worker} failed: {message}")]
+    WorkerFailed { worker: usize, message: String },
+
+    /// Transaction rejected since the worker thread of its shard (the clients with `client % workers == worker`) failed
+    /// while applying it or earlier in the run (see [`WorkerFailed`](Self::WorkerFailed)), so that it cannot be
+    /// applied
+    #[error("shard unavailable — client: {client_id}, tx: {tx_id}: worker {worker} failed")]
+    ShardUnavailable {
+        worker: usize,
+        client_id: u16,
+        tx_id: u64,
+    },
+
+    /// The input has more rows than the configured [`max_rows`](crate::ProcessConfig::max_rows). Reported once, after
+    /// the first `max_rows` rows were processed; the remaining rows are skipped.
+    #[error("input truncated: more than {max_rows} rows")]
+    Truncated { max_rows: usize },
+
+    /// The total funds of the accounts differ from the funds moved by the applied transactions (deposits minus
+    /// withdrawals minus chargebacks), which indicates an internal logic error. Reported once at the end of a run if
+    /// [`ProcessConfig::verify_conservation`](crate::ProcessConfig::verify_conservation) is enabled.
+    #[error(
+        "funds not conserved: the accounts total {actual}, but the transactions moved {expected}"
+    )]
+    NotConserved {
+        expected: rust_decimal::Decimal,
+        actual: rust_decimal::Decimal,
+    },
+}
+
+impl Error {
+    /// The cause of a processing conflict (including locked accounts), or `None` for other errors
+    pub fn processing_kind(&self) -> Option<ProcessingKind> {
+        match self {
+            Error::Processing { kind, .. } => Some(*kind),
+            Error::AccountLocked { .. } => Some(ProcessingKind::AccountLocked),
+            Error::Malformed { source, .. } => source.processing_kind(),
+            Error::Csv { .. }
+            | Error::Binary { .. }
+            | Error::Header { .. }
+            | Error::Validation { .. }
+            | Error::WorkerFailed { .. }
+            | Error::ShardUnavailable { .. }
+            | Error::Truncated { .. }
+            | Error::NotConserved { .. } => None,
+        }
+    }
+
+    /// The snake-cased name of the cause of the error, e.g., `validation` or `insufficient_funds` (for a processing
+    /// conflict). Used as the `kind` of the metrics and of the serialized errors.
+    #[cfg_attr(
+        not(any(feature = "metrics", feature = "error-json")),
+        allow(dead_code)
+    )]
+    pub(crate) fn kind_label(&self) -> &'static str {
+        match self {
+            // reported in place of its cause, and counted as such
+            Error::Malformed { source, .. } => source.kind_label(),
+            Error::Csv { .. } => "csv",
+            Error::Binary { .. } => "binary",
+            Error::Header { .. } => "header",
+            Error::Validation { .. } => "validation",
+            Error::AccountLocked { .. } => "account_locked",
+            Error::WorkerFailed { .. } => "worker_failed",
+            Error::ShardUnavailable { .. } => "shard_unavailable",
+            Error::Truncated { .. } => "truncated",
+            Error::NotConserved { .. } => "not_conserved",
+            Error::Processing { kind, .. } => match kind {
+                ProcessingKind::InsufficientFunds => "insufficient_funds",
+                ProcessingKind::UnknownTransaction => "unknown_transaction",
+                ProcessingKind::AlreadyDisputed => "already_disputed",
+                ProcessingKind::UndisputedResolve => "undisputed_resolve",
+                ProcessingKind::UndisputedChargeback => "undisputed_chargeback",
+                ProcessingKind::AlreadyResolved => "already_resolved",
+                ProcessingKind::AlreadyChargedBack => "already_charged_back",
+                ProcessingKind::DisputeFundsWithdrawn => "dispute_funds_withdrawn",
+                ProcessingKind::AccountLocked => "account_locked",
+                ProcessingKind::NoAccount => "no_account",
+                ProcessingKind::ArithmeticOverflow => "arithmetic_overflow",
+                ProcessingKind::BalanceCapExceeded => "balance_cap_exceeded",
+                ProcessingKind::InvariantViolation => "invariant_violation",
+                ProcessingKind::UnlockNotAllowed => "unlock_not_allowed",
+                ProcessingKind::ForeignTransaction => "foreign_transaction",
+                ProcessingKind::InvalidSettlement => "invalid_settlement",
+            },
+        }
+    }
+}
+
+/// Serializes the error as a flat event (requires the `error-json` feature), e.g., for forwarding it to a structured
+/// log pipeline:
+///
+/// ```json
+/// {"kind":"validation","client_id":1,"tx_id":2,"message":"amount must be positive"}
+/// ```
+///
+/// `kind` is the snake-cased cause (e.g., `insufficient_funds` for a processing conflict). The ids are omitted for
+/// CSV and header errors, failed workers, truncated input, and funds not conserved, `line` is only present for CSV and
+/// header errors with a known line, and `worker` only for failed workers. A malformed row is serialized as its cause,
+/// with the row added as `raw`.
+#[cfg(feature = "error-json")]
+impl serde::Serialize for Error {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        #[derive(serde::Serialize)]
+        struct ErrorEvent<'a> {
+            kind: &'static str,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            client_id: Option<u16>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            tx_id: Option<u64>,
+            message: std::borrow::Cow<'a, str>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            line: Option<u64>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            worker: Option<usize>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            raw: Option<&'a str>,
+        }
+
+        // a malformed row is serialized as its cause, together with the raw row
+        let (error, line, raw) = match self {
+            Error::Malformed { line, raw, source } => (source.as_ref(), *line, Some(raw.as_str())),
+            error => (error, None, None),
+        };
+        let mut event = match error {
+            Error::Csv { line, source } => ErrorEvent {
+                kind: error.kind_label(),
+                client_id: None,
+                tx_id: None,
+                message: source.to_string().into(),
+                line: *line,
+                worker: None,
+                raw: None,
+            },
+            Error::Binary { source, .. } => ErrorEvent {
+                kind: error.kind_label(),
+                client_id: None,
+                tx_id: None,
+                message: source.to_string().into(),
+                line: None,
+                worker: None,
+                raw: None,
+            },
+            Error::Validation {
+                client_id,
+                tx_id,
+                message,
+            }
+            | Error::Processing {
+                client_id,
+                tx_id,
+                message,
+                ..
+            } => ErrorEvent {
+                kind: error.kind_label(),
+                client_id: Some(*client_id),
+                tx_id: Some(*tx_id),
+                message: message.into(),
+                line: None,
+                worker: None,
+                raw: None,
+            },
+            Error::AccountLocked { client_id, tx_id } => ErrorEvent {
+                kind: error.kind_label(),
+                client_id: Some(*client_id),
+                tx_id: Some(*tx_id),
+                message: "transaction rejected".into(),
+                line: None,
+                worker: None,
+                raw: None,
+            },
+            Error::WorkerFailed { worker, message } => ErrorEvent {
+                kind: error.kind_label(),
+                client_id: None,
+                tx_id: None,
+                message: message.into(),
+                line: None,
+                worker: Some(*worker),
+                raw: None,
+            },
+            Error::ShardUnavailable {
+                worker,
+                client_id,
+                tx_id,
+            } => ErrorEvent {
+                kind: error.kind_label(),
+                client_id: Some(*client_id),
+                tx_id: Some(*tx_id),
+                message: "shard unavailable".into(),
+                line: None,
+                worker: Some(*worker),
+                raw: None,
+            },
+            Error::Header { line, message } => ErrorEvent {
+                kind: error.kind_label(),
+                client_id: None,
+                tx_id: None,
+                message: message.into(),
+                line: *line,
+                worker: None,
+                raw: None,
+            },
+            Error::Truncated { .. } | Error::NotConserved { .. } => ErrorEvent {
+                kind: error.kind_label(),
+                client_id: None,
+                tx_id: None,
+                message: error.to_string().into(),
+                line: None,
+                worker: None,
+                raw: None,
+            },
+            Error::Malformed { .. } => unreachable!("a malformed row does not wrap another one"),
+        };
+        event.line = event.line.or(line);
+        event.raw = raw;
+        event.serialize(serializer)
+    }
+}
+
+impl From<csv::Error> for Error {
+    fn from(source: csv::Error) -> Self {
+        Error::Csv {
+            line: source.position().map(|pos| pos.line()),
+            source,
+        }
+    }
 }
 
 pub(crate) fn validation_error(
     client_id: impl Into<u16>,
-    tx_id: impl Into<u32>,
+    tx_id: impl Into<u64>,
     message: impl Into<String>,
 ) -> Error {
     Error::Validation {
@@ -37,12 +309,29 @@ pub(crate) fn validation_error(
 
 pub(crate) fn processing_error(
     client_id: impl Into<u16>,
-    tx_id: impl Into<u32>,
+    tx_id: impl Into<u64>,
+    kind: ProcessingKind,
     message: impl Into<String>,
 ) -> Error {
     Error::Processing {
         client_id: client_id.into(),
         tx_id: tx_id.into(),
+        kind,
         message: message.into(),
     }
 }
+
+/// Converts the rejection of a transaction by an account into the error reported to the caller
+pub(crate) fn rejection_error(
+    client_id: impl Into<u16>,
+    tx_id: impl Into<u64>,
+    rejection: Rejection,
+) -> Error {
+    match rejection.kind {
+        ProcessingKind::AccountLocked => Error::AccountLocked {
+            client_id: client_id.into(),
+            tx_id: tx_id.into(),
+        },
+        kind => processing_error(client_id, tx_id, kind, rejection.message),
+    }
+}
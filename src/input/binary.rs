@@ -0,0 +1,167 @@
+//! Module defining a compact binary encoding of the transactions, for pre-encoding an input once and replaying it
+//! without the cost of parsing CSV.
+//!
+//! Every transaction is a fixed-width record of [`RECORD_LEN`] bytes, with the integers in little-endian byte order:
+//!
+//! | bytes  | field                                                                                   |
+//! |--------|-----------------------------------------------------------------------------------------|
+//! | 0      | type: 0 deposit, 1 withdrawal, 2 dispute, 3 resolve, 4 chargeback, 5 open, 6 unlock     |
+//! | 1..3   | client (`u16`)                                                                          |
+//! | 3..7   | tx (`u32`)                                                                              |
+//! | 7..15  | amount, scaled by 10^[`MAX_AMOUNT_SCALE`] (`i64`); zero for the types without an amount |
+
+use std::io::{self, BufReader, Read, Write};
+
+use rust_decimal::Decimal;
+
+use crate::TransactionRecord;
+use crate::domain::Transaction;
+use crate::error::{Error, validation_error};
+use crate::input::{MAX_AMOUNT_SCALE, ParseOptions, RawTransaction, RowValidator, TxType};
+
+/// The number of bytes of an encoded transaction
+pub(crate) const RECORD_LEN: usize = 15;
+
+const TYPE_CODES: [TxType; 7] = [
+    TxType::Deposit,
+    TxType::Withdrawal,
+    TxType::Dispute,
+    TxType::Resolve,
+    TxType::Chargeback,
+    TxType::Open,
+    TxType::Unlock,
+];
+
+/// Decodes the binary records provided by the reader into transactions, validated like the rows of a CSV input.
+/// Errors are reported per record: an invalid record (e.g., with an unknown type) yields an error and the decoding
+/// continues with the next record. An I/O error of the reader, or an input ending within a record, ends the
+/// iteration.
+pub(crate) fn parse_transactions_binary<R: Read>(
+    reader: R,
+    options: &ParseOptions,
+) -> impl Iterator<Item = Result<Transaction, Error>> + use<R> {
+    let mut reader = BufReader::new(reader);
+    let mut validator = RowValidator::new(options);
+    let mut index = 0u64;
+    let mut done = false;
+    std::iter::from_fn(move || {
+        if done {
+            return None;
+        }
+        let record = match read_record(&mut reader) {
+            Ok(Some(record)) => record,
+            Ok(None) => return None,
+            Err(source) => {
+                done = true;
+                return Some(Err(Error::Binary {
+                    record: index,
+                    source,
+                }));
+            }
+        };
+        index += 1;
+        Some(decode(&record).and_then(|raw| validator.validate(raw)))
+    })
+}
+
+/// Reads the next record; `None` if the input ends before it
+fn read_record(reader: &mut impl Read) -> io::Result<Option<[u8; RECORD_LEN]>> {
+    let mut record = [0; RECORD_LEN];
+    let mut filled = 0;
+    while filled < RECORD_LEN {
+        match reader.read(&mut record[filled..]) {
+            Ok(0) if filled == 0 => return Ok(None),
+            Ok(0) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    format!("input ends within a record ({filled} of {RECORD_LEN} bytes)"),
+                ));
+            }
+            Ok(read) => filled += read,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(Some(record))
+}
+
+fn decode(record: &[u8; RECORD_LEN]) -> Result<RawTransaction, Error> {
+    let client = u16::from_le_bytes([record[1], record[2]]);
+    let tx = u32::from_le_bytes(record[3..7].try_into().expect("4 bytes"));
+    let amount = i64::from_le_bytes(record[7..15].try_into().expect("8 bytes"));
+
+    let tx_type = TYPE_CODES
+        .into_iter()
+        .nth(record[0].into())
+        .ok_or_else(|| {
+            validation_error(
+                client,
+                tx,
+                format!("unknown transaction type {}", record[0]),
+            )
+        })?;
+    let amount = Decimal::new(amount, MAX_AMOUNT_SCALE).normalize();
+    // the types without an amount encode none as zero; a non-zero amount is rejected by the validation
+    let amount = match tx_type {
+        TxType::Deposit | TxType::Withdrawal => Some(amount),
+        _ => (!amount.is_zero()).then_some(amount),
+    };
+    Ok(RawTransaction::new(tx_type, client, tx.into(), amount))
+}
+
+/// Encodes the transactions in the binary encoding read by [`process_binary()`](crate::process_binary), e.g., to
+/// pre-encode an input once for replaying it repeatedly. Fails with [`io::ErrorKind::InvalidInput`] on a transaction
+/// which cannot be encoded: a tx id beyond `u32::MAX`, or an amount with more than 4 decimal places or beyond the
+/// range of the scaled `i64`. The transactions before it are written.
+pub fn write_transactions_binary(
+    records: impl IntoIterator<Item = TransactionRecord>,
+    writer: impl Write,
+) -> io::Result<()> {
+    let mut writer = io::BufWriter::new(writer);
+    for record in records {
+        writer.write_all(&encode(&record)?)?;
+    }
+    writer.flush()
+}
+
+fn encode(record: &TransactionRecord) -> io::Result<[u8; RECORD_LEN]> {
+    let (code, client, tx, amount) = match *record {
+        TransactionRecord::Deposit { client, tx, amount } => (0u8, client, tx, amount),
+        TransactionRecord::Withdrawal { client, tx, amount } => (1, client, tx, amount),
+        TransactionRecord::Dispute { client, tx } => (2, client, tx, Decimal::ZERO),
+        // a full settlement encodes no amount as zero
+        TransactionRecord::Resolve { client, tx, amount } => {
+            (3, client, tx, amount.unwrap_or(Decimal::ZERO))
+        }
+        TransactionRecord::Chargeback { client, tx, amount } => {
+            (4, client, tx, amount.unwrap_or(Decimal::ZERO))
+        }
+        TransactionRecord::Open { client, tx } => (5, client, tx, Decimal::ZERO),
+        TransactionRecord::Unlock { client, tx } => (6, client, tx, Decimal::ZERO),
+    };
+    let invalid = |message: String| io::Error::new(io::ErrorKind::InvalidInput, message);
+    let tx = u32::try_from(tx).map_err(|_| invalid(format!("tx id {tx} exceeds u32::MAX")))?;
+    let scaled =
+        scale(amount).ok_or_else(|| invalid(format!("amount {amount} cannot be encoded")))?;
+
+    let mut encoded = [0; RECORD_LEN];
+    encoded[0] = code;
+    encoded[1..3].copy_from_slice(&client.to_le_bytes());
+    encoded[3..7].copy_from_slice(&tx.to_le_bytes());
+    encoded[7..15].copy_from_slice(&scaled.to_le_bytes());
+    Ok(encoded)
+}
+
+/// The amount as an integer multiple of 10^-[`MAX_AMOUNT_SCALE`], if it is one and fits into an `i64`
+fn scale(amount: Decimal) -> Option<i64> {
+    if amount.normalize().scale() > MAX_AMOUNT_SCALE {
+        return None;
+    }
+    let mut scaled = amount;
+    scaled.rescale(MAX_AMOUNT_SCALE);
+    // a rescale overflowing the mantissa keeps a smaller scale
+    if scaled.scale() != MAX_AMOUNT_SCALE {
+        return None;
+    }
+    i64::try_from(scaled.mantissa()).ok()
+}
@@ -1,57 +1,537 @@
 //! Module defining the parsing logic used to convert the user-provided input into validated domain types that can be provided to the core logic of the engine.
 
-use std::io::Read;
+use std::io::{self, Read};
+use std::ops::RangeInclusive;
 
 use rust_decimal::Decimal;
-use serde::Deserialize;
+use serde::{Deserialize, Deserializer, de};
 
+use crate::TransactionRecord;
 use crate::domain::{
-    Chargeback, ClientId, Deposit, Dispute, Resolve, Transaction, TxId, Withdrawal,
+    Chargeback, ClientId, Deposit, Dispute, Open, Resolve, Transaction, TxId, Unlock, Withdrawal,
 };
 use crate::error::{Error, validation_error};
 
-pub(crate) const TYPE_KW_DISPUTE: &str = "dispute";
-pub(crate) const TYPE_KW_RESOLVE: &str = "resolve";
-pub(crate) const TYPE_KW_CHARGEBACK: &str = "chargeback";
+pub(crate) use crate::domain::{
+    TYPE_KW_CHARGEBACK, TYPE_KW_DEPOSIT, TYPE_KW_DISPUTE, TYPE_KW_OPEN, TYPE_KW_RESOLVE,
+    TYPE_KW_UNLOCK, TYPE_KW_WITHDRAWAL,
+};
+
+/// The maximal number of decimal places of a transaction amount
+pub(crate) const MAX_AMOUNT_SCALE: u32 = 4;
 
+pub(crate) mod binary;
 #[cfg(test)]
 mod tests;
 
-/// Parses the data provided by the reader and returns an iterator over the parsing results
-pub(crate) fn parse_transactions(
-    reader: impl Read,
-) -> impl Iterator<Item = Result<Transaction, Error>> {
-    let csv_reader = csv::ReaderBuilder::new()
+/// Options controlling how the input is parsed
+#[derive(Debug, Clone)]
+pub(crate) struct ParseOptions {
+    pub(crate) delimiter: u8,
+    /// If `false`, the columns are mapped by position: type, client, tx, amount
+    pub(crate) has_headers: bool,
+    /// Lines starting with this byte are skipped
+    pub(crate) comment: Option<u8>,
+    /// If `true`, rows may have more columns than the header; the columns beyond `amount` are ignored
+    pub(crate) flexible: bool,
+    /// If `true`, the rows must carry a `timestamp` column with non-decreasing values
+    pub(crate) enforce_ordering: bool,
+    /// Client ids which must not be used by transactions, e.g., since they serve as sentinels upstream
+    pub(crate) reserved_clients: RangeInclusive<u16>,
+    /// If `true`, deposits and withdrawals with a zero amount are accepted as balance-neutral transactions
+    pub(crate) allow_zero_amount: bool,
+    /// If `true`, deposits with a negative amount are applied as withdrawals of the absolute amount
+    pub(crate) negative_as_reversal: bool,
+    /// If `true`, a header lacking one of the expected columns fails the parsing up front
+    pub(crate) check_header: bool,
+    /// If `true`, the rows failing to parse or validate are reported together with their fields
+    pub(crate) capture_malformed_rows: bool,
+    /// If `true`, resolves and chargebacks may carry an amount settling only part of the held funds
+    pub(crate) partial_settlements: bool,
+}
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        Self {
+            delimiter: b',',
+            has_headers: true,
+            comment: None,
+            flexible: false,
+            enforce_ordering: false,
+            reserved_clients: 0..=0,
+            allow_zero_amount: false,
+            negative_as_reversal: false,
+            check_header: true,
+            capture_malformed_rows: false,
+            partial_settlements: false,
+        }
+    }
+}
+
+impl ParseOptions {
+    /// Converts the row into a transaction, accepting zero-amount deposits and withdrawals, converting negative
+    /// deposits into withdrawals, and accepting partial settlements if configured
+    fn to_transaction(&self, mut raw: RawTransaction) -> Result<Transaction, Error> {
+        if self.partial_settlements
+            && let Some(amount) = raw.amount
+            && matches!(raw.tx_type, TxType::Resolve | TxType::Chargeback)
+        {
+            return partial_settlement(&raw, amount);
+        }
+        let negative_deposit = raw.tx_type == TxType::Deposit
+            && raw.amount.is_some_and(|amount| amount < Decimal::ZERO);
+        if self.negative_as_reversal && negative_deposit {
+            // the refund reverses a prior credit
+            raw.tx_type = TxType::Withdrawal;
+            raw.amount = raw.amount.map(|amount| -amount);
+            return Transaction::try_from(raw).map(|tx| match tx {
+                Transaction::Withdrawal(withdrawal) => {
+                    Transaction::Withdrawal(withdrawal.into_reversal())
+                }
+                tx => tx,
+            });
+        }
+        let zero_amount = raw.amount.is_some_and(|amount| amount.is_zero());
+        if !(self.allow_zero_amount && zero_amount) {
+            return Transaction::try_from(raw);
+        }
+        let (client_id, tx_id) = (ClientId::new(raw.client), TxId::new(raw.tx));
+        match raw.tx_type {
+            TxType::Deposit => Ok(Transaction::Deposit(Deposit::zero(client_id, tx_id))),
+            TxType::Withdrawal => Ok(Transaction::Withdrawal(Withdrawal::zero(client_id, tx_id))),
+            _ => Transaction::try_from(raw),
+        }
+    }
+
+    fn ensure_client_not_reserved(&self, client: u16, tx: u64) -> Result<(), Error> {
+        if self.reserved_clients.contains(&client) {
+            Err(validation_error(
+                client,
+                tx,
+                format!("client id {client} is reserved"),
+            ))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// Parses the data provided by the reader and returns an iterator over the parsing results. Errors are reported per
+/// row: a malformed row (e.g., with a missing column) yields an error and the parsing continues with the next row.
+/// Only an I/O error of the reader ends the iteration.
+///
+/// If the header is checked and lacks an expected column, a single error describing the header is reported instead,
+/// without parsing any row.
+pub(crate) fn parse_transactions<R: Read>(
+    reader: R,
+    options: &ParseOptions,
+) -> impl Iterator<Item = Result<Transaction, Error>> + use<R> {
+    let mut csv_reader = csv::ReaderBuilder::new()
         .trim(csv::Trim::All)
-        .from_reader(reader);
+        // `\r\n`, `\n`, and a bare `\r` all end a row (also mixed within one input), so that no `\r` is left in the
+        // last column of input written on Windows
+        .terminator(csv::Terminator::CRLF)
+        .delimiter(options.delimiter)
+        .has_headers(options.has_headers)
+        .comment(options.comment)
+        .flexible(options.flexible)
+        .from_reader(HeaderScan::new(
+            reader,
+            options.has_headers && options.check_header,
+        ));
+
+    let header_error = if options.has_headers && options.check_header {
+        let header_error = check_header(&mut csv_reader).err();
+        csv_reader.get_mut().stop();
+        header_error
+    } else {
+        None
+    };
+    let max_rows = if header_error.is_some() {
+        0
+    } else {
+        usize::MAX
+    };
+
+    let headers = if options.has_headers {
+        csv_reader.headers().ok().cloned()
+    } else {
+        None
+    };
+    let (capture, delimiter) = (options.capture_malformed_rows, options.delimiter);
+    let mut validator = RowValidator::new(options);
+    // reused for all rows, just like deserializing the reader does
+    let mut record = csv::StringRecord::new();
+    let rows = std::iter::from_fn(move || match csv_reader.read_record(&mut record) {
+        Ok(false) => None,
+        Err(e) => Some(Err(e.into())),
+        Ok(true) => {
+            let result = record
+                .deserialize::<RawTransaction>(headers.as_ref())
+                .map_err(Error::from)
+                .and_then(|raw| validator.validate(raw));
+            Some(result.map_err(|e| {
+                if capture {
+                    malformed(&record, delimiter, e)
+                } else {
+                    e
+                }
+            }))
+        }
+    })
+    .take(max_rows);
+    header_error.map(Err).into_iter().chain(rows)
+}
 
-    csv_reader
-        .into_deserialize::<RawTransaction>()
-        .map(|result| {
-            let raw = result?;
-            Transaction::try_from(raw)
+/// Wraps the error of a row which failed to parse or validate together with the row's fields, joined by the delimiter
+/// (and quoted where needed)
+fn malformed(record: &csv::StringRecord, delimiter: u8, source: Error) -> Error {
+    let mut writer = csv::WriterBuilder::new()
+        .delimiter(delimiter)
+        .terminator(csv::Terminator::Any(b'\n'))
+        .from_writer(Vec::new());
+    let raw = writer
+        .write_record(record)
+        .ok()
+        .and_then(|()| writer.into_inner().ok())
+        .map(|bytes| {
+            String::from_utf8_lossy(&bytes)
+                .trim_end_matches('\n')
+                .to_string()
         })
+        .unwrap_or_default();
+    Error::Malformed {
+        line: record.position().map(|pos| pos.line()),
+        raw,
+        source: Box::new(source),
+    }
+}
+
+/// Validates the parsed rows of one input, independent of its encoding
+struct RowValidator {
+    options: ParseOptions,
+    ordering: Option<OrderingCheck>,
+}
+
+impl RowValidator {
+    fn new(options: &ParseOptions) -> Self {
+        Self {
+            options: options.clone(),
+            ordering: options.enforce_ordering.then(OrderingCheck::default),
+        }
+    }
+
+    /// Rejects rows of reserved clients and, if the ordering is enforced, rows out of order, and converts the row
+    /// into a transaction
+    fn validate(&mut self, raw: RawTransaction) -> Result<Transaction, Error> {
+        self.options
+            .ensure_client_not_reserved(raw.client, raw.tx)?;
+        if let Some(ordering) = &mut self.ordering {
+            ordering.check(&raw)?;
+        }
+        self.options.to_transaction(raw)
+    }
+}
+
+/// The columns every header must name; further columns are ignored
+const EXPECTED_COLUMNS: [&str; 4] = ["type", "client", "tx", "amount"];
+
+/// Reader keeping the bytes read from the input until the header is checked, to locate the line of the header: the
+/// position `csv` reports for the header record precedes skipped comment and blank lines
+struct HeaderScan<R> {
+    inner: R,
+    // `None` once the header is checked (or if it is not)
+    scanned: Option<Vec<u8>>,
+}
+
+impl<R> HeaderScan<R> {
+    fn new(inner: R, scan: bool) -> Self {
+        Self {
+            inner,
+            scanned: scan.then(Vec::new),
+        }
+    }
+
+    fn stop(&mut self) {
+        self.scanned = None;
+    }
+
+    /// The 1-based line of the header, given the position of the CSV reader right after it. The reader counts the line
+    /// feed ending the header, if any, but not a line feed following a carriage return ending it.
+    fn header_line(&self, after_header: &csv::Position) -> Option<u64> {
+        let scanned = self.scanned.as_ref()?;
+        let end = usize::try_from(after_header.byte()).ok()?;
+        let ended_by_line_feed = end
+            .checked_sub(1)
+            .and_then(|last| scanned.get(last))
+            .is_some_and(|&byte| byte == b'\n');
+        Some(after_header.line() - u64::from(ended_by_line_feed))
+    }
+}
+
+impl<R: Read> Read for HeaderScan<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        if let Some(scanned) = &mut self.scanned {
+            scanned.extend_from_slice(&buf[..read]);
+        }
+        Ok(read)
+    }
+}
+
+/// Verifies that the header names all expected columns, so that a mistyped column (e.g., `txid`) is reported once
+/// instead of failing every row. An unreadable or empty header is left to the parsing of the rows.
+fn check_header(csv_reader: &mut csv::Reader<HeaderScan<impl Read>>) -> Result<(), Error> {
+    let Ok(header) = csv_reader.headers() else {
+        return Ok(());
+    };
+    if header.is_empty() {
+        return Ok(());
+    }
+    let missing: Vec<&str> = EXPECTED_COLUMNS
+        .into_iter()
+        .filter(|column| !header.iter().any(|name| name == *column))
+        .collect();
+    if missing.is_empty() {
+        return Ok(());
+    }
+    let message = format!(
+        "missing column(s) {} (found: {})",
+        missing.join(", "),
+        header.iter().collect::<Vec<_>>().join(", ")
+    );
+    Err(Error::Header {
+        line: csv_reader.get_ref().header_line(csv_reader.position()),
+        message,
+    })
+}
+
+/// Parses a single headerless row (mapped by position: type, client, tx, amount) with the given options. Only the
+/// first row of the line is parsed.
+pub(crate) fn parse_line(line: &str, options: &ParseOptions) -> Result<Transaction, Error> {
+    let options = ParseOptions {
+        has_headers: false,
+        ..options.clone()
+    };
+    match parse_transactions(line.as_bytes(), &options).next() {
+        Some(result) => result,
+        // a blank line holds no row; deserializing the empty row reports the missing columns
+        None => Err(csv::StringRecord::new()
+            .deserialize::<RawTransaction>(None)
+            .err()
+            .expect("an empty row is not a transaction")
+            .into()),
+    }
+}
+
+/// Verifies that the rows arrive in non-decreasing order of their timestamps
+#[derive(Default)]
+struct OrderingCheck {
+    latest: Option<u64>,
+}
+
+impl OrderingCheck {
+    /// Rejects rows without a (numeric) timestamp and rows preceding the latest accepted timestamp. Rejected rows do
+    /// not advance the latest timestamp.
+    fn check(&mut self, raw: &RawTransaction) -> Result<(), Error> {
+        let timestamp = raw
+            .timestamp
+            .as_deref()
+            .ok_or_else(|| {
+                validation_error(
+                    raw.client,
+                    raw.tx,
+                    "a timestamp must be provided when the ordering is enforced",
+                )
+            })?
+            .parse::<u64>()
+            .map_err(|_| {
+                validation_error(
+                    raw.client,
+                    raw.tx,
+                    "the timestamp must be a non-negative integer",
+                )
+            })?;
+
+        match self.latest {
+            Some(latest) if timestamp < latest => Err(validation_error(
+                raw.client,
+                raw.tx,
+                format!("timestamp {timestamp} precedes the previous timestamp {latest}"),
+            )),
+            _ => {
+                self.latest = Some(timestamp);
+                Ok(())
+            }
+        }
+    }
 }
 
-// Intermediate type mirroring the CSV columns
+/// Validates a transaction provided as a record (instead of a CSV row) as if it was parsed with the given options
+pub(crate) fn validate_record(
+    record: TransactionRecord,
+    options: &ParseOptions,
+) -> Result<Transaction, Error> {
+    let (client, tx) = match record {
+        TransactionRecord::Deposit { client, tx, .. }
+        | TransactionRecord::Withdrawal { client, tx, .. }
+        | TransactionRecord::Dispute { client, tx }
+        | TransactionRecord::Resolve { client, tx, .. }
+        | TransactionRecord::Chargeback { client, tx, .. }
+        | TransactionRecord::Open { client, tx }
+        | TransactionRecord::Unlock { client, tx } => (client, tx),
+    };
+    options.ensure_client_not_reserved(client, tx)?;
+    options.to_transaction(RawTransaction::from(record))
+}
+
+// Intermediate type mirroring the CSV columns. The field order defines the column order of headerless input.
 #[derive(Deserialize)]
 struct RawTransaction {
     #[serde(rename = "type")]
     tx_type: TxType,
     client: u16,
-    tx: u32,
+    tx: u64,
     #[serde(with = "rust_decimal::serde::str_option")]
     amount: Option<Decimal>,
+    // optional column, only interpreted if the ordering is enforced
+    #[serde(default)]
+    timestamp: Option<String>,
 }
 
-#[derive(Deserialize, Debug, PartialEq, Eq)]
-#[serde(rename_all = "lowercase")]
+impl RawTransaction {
+    fn new(tx_type: TxType, client: u16, tx: u64, amount: Option<Decimal>) -> Self {
+        Self {
+            tx_type,
+            client,
+            tx,
+            amount,
+            timestamp: None,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
 pub(crate) enum TxType {
     Deposit,
     Withdrawal,
     Dispute,
     Resolve,
     Chargeback,
+    Open,
+    Unlock,
+}
+
+impl TxType {
+    const KEYWORDS: &[&str] = &[
+        TYPE_KW_DEPOSIT,
+        TYPE_KW_WITHDRAWAL,
+        TYPE_KW_DISPUTE,
+        TYPE_KW_RESOLVE,
+        TYPE_KW_CHARGEBACK,
+        TYPE_KW_OPEN,
+        TYPE_KW_UNLOCK,
+    ];
+
+    /// Matches the keyword case-insensitively
+    fn from_keyword(keyword: &str) -> Option<Self> {
+        let matches = |kw: &str| keyword.eq_ignore_ascii_case(kw);
+        if matches(TYPE_KW_DEPOSIT) {
+            Some(TxType::Deposit)
+        } else if matches(TYPE_KW_WITHDRAWAL) {
+            Some(TxType::Withdrawal)
+        } else if matches(TYPE_KW_DISPUTE) {
+            Some(TxType::Dispute)
+        } else if matches(TYPE_KW_RESOLVE) {
+            Some(TxType::Resolve)
+        } else if matches(TYPE_KW_CHARGEBACK) {
+            Some(TxType::Chargeback)
+        } else if matches(TYPE_KW_OPEN) {
+            Some(TxType::Open)
+        } else if matches(TYPE_KW_UNLOCK) {
+            Some(TxType::Unlock)
+        } else {
+            None
+        }
+    }
+}
+
+// Implemented by hand (instead of derived) to accept the keywords in any case without allocating
+impl<'de> Deserialize<'de> for TxType {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct TxTypeVisitor;
+
+        impl de::Visitor<'_> for TxTypeVisitor {
+            type Value = TxType;
+
+            fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                f.write_str("a transaction type keyword")
+            }
+
+            fn visit_str<E: de::Error>(self, v: &str) -> Result<TxType, E> {
+                TxType::from_keyword(v).ok_or_else(|| E::unknown_variant(v, TxType::KEYWORDS))
+            }
+        }
+
+        deserializer.deserialize_str(TxTypeVisitor)
+    }
+}
+
+// Transactions provided as records (instead of CSV rows) go through the same validation as the parsed ones
+impl From<TransactionRecord> for RawTransaction {
+    fn from(record: TransactionRecord) -> Self {
+        match record {
+            TransactionRecord::Deposit { client, tx, amount } => {
+                RawTransaction::new(TxType::Deposit, client, tx, Some(amount))
+            }
+            TransactionRecord::Withdrawal { client, tx, amount } => {
+                RawTransaction::new(TxType::Withdrawal, client, tx, Some(amount))
+            }
+            TransactionRecord::Dispute { client, tx } => {
+                RawTransaction::new(TxType::Dispute, client, tx, None)
+            }
+            TransactionRecord::Resolve { client, tx, amount } => {
+                RawTransaction::new(TxType::Resolve, client, tx, amount)
+            }
+            TransactionRecord::Chargeback { client, tx, amount } => {
+                RawTransaction::new(TxType::Chargeback, client, tx, amount)
+            }
+            TransactionRecord::Open { client, tx } => {
+                RawTransaction::new(TxType::Open, client, tx, None)
+            }
+            TransactionRecord::Unlock { client, tx } => {
+                RawTransaction::new(TxType::Unlock, client, tx, None)
+            }
+        }
+    }
+}
+
+/// Rejects amounts with more than [`MAX_AMOUNT_SCALE`] decimal places. Trailing zeros are not counted, so that, e.g.,
+/// `1.00000` is accepted.
+fn ensure_amount_precision(raw: &RawTransaction, amount: Decimal) -> Result<(), Error> {
+    if amount.normalize().scale() > MAX_AMOUNT_SCALE {
+        Err(validation_error(
+            raw.client,
+            raw.tx,
+            format!("amount exceeds {MAX_AMOUNT_SCALE} decimal places"),
+        ))
+    } else {
+        Ok(())
+    }
+}
+
+/// Converts a resolve or chargeback row carrying an amount into a partial settlement of the held funds
+fn partial_settlement(raw: &RawTransaction, amount: Decimal) -> Result<Transaction, Error> {
+    ensure_amount_precision(raw, amount)?;
+    let (client_id, tx_id) = (ClientId::new(raw.client), TxId::new(raw.tx));
+    let tx = match raw.tx_type {
+        TxType::Resolve => Resolve::partial(client_id, tx_id, amount).map(Transaction::Resolve),
+        TxType::Chargeback => {
+            Chargeback::partial(client_id, tx_id, amount).map(Transaction::Chargeback)
+        }
+        _ => unreachable!("only resolves and chargebacks settle disputes"),
+    };
+    tx.map_err(|msg| validation_error(raw.client, raw.tx, msg))
 }
 
 impl TryFrom<RawTransaction> for Transaction {
@@ -71,6 +551,7 @@ impl TryFrom<RawTransaction> for Transaction {
                         "an amount must be provided with a deposit transaction",
                     )
                 })?;
+                ensure_amount_precision(&raw, amount)?;
                 Ok(Transaction::Deposit(
                     Deposit::new(client_id, tx_id, amount)
                         .map_err(|msg| validation_error(raw.client, raw.tx, msg))?,
@@ -84,6 +565,7 @@ impl TryFrom<RawTransaction> for Transaction {
                         "an amount must be provided with a withdrawal transaction",
                     )
                 })?;
+                ensure_amount_precision(&raw, amount)?;
                 Ok(Transaction::Withdrawal(
                     Withdrawal::new(client_id, tx_id, amount)
                         .map_err(|msg| validation_error(raw.client, raw.tx, msg))?,
@@ -119,6 +601,27 @@ impl TryFrom<RawTransaction> for Transaction {
                 }
                 Ok(Transaction::Chargeback(Chargeback::new(client_id, tx_id)))
             }
+            TxType::Open => {
+                // upstreams may send the opening with an explicit zero amount
+                if amount.is_some_and(|amount| !amount.is_zero()) {
+                    return Err(validation_error(
+                        raw.client,
+                        raw.tx,
+                        "an open transaction must not carry a non-zero amount",
+                    ));
+                }
+                Ok(Transaction::Open(Open::new(client_id, tx_id)))
+            }
+            TxType::Unlock => {
+                if amount.is_some() {
+                    return Err(validation_error(
+                        raw.client,
+                        raw.tx,
+                        "an amount must not be provided with an unlock transaction",
+                    ));
+                }
+                Ok(Transaction::Unlock(Unlock::new(client_id, tx_id)))
+            }
         }
     }
 }
@@ -1,17 +1,15 @@
-use crate::domain::{ClientId, Deposit, Transaction, TxId};
+use crate::domain::{Chargeback, ClientId, Deposit, Resolve, Transaction, TxId};
 use crate::error::Error;
 use claims::{assert_err, assert_matches, assert_ok};
 
 use rstest::rstest;
+use rust_decimal_macros::dec;
 
 use super::*;
 
-pub(crate) const TYPE_KW_DEPOSIT: &str = "deposit";
-pub(crate) const TYPE_KW_WITHDRAWAL: &str = "withdrawal";
-
 /// Helper: parse a CSV string and collect all results.
 fn parse_csv(input: &str) -> Vec<Result<Transaction, Error>> {
-    parse_transactions(input.as_bytes()).collect()
+    parse_transactions(input.as_bytes(), &ParseOptions::default()).collect()
 }
 
 /// Helper: parse a CSV string, assert all rows succeed, return the transactions.
@@ -63,6 +61,8 @@ fn parse_transaction_row(
         TYPE_KW_DISPUTE,
         TYPE_KW_RESOLVE,
         TYPE_KW_CHARGEBACK,
+        TYPE_KW_OPEN,
+        TYPE_KW_UNLOCK,
         "invalid"
     )]
     tx_type: &str,
@@ -70,7 +70,7 @@ fn parse_transaction_row(
 ) {
     // Arrange
     let client_id = 1u16;
-    let tx_id = 1u32;
+    let tx_id = 1u64;
 
     let input = format!("type, client, tx, amount\n{tx_type}, {client_id}, {tx_id}, {amount}");
     let is_valid = specified_tx_is_valid(tx_type, amount);
@@ -98,6 +98,12 @@ fn parse_transaction_row(
             TYPE_KW_CHARGEBACK => {
                 assert_matches!(tx, Transaction::Chargeback(c) if c == Chargeback::new(ClientId::new(client_id), TxId::new(tx_id)))
             }
+            TYPE_KW_OPEN => {
+                assert_matches!(tx, Transaction::Open(o) if o == Open::new(ClientId::new(client_id), TxId::new(tx_id)))
+            }
+            TYPE_KW_UNLOCK => {
+                assert_matches!(tx, Transaction::Unlock(u) if u == Unlock::new(ClientId::new(client_id), TxId::new(tx_id)))
+            }
 
             _ => unreachable!("invalid type"),
         }
@@ -111,7 +117,483 @@ fn specified_tx_is_valid(tx_type: &str, amount: &str) -> bool {
         TYPE_KW_DEPOSIT | TYPE_KW_WITHDRAWAL => {
             !amount.is_empty() && amount.parse::<Decimal>().unwrap() > Decimal::ZERO
         }
-        TYPE_KW_DISPUTE | TYPE_KW_RESOLVE | TYPE_KW_CHARGEBACK => amount.is_empty(),
+        TYPE_KW_DISPUTE | TYPE_KW_RESOLVE | TYPE_KW_CHARGEBACK | TYPE_KW_UNLOCK => {
+            amount.is_empty()
+        }
+        TYPE_KW_OPEN => amount.is_empty() || amount.parse::<Decimal>().unwrap().is_zero(),
         _ => false,
     }
 }
+
+#[test]
+fn csv_error_reports_line_of_bad_row() {
+    let input = "\
+type, client, tx, amount
+deposit, 1, 1, 1.0
+deposit, 1, not-a-number, 2.0
+deposit, 1, 3, 3.0";
+
+    let results = parse_csv(input);
+    assert_eq!(results.len(), 3);
+    assert_ok!(&results[0]);
+    assert_matches!(&results[1], Err(Error::Csv { line: Some(3), .. }));
+    assert_ok!(&results[2]);
+
+    let message = results[1].as_ref().unwrap_err().to_string();
+    assert!(
+        message.starts_with("CSV error on line 3: "),
+        "unexpected message: {message}"
+    );
+}
+
+#[rstest]
+#[case::missing_column("deposit, 2, 2")]
+#[case::surplus_column("deposit, 2, 2, 1.0, 7")]
+#[case::quote_in_field("deposit, 2, 2\"x, 1.0")]
+fn structural_error_does_not_stop_the_parsing(#[case] bad_row: &str) {
+    let input =
+        format!("type, client, tx, amount\ndeposit, 1, 1, 1.0\n{bad_row}\ndeposit, 3, 3, 3.0");
+
+    let results = parse_csv(&input);
+    assert_eq!(results.len(), 3, "unexpected results: {results:?}");
+    assert_ok!(&results[0]);
+    assert_err!(&results[1]);
+    assert_ok!(&results[2]);
+}
+
+#[test]
+fn invalid_utf8_row_does_not_stop_the_parsing() {
+    let input =
+        b"type, client, tx, amount\ndeposit, 1, 1, 1.0\ndeposit, 2, 2, \xff\ndeposit, 3, 3, 3.0";
+
+    let results: Vec<_> = parse_transactions(&input[..], &ParseOptions::default()).collect();
+    assert_eq!(results.len(), 3, "unexpected results: {results:?}");
+    assert_matches!(&results[1], Err(Error::Csv { .. }));
+    assert_ok!(&results[2]);
+}
+
+/// Reader which provides the given input and fails on every read afterwards
+struct FailingReader<'a>(&'a [u8]);
+
+impl std::io::Read for FailingReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if self.0.is_empty() {
+            return Err(std::io::Error::other("connection reset"));
+        }
+        self.0.read(buf)
+    }
+}
+
+#[test]
+fn io_error_ends_the_parsing() {
+    let reader = FailingReader(b"type, client, tx, amount\ndeposit, 1, 1, 1.0\n");
+
+    // the parsing ends with the I/O error instead of polling the failing reader forever
+    let results: Vec<_> = parse_transactions(reader, &ParseOptions::default())
+        .take(10)
+        .collect();
+    assert_eq!(results.len(), 2, "unexpected results: {results:?}");
+    assert_ok!(&results[0]);
+    assert_matches!(&results[1], Err(Error::Csv { .. }));
+}
+
+#[rstest]
+#[case::upper_deposit("DEPOSIT", TYPE_KW_DEPOSIT, "1.5")]
+#[case::title_withdrawal("Withdrawal", TYPE_KW_WITHDRAWAL, "1.5")]
+#[case::title_dispute("Dispute", TYPE_KW_DISPUTE, "")]
+#[case::mixed_resolve("ReSoLvE", TYPE_KW_RESOLVE, "")]
+#[case::upper_chargeback("CHARGEBACK", TYPE_KW_CHARGEBACK, "")]
+fn type_keywords_are_case_insensitive(
+    #[case] keyword: &str,
+    #[case] lowercase: &str,
+    #[case] amount: &str,
+) {
+    let row = |kw: &str| format!("type, client, tx, amount\n{kw}, 1, 1, {amount}");
+
+    let actual = parse_csv_ok(&row(keyword));
+    let expected = parse_csv_ok(&row(lowercase));
+    assert_eq!(actual, expected);
+}
+
+#[rstest]
+#[case::tab(b'\t')]
+#[case::semicolon(b';')]
+fn delimited_fixture_parses_like_comma_version(#[case] delimiter: u8) {
+    let path = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/data/two_deposits.csv");
+    let comma_input = std::fs::read_to_string(path).unwrap();
+    let delimited_input = comma_input.replace(',', &(delimiter as char).to_string());
+
+    let options = ParseOptions {
+        delimiter,
+        ..ParseOptions::default()
+    };
+    let actual = parse_transactions(delimited_input.as_bytes(), &options)
+        .collect::<Result<Vec<_>, _>>()
+        .expect("all rows should parse successfully");
+    assert_eq!(actual, parse_csv_ok(&comma_input));
+    assert_eq!(actual.len(), 2);
+}
+
+const HEADERLESS_INPUT: &str = "\
+deposit, 1, 1, 1.0
+deposit, 2, 2, 2.0
+dispute, 1, 1,";
+
+#[test]
+fn headerless_input_is_mapped_by_position() {
+    let options = ParseOptions {
+        has_headers: false,
+        ..ParseOptions::default()
+    };
+    let actual = parse_transactions(HEADERLESS_INPUT.as_bytes(), &options)
+        .collect::<Result<Vec<_>, _>>()
+        .expect("all rows should parse successfully");
+
+    let with_header = format!("type, client, tx, amount\n{HEADERLESS_INPUT}");
+    assert_eq!(actual, parse_csv_ok(&with_header));
+    assert_eq!(actual.len(), 3);
+}
+
+#[test]
+fn headerless_input_is_an_invalid_header_when_headers_are_expected() {
+    let results = parse_csv(HEADERLESS_INPUT);
+
+    // the first deposit is read as the header, which lacks the expected columns
+    assert_eq!(results.len(), 1);
+    assert_matches!(&results[0], Err(Error::Header { .. }));
+}
+
+#[rstest]
+#[case::four_places(TYPE_KW_DEPOSIT, "1.2345", true)]
+#[case::five_places(TYPE_KW_DEPOSIT, "1.23456", false)]
+#[case::trailing_zeros(TYPE_KW_DEPOSIT, "1.23450", true)]
+#[case::withdrawal_four_places(TYPE_KW_WITHDRAWAL, "1.2345", true)]
+#[case::withdrawal_five_places(TYPE_KW_WITHDRAWAL, "1.23456", false)]
+fn amount_precision_is_limited_to_four_decimal_places(
+    #[case] tx_type: &str,
+    #[case] amount: &str,
+    #[case] is_valid: bool,
+) {
+    let input = format!("type, client, tx, amount\n{tx_type}, 1, 1, {amount}");
+
+    let result = parse_csv(&input).into_iter().next().unwrap();
+
+    if is_valid {
+        assert_ok!(result);
+    } else {
+        let err = assert_err!(result);
+        assert_matches!(
+            &err,
+            Error::Validation { message, .. } if message == "amount exceeds 4 decimal places"
+        );
+    }
+}
+
+#[rstest]
+#[case::default_rejects_zero(ParseOptions::default(), 0, false)]
+#[case::default_accepts_one(ParseOptions::default(), 1, true)]
+#[case::range_rejects_inside(ParseOptions { reserved_clients: 900..=999, ..Default::default() }, 950, false)]
+#[case::range_accepts_zero(ParseOptions { reserved_clients: 900..=999, ..Default::default() }, 0, true)]
+#[allow(clippy::reversed_empty_ranges)]
+#[case::empty_range_accepts_zero(ParseOptions { reserved_clients: 1..=0, ..Default::default() }, 0, true)]
+fn reserved_clients_are_rejected(
+    #[case] options: ParseOptions,
+    #[case] client: u16,
+    #[case] is_valid: bool,
+) {
+    let input = format!("type, client, tx, amount\ndeposit, {client}, 1, 5.0");
+
+    let result = parse_transactions(input.as_bytes(), &options)
+        .next()
+        .unwrap();
+
+    if is_valid {
+        assert_ok!(result);
+    } else {
+        let err = assert_err!(result);
+        assert_matches!(err, Error::Validation { client_id, tx_id: 1, .. } if client_id == client);
+    }
+}
+
+#[test]
+fn comment_lines_are_skipped_if_configured() {
+    let plain = "\
+type, client, tx, amount
+deposit, 1, 1, 1.0
+withdrawal, 1, 2, 0.5";
+    let commented = "\
+# deposits first
+type, client, tx, amount
+deposit, 1, 1, 1.0
+# note: partial withdrawal
+withdrawal, 1, 2, 0.5";
+
+    let options = ParseOptions {
+        comment: Some(b'#'),
+        ..Default::default()
+    };
+    let txs = parse_transactions(commented.as_bytes(), &options)
+        .collect::<Result<Vec<_>, _>>()
+        .expect("all rows should parse successfully");
+
+    assert_eq!(txs, parse_csv_ok(plain));
+}
+
+#[test]
+fn comment_lines_are_rejected_by_default() {
+    let input = "\
+type, client, tx, amount
+# note
+deposit, 1, 1, 1.0";
+
+    let results = parse_csv(input);
+
+    assert_eq!(results.len(), 2);
+    assert_err!(&results[0]);
+    assert_ok!(&results[1]);
+}
+
+#[rstest]
+#[case::strict(false)]
+#[case::flexible(true)]
+fn extra_named_column_is_ignored(#[case] flexible: bool) {
+    let input = "\
+type, client, tx, amount, timestamp
+deposit, 1, 1, 1.0, 2024-01-01T10:00:00Z
+dispute, 1, 1, , 2024-01-02T10:00:00Z";
+
+    let options = ParseOptions {
+        flexible,
+        ..Default::default()
+    };
+    let txs = parse_transactions(input.as_bytes(), &options)
+        .collect::<Result<Vec<_>, _>>()
+        .expect("all rows should parse successfully");
+
+    assert_eq!(
+        txs,
+        parse_csv_ok("type, client, tx, amount\ndeposit, 1, 1, 1.0\ndispute, 1, 1,")
+    );
+}
+
+#[test]
+fn trailing_columns_beyond_the_header_are_ignored_if_flexible() {
+    let input = "\
+type, client, tx, amount
+deposit, 1, 1, 1.0, 2024-01-01T10:00:00Z
+withdrawal, 1, 2, 0.5, 2024-01-02T10:00:00Z, extra";
+
+    let strict = parse_csv(input);
+    assert!(strict.iter().all(|r| matches!(r, Err(Error::Csv { .. }))));
+
+    let options = ParseOptions {
+        flexible: true,
+        ..Default::default()
+    };
+    let txs = parse_transactions(input.as_bytes(), &options)
+        .collect::<Result<Vec<_>, _>>()
+        .expect("all rows should parse successfully");
+    assert_eq!(
+        txs,
+        parse_csv_ok("type, client, tx, amount\ndeposit, 1, 1, 1.0\nwithdrawal, 1, 2, 0.5")
+    );
+}
+
+fn parse_ordered(input: &str) -> Vec<Result<Transaction, Error>> {
+    let options = ParseOptions {
+        enforce_ordering: true,
+        ..Default::default()
+    };
+    parse_transactions(input.as_bytes(), &options).collect()
+}
+
+#[test]
+fn out_of_order_rows_are_rejected_if_ordering_is_enforced() {
+    let input = "\
+type, client, tx, amount, timestamp
+deposit, 1, 1, 1.0, 100
+deposit, 1, 2, 1.0, 100
+deposit, 1, 3, 1.0, 50
+deposit, 1, 4, 1.0, 120
+deposit, 1, 5, 1.0, 110";
+
+    let results = parse_ordered(input);
+
+    assert_eq!(results.len(), 5);
+    assert_ok!(&results[0]);
+    assert_ok!(&results[1], "equal timestamps are in order");
+    assert_matches!(&results[2], Err(Error::Validation { tx_id: 3, message, .. }) if message == "timestamp 50 precedes the previous timestamp 100");
+    assert_ok!(&results[3]);
+    assert_matches!(&results[4], Err(Error::Validation { tx_id: 5, .. }));
+}
+
+#[rstest]
+#[case::missing_column("type, client, tx, amount\ndeposit, 1, 1, 1.0")]
+#[case::empty_value("type, client, tx, amount, timestamp\ndeposit, 1, 1, 1.0,")]
+#[case::not_an_integer("type, client, tx, amount, timestamp\ndeposit, 1, 1, 1.0, 2024-01-01")]
+fn rows_without_integer_timestamp_are_rejected_if_ordering_is_enforced(#[case] input: &str) {
+    let results = parse_ordered(input);
+
+    assert_eq!(results.len(), 1);
+    assert_matches!(&results[0], Err(Error::Validation { tx_id: 1, .. }));
+}
+
+#[test]
+fn timestamps_are_ignored_by_default() {
+    let input = "\
+type, client, tx, amount, timestamp
+deposit, 1, 1, 1.0, 100
+deposit, 1, 2, 1.0, 50
+deposit, 1, 3, 1.0, yesterday";
+
+    let txs = parse_csv_ok(input);
+    assert_eq!(txs.len(), 3);
+}
+
+#[rstest]
+#[case::deposit("deposit, 1, 7, 2.5")]
+#[case::dispute_with_trailing_comma("dispute, 1, 7,")]
+fn single_line_is_parsed(#[case] line: &str) {
+    let tx = assert_ok!(parse_line(line, &ParseOptions::default()));
+
+    assert_eq!(tx.client_id(), ClientId::new(1));
+    assert_eq!(tx.tx_id(), TxId::new(7));
+}
+
+#[rstest]
+#[case::empty("")]
+#[case::header("type, client, tx, amount")]
+#[case::missing_column("deposit, 1")]
+#[case::invalid_amount("withdrawal, 1, 7, 0")]
+#[case::reserved_client("deposit, 0, 7, 1.0")]
+fn invalid_single_line_is_rejected(#[case] line: &str) {
+    assert_err!(parse_line(line, &ParseOptions::default()));
+}
+
+#[rstest]
+#[case::crlf("\r\n")]
+#[case::bare_cr("\r")]
+#[case::lf("\n")]
+fn line_endings_do_not_leak_into_the_last_column(#[case] ending: &str) {
+    // the last row has no line ending
+    let input = [
+        "type,client,tx,amount",
+        "deposit,1,1,1.5",
+        "dispute,1,1,",
+        "withdrawal,1,2,0.5",
+    ]
+    .join(ending);
+
+    let txs = parse_csv_ok(&input);
+    assert_eq!(txs.len(), 3, "unexpected transactions: {txs:?}");
+    assert_matches!(txs[0], Transaction::Deposit(d) if d.amount() == dec!(1.5));
+    assert_matches!(txs[1], Transaction::Dispute(_));
+    assert_matches!(txs[2], Transaction::Withdrawal(w) if w.amount() == dec!(0.5));
+}
+
+#[test]
+fn mixed_line_endings_are_parsed() {
+    let input = "type, client, tx, amount\r\ndeposit, 1, 1, 1.5\rdeposit, 1, 2, 2.0\ndispute, 1, 2,\r\nresolve, 1, 2,";
+
+    let txs = parse_csv_ok(input);
+    assert_eq!(txs.len(), 4, "unexpected transactions: {txs:?}");
+    assert_matches!(txs[1], Transaction::Deposit(d) if d.amount() == dec!(2.0));
+    assert_matches!(txs[3], Transaction::Resolve(_));
+}
+
+#[test]
+fn mistyped_header_column_is_reported_once() {
+    let input = "type, client, txid, amount\ndeposit, 1, 1, 1.0\ndeposit, 2, 2, 2.0";
+
+    let results = parse_csv(input);
+    assert_eq!(results.len(), 1, "unexpected results: {results:?}");
+    let Err(Error::Header { line, message }) = &results[0] else {
+        panic!("expected a header error, got: {results:?}");
+    };
+    assert_eq!(*line, Some(1));
+    assert_eq!(
+        message,
+        "missing column(s) tx (found: type, client, txid, amount)"
+    );
+}
+
+#[rstest]
+#[case::line_feeds("type, client, txid, amount\ndeposit, 1, 1, 1.0\n", 1)]
+#[case::header_only("type, client, txid, amount", 1)]
+#[case::after_comments("# one\n# two\ntype, client, txid, amount\ndeposit, 1, 1, 1.0\n", 3)]
+#[case::last_line_after_comments("# one\n# two\ntype, client, txid, amount", 3)]
+#[case::crlf_after_comments("# one\r\n# two\r\ntype, client, txid, amount\r\n", 3)]
+#[case::after_blank_lines("\n\ntype, client, txid, amount\n", 3)]
+fn header_error_carries_the_line_of_the_header(#[case] input: &str, #[case] expected: u64) {
+    let options = ParseOptions {
+        comment: Some(b'#'),
+        ..Default::default()
+    };
+    let results: Vec<_> = parse_transactions(input.as_bytes(), &options).collect();
+
+    assert_matches!(
+        results.as_slice(),
+        [Err(Error::Header { line: Some(line), .. })] if *line == expected
+    );
+}
+
+#[test]
+fn unchecked_header_fails_every_row() {
+    let input = "type, client, txid, amount\ndeposit, 1, 1, 1.0\ndeposit, 2, 2, 2.0";
+    let options = ParseOptions {
+        check_header: false,
+        ..ParseOptions::default()
+    };
+
+    let results: Vec<_> = parse_transactions(input.as_bytes(), &options).collect();
+    assert_eq!(results.len(), 2, "unexpected results: {results:?}");
+    assert!(
+        results
+            .iter()
+            .all(|result| matches!(result, Err(Error::Csv { .. })))
+    );
+}
+
+#[test]
+fn header_with_reordered_and_additional_columns_is_accepted() {
+    let txs = parse_csv_ok("amount, tx, timestamp, client, type\n1.0, 7, 100, 1, deposit");
+    assert_eq!(txs.len(), 1);
+}
+
+#[rstest]
+#[case::resolve("resolve, 1, 2, 1.5", Transaction::Resolve(Resolve::partial(ClientId::new(1), TxId::new(2), dec!(1.5)).unwrap()))]
+#[case::chargeback("chargeback, 1, 2, 1.5", Transaction::Chargeback(Chargeback::partial(ClientId::new(1), TxId::new(2), dec!(1.5)).unwrap()))]
+#[case::without_amount(
+    "resolve, 1, 2,",
+    Transaction::Resolve(Resolve::new(ClientId::new(1), TxId::new(2)))
+)]
+fn settlement_amounts_are_parsed_if_enabled(#[case] row: &str, #[case] expected: Transaction) {
+    let input = format!("type, client, tx, amount\n{row}");
+    let options = ParseOptions {
+        partial_settlements: true,
+        ..Default::default()
+    };
+
+    let result: Vec<_> = parse_transactions(input.as_bytes(), &options).collect();
+
+    assert_matches!(result.as_slice(), [Ok(tx)] if *tx == expected);
+}
+
+#[rstest]
+#[case::zero("resolve, 1, 2, 0.0", "the resolved amount must be positive")]
+#[case::negative("chargeback, 1, 2, -1.0", "the charged back amount must be positive")]
+#[case::too_precise("resolve, 1, 2, 1.23456", "amount exceeds 4 decimal places")]
+fn invalid_settlement_amounts_are_rejected(#[case] row: &str, #[case] expected: &str) {
+    let input = format!("type, client, tx, amount\n{row}");
+    let options = ParseOptions {
+        partial_settlements: true,
+        ..Default::default()
+    };
+
+    let result = parse_transactions(input.as_bytes(), &options)
+        .next()
+        .unwrap();
+
+    let err = assert_err!(result);
+    assert_matches!(&err, Error::Validation { message, .. } if message == expected);
+}
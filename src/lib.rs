@@ -1,21 +1,81 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+// the domain helpers only used by the parsing and processing layers are unused without `std`
+#![cfg_attr(not(feature = "std"), allow(dead_code, unused_imports))]
+
+extern crate alloc;
+
+#[cfg(feature = "std")]
+mod config;
+#[cfg(feature = "std")]
+mod deposit_log;
 mod domain;
+#[cfg(feature = "std")]
 mod engine;
+#[cfg(feature = "std")]
 mod error;
+#[cfg(feature = "std")]
 mod input;
+#[cfg(feature = "std")]
 mod output;
+#[cfg(feature = "std")]
+mod stats;
+#[cfg(feature = "std")]
 mod telemetry;
 
+#[cfg(feature = "std")]
+pub use config::{ChannelCapacity, DEFAULT_CHANNEL_CAPACITY, ProcessConfig};
+#[cfg(feature = "std")]
+pub use deposit_log::{DepositEntry, DepositLog, DepositStatus};
+pub use domain::{ClientId, DisputePolicy, LockReason, ProcessingKind, TxId};
+#[cfg(feature = "std")]
+pub use engine::{EngineSnapshot, TransactionEngine};
+#[cfg(feature = "std")]
 pub use error::Error;
-pub use output::{AccountRecord, TransactionRecord};
-pub use telemetry::setup_logging;
+#[cfg(feature = "std")]
+pub use input::binary::write_transactions_binary;
+#[cfg(feature = "std")]
+pub use output::{
+    AccountFilter, AccountRecord, Column, ColumnSpec, FundsDirection, FundsMoved, LedgerTotals,
+    Mutation, OutputFormat, TransactionRecord, Warning, aggregate, write_columns, write_records,
+};
+#[cfg(feature = "std")]
+pub use stats::ProcessStats;
+#[cfg(feature = "std")]
+pub use telemetry::{setup_logging, setup_logging_with_default};
 
+/// The account state machine without the parsing, output, and threading layers (available without the `std`
+/// feature), see [`Ledger`](ledger::Ledger). This is also the public path of the domain types of the transactions, for
+/// constructing them programmatically, e.g., to process them with `process_transactions_iter()` (requires the `model`
+/// feature) without a round trip through CSV. The constructors reject non-positive amounts; the remaining validation
+/// of the parser is applied when the transactions are processed by the engine.
+pub mod ledger {
+    pub use crate::domain::{
+        Amount, Chargeback, ClientId, Deposit, Dispute, Ledger, LockReason, Money, Open,
+        ProcessingKind, Rejection, Resolve, Transaction, TxId, Unlock, Withdrawal,
+    };
+}
+
+#[cfg(feature = "std")]
+use std::collections::{HashMap, HashSet};
+#[cfg(feature = "std")]
+use std::ops::ControlFlow;
+
+#[cfg(feature = "std")]
+use crate::config::fire;
+#[cfg(feature = "std")]
+use crate::domain::AccountState;
+#[cfg(feature = "std")]
+use crate::engine::continuing;
+#[cfg(feature = "std")]
 use crate::input::parse_transactions;
 
 /// Processes financial transactions from a CSV source and returns per-client account records.
 ///
-/// This is the single public entry point of the crate. It reads CSV-encoded transactions
-/// from `reader`, applies them to in-memory account state, and yields the final balances
-/// as an iterator of [`AccountRecord`]s ready for serialization.
+/// This is the basic entry point of the crate, with the default configuration. It reads CSV-encoded
+/// transactions from `reader`, applies them to in-memory account state, and yields the final balances
+/// as an iterator of [`AccountRecord`]s ready for serialization. The other entry points build on it,
+/// e.g., [`process_with_config()`] for a custom configuration or [`process_parallel()`] for sharding the
+/// clients across workers.
 ///
 /// # Callbacks
 ///
@@ -23,11 +83,10 @@ use crate::input::parse_transactions;
 ///
 /// - **`on_error`** — invoked for every transaction that cannot be processed (malformed CSV
 ///   row, domain validation failure, or a processing error such as insufficient funds).
-///   The transaction is skipped and processing continues.
+///   The transaction is skipped and processing continues (see [`process_until()`] for stopping early).
 /// - **`on_success`** — invoked with each [`TransactionRecord`] that was
 ///   successfully applied. Useful for logging, metrics, publishing, or progress tracking.
 ///
-///
 /// # Example
 ///
 /// ```no_run
@@ -48,15 +107,187 @@ use crate::input::parse_transactions;
 /// }
 /// wtr.flush().unwrap();
 /// ```
+#[cfg(feature = "std")]
 #[must_use = "this iterator is lazy and must be consumed to process the account states"]
 pub fn process(
     reader: impl std::io::Read,
     on_error: impl FnMut(Error),
     on_success: impl FnMut(TransactionRecord),
 ) -> impl Iterator<Item = AccountRecord> {
-    let results = parse_transactions(reader);
-    let accounts = engine::process_transactions(results, on_error, on_success);
-    output::to_account_records(accounts)
+    let config = ProcessConfig::default();
+    let results = parse_transactions(reader, &config.parse);
+    let accounts = engine::process_transactions(
+        results,
+        continuing(on_error),
+        continuing(on_success),
+        &config,
+    );
+    to_output(accounts, config)
+}
+
+/// In-memory variant of [`process()`], taking the CSV input as a string, e.g., for tests or for input which was
+/// already read in full.
+///
+/// # Example
+///
+/// ```
+/// use rust_decimal_macros::dec;
+/// use tx_engine_rs::process_str;
+///
+/// let input = "type, client, tx, amount\ndeposit, 1, 1, 2.5\nwithdrawal, 1, 2, 1.0\n";
+/// let records: Vec<_> = process_str(input, |_| {}, |_| {}).collect();
+///
+/// assert_eq!(records[0].available, dec!(1.5));
+/// ```
+#[cfg(feature = "std")]
+#[must_use = "this iterator is lazy and must be consumed to process the account states"]
+pub fn process_str(
+    input: &str,
+    on_error: impl FnMut(Error),
+    on_success: impl FnMut(TransactionRecord),
+) -> impl Iterator<Item = AccountRecord> {
+    process(input.as_bytes(), on_error, on_success)
+}
+
+/// Variant of [`process()`] folding the account records into a single value as they are produced, e.g., to compute
+/// aggregates over a large number of clients in one pass without materializing the records.
+///
+/// # Example
+///
+/// ```
+/// use rust_decimal_macros::dec;
+/// use tx_engine_rs::process_fold;
+///
+/// let input = "type, client, tx, amount\ndeposit, 1, 1, 1.5\ndeposit, 2, 2, 2.0\n";
+/// let (accounts, available) = process_fold(
+///     input.as_bytes(),
+///     (0, dec!(0)),
+///     |(accounts, available), record| (accounts + 1, available + record.available),
+///     |_| {},
+///     |_| {},
+/// );
+///
+/// assert_eq!(accounts, 2);
+/// assert_eq!(available, dec!(3.5));
+/// ```
+#[cfg(feature = "std")]
+pub fn process_fold<B>(
+    reader: impl std::io::Read,
+    init: B,
+    f: impl FnMut(B, AccountRecord) -> B,
+    on_error: impl FnMut(Error),
+    on_success: impl FnMut(TransactionRecord),
+) -> B {
+    process(reader, on_error, on_success).fold(init, f)
+}
+
+/// Variant of [`process()`] collecting the account records into a map by client id, for random access to the
+/// accounts after the run.
+///
+/// # Example
+///
+/// ```
+/// use rust_decimal_macros::dec;
+/// use tx_engine_rs::process_to_map;
+///
+/// let input = "type, client, tx, amount\ndeposit, 1, 1, 1.5\ndeposit, 2, 2, 2.0\n";
+/// let accounts = process_to_map(input.as_bytes(), |_| {}, |_| {});
+///
+/// assert_eq!(accounts[&2].available, dec!(2.0));
+/// assert!(accounts.get(&3).is_none());
+/// ```
+#[cfg(feature = "std")]
+pub fn process_to_map(
+    reader: impl std::io::Read,
+    on_error: impl FnMut(Error),
+    on_success: impl FnMut(TransactionRecord),
+) -> HashMap<u16, AccountRecord> {
+    process(reader, on_error, on_success)
+        .map(|record| (record.client, record))
+        .collect()
+}
+
+/// Variant of [`process()`] reading the transactions from several sources, e.g., the shard files of one ledger.
+///
+/// The sources are parsed one after the other into a single stream of transactions, which is applied to one set of
+/// accounts — just as if the files had been concatenated, but with the header of each file skipped. Each file is
+/// parsed by its own header, so the files may order their columns differently. An empty file contributes no
+/// transactions.
+#[cfg(feature = "std")]
+#[must_use = "this iterator is lazy and must be consumed to process the account states"]
+pub fn process_many<R: std::io::Read>(
+    readers: impl IntoIterator<Item = R>,
+    on_error: impl FnMut(Error),
+    on_success: impl FnMut(TransactionRecord),
+) -> impl Iterator<Item = AccountRecord> {
+    let config = ProcessConfig::default();
+    let results = readers
+        .into_iter()
+        .flat_map(|reader| parse_transactions(reader, &config.parse));
+    let accounts = engine::process_transactions(
+        results,
+        continuing(on_error),
+        continuing(on_success),
+        &config,
+    );
+    to_output(accounts, config)
+}
+
+/// Verifies that the shards of a pre-sharded input (e.g., the files to be passed to separate [`process()`] calls) are
+/// disjoint by client, i.e., that no client has transactions in more than one shard, which would otherwise silently
+/// yield a partial account per shard. Returns the ids of the clients found in several shards, in ascending order.
+///
+/// The readers are consumed by the check, so that the shards need to be read again for processing. Only the rows
+/// parsed as valid transactions are considered, since the others are rejected anyway.
+///
+/// # Example
+///
+/// ```
+/// use tx_engine_rs::verify_sharding;
+///
+/// let shard_a = "type, client, tx, amount\ndeposit, 1, 1, 1.0\ndeposit, 2, 2, 1.0\n";
+/// let shard_b = "type, client, tx, amount\ndeposit, 3, 3, 1.0\nwithdrawal, 2, 4, 1.0\n";
+///
+/// assert_eq!(verify_sharding([shard_a.as_bytes(), shard_b.as_bytes()]), Err(vec![2]));
+/// ```
+#[cfg(feature = "std")]
+pub fn verify_sharding<R: std::io::Read>(
+    readers: impl IntoIterator<Item = R>,
+) -> Result<(), Vec<u16>> {
+    let options = input::ParseOptions::default();
+    // the shard in which each client was seen first
+    let mut shards: HashMap<ClientId, usize> = HashMap::new();
+    let mut offending = std::collections::BTreeSet::new();
+    for (shard, reader) in readers.into_iter().enumerate() {
+        for tx in parse_transactions(reader, &options).flatten() {
+            let client_id = tx.client_id();
+            if *shards.entry(client_id).or_insert(shard) != shard {
+                offending.insert(u16::from(client_id));
+            }
+        }
+    }
+    if offending.is_empty() {
+        Ok(())
+    } else {
+        Err(offending.into_iter().collect())
+    }
+}
+
+/// Variant of [`process()`] reading gzip-compressed CSV input, e.g., from a `.csv.gz` file (requires the `gzip` feature).
+///
+/// The input is decompressed on the fly while it is parsed, so it never needs to be decompressed in full.
+#[cfg(feature = "gzip")]
+#[must_use = "this iterator is lazy and must be consumed to process the account states"]
+pub fn process_gzip(
+    reader: impl std::io::Read,
+    on_error: impl FnMut(Error),
+    on_success: impl FnMut(TransactionRecord),
+) -> impl Iterator<Item = AccountRecord> {
+    process(
+        flate2::read::MultiGzDecoder::new(reader),
+        on_error,
+        on_success,
+    )
 }
 
 /// Parallel variant — client-sharded, multi-threaded processing.
@@ -64,7 +295,9 @@ pub fn process(
 /// Designed for standalone batch processing of large inputs where
 /// the engine itself must shard and parallelise. For pre-sharded streams
 /// (e.g., in a distributed deployment), prefer [`process()`] which avoids
-/// threading overhead entirely.
+/// threading overhead entirely; [`verify_sharding()`] checks that the shards
+/// are disjoint by client.
+#[cfg(feature = "std")]
 pub fn process_parallel(
     reader: impl std::io::Read,
     on_error: impl FnMut(Error) + Send,
@@ -72,20 +305,548 @@ pub fn process_parallel(
     num_workers: usize,
     channel_capacity: usize,
 ) -> impl Iterator<Item = AccountRecord> {
-    let num_workers = if num_workers == 0 {
-        tracing::warn!("num_workers set to 0, defaulting to 1");
-        1
-    } else {
-        num_workers
-    };
+    let config = ProcessConfig::default()
+        .workers(num_workers)
+        .channel_capacity(channel_capacity);
+    process_with_config(reader, config, on_error, on_success)
+}
+
+/// Configurable variant of [`process()`] and [`process_parallel()`].
+///
+/// Processes the transactions as configured by the given [`ProcessConfig`] — sequentially by default,
+/// or with the parallel engine if [`ProcessConfig::workers`] is set. The callbacks must be `Send`
+/// since they may be moved to dedicated threads.
+///
+/// # Example
+///
+/// ```
+/// use tx_engine_rs::{ProcessConfig, process_with_config};
+///
+/// let input = "type, client, tx, amount\ndeposit, 1, 1, 1.2346\n";
+/// let config = ProcessConfig::default().workers(2).rounding(2);
+///
+/// let records: Vec<_> = process_with_config(input.as_bytes(), config, |_| {}, |_| {}).collect();
+/// assert_eq!(records[0].available.to_string(), "1.23");
+/// ```
+#[cfg(feature = "std")]
+#[must_use = "this iterator is lazy and must be consumed to process the account states"]
+pub fn process_with_config(
+    reader: impl std::io::Read,
+    config: ProcessConfig<'_>,
+    on_error: impl FnMut(Error) + Send,
+    on_success: impl FnMut(TransactionRecord) + Send,
+) -> impl Iterator<Item = AccountRecord> {
+    let (accounts, _) = run(
+        reader,
+        &config,
+        continuing(on_error),
+        continuing(on_success),
+    );
+    to_output(accounts.into_iter().flatten(), config)
+}
+
+/// Variant of [`process_with_config()`] which additionally returns the [`ProcessStats`] of the run.
+///
+/// # Example
+///
+/// ```
+/// use tx_engine_rs::{ProcessConfig, process_with_stats};
+///
+/// let input = "type, client, tx, amount\ndeposit, 1, 1, 5.0\nwithdrawal, 1, 2, 7.0\n";
+/// let (records, stats) = process_with_stats(input.as_bytes(), ProcessConfig::default(), |_| {}, |_| {});
+///
+/// assert_eq!(records.count(), 1);
+/// assert_eq!(stats.accepted, 1);
+/// assert_eq!(stats.processing_errors, 1);
+/// ```
+#[cfg(feature = "std")]
+pub fn process_with_stats(
+    reader: impl std::io::Read,
+    config: ProcessConfig<'_>,
+    on_error: impl FnMut(Error) + Send,
+    on_success: impl FnMut(TransactionRecord) + Send,
+) -> (impl Iterator<Item = AccountRecord>, ProcessStats) {
+    let (accounts, stats) = run(
+        reader,
+        &config,
+        continuing(on_error),
+        continuing(on_success),
+    );
+    (to_output(accounts.into_iter().flatten(), config), stats)
+}
+
+/// Variant of [`process_with_config()`] which additionally returns the [`DepositLog`] of the run: every accepted
+/// deposit with its final status, e.g., for answering audit queries about individual transactions after the run.
+/// The log retains all deposits, so that its memory grows with the input; use it only if such queries are needed.
+///
+/// # Example
+///
+/// ```
+/// use rust_decimal_macros::dec;
+/// use tx_engine_rs::{DepositStatus, ProcessConfig, process_with_deposit_log};
+///
+/// let input = "type, client, tx, amount\ndeposit, 1, 1, 5.0\ndispute, 1, 1,\nchargeback, 1, 1,\n";
+/// let (records, deposits) =
+///     process_with_deposit_log(input.as_bytes(), ProcessConfig::default(), |_| {}, |_| {});
+///
+/// assert_eq!(records.count(), 1);
+/// let deposit = deposits.get(1).unwrap();
+/// assert_eq!(deposit.amount, dec!(5.0));
+/// assert_eq!(deposit.status, DepositStatus::ChargedBack);
+/// ```
+#[cfg(feature = "std")]
+pub fn process_with_deposit_log(
+    reader: impl std::io::Read,
+    config: ProcessConfig<'_>,
+    on_error: impl FnMut(Error) + Send,
+    mut on_success: impl FnMut(TransactionRecord) + Send,
+) -> (impl Iterator<Item = AccountRecord>, DepositLog) {
+    let mut deposits = DepositLog::default();
+    // the outcomes of a client are reported in input order, also by the parallel engine
+    let (accounts, _) = run(
+        reader,
+        &config,
+        continuing(on_error),
+        continuing(|tx| {
+            deposits.record(&tx);
+            on_success(tx);
+        }),
+    );
+    (to_output(accounts.into_iter().flatten(), config), deposits)
+}
+
+/// Variant of [`process_with_config()`] whose callbacks decide whether to continue the processing.
+///
+/// Once a callback returns [`ControlFlow::Break`], e.g., after an error indicating a corrupt input, the remaining
+/// input is skipped and the account records reflect the transactions processed so far. With the parallel engine,
+/// the workers discard the transactions still queued for them; transactions a worker applied before noticing the
+/// break stay applied, but are no longer reported to the callbacks.
+///
+/// # Example
+///
+/// ```
+/// use std::ops::ControlFlow;
+/// use tx_engine_rs::{ProcessConfig, process_until};
+///
+/// let input = "type, client, tx, amount\ndeposit, 1, 1, 5.0\ndeposit, 1, x, 1.0\ndeposit, 1, 3, 2.0\n";
+/// let records: Vec<_> = process_until(
+///     input.as_bytes(),
+///     ProcessConfig::default(),
+///     |_| ControlFlow::Break(()),
+///     |_| ControlFlow::Continue(()),
+/// )
+/// .collect();
+///
+/// // the deposit following the malformed row is not applied
+/// assert_eq!(records[0].available.to_string(), "5.0");
+/// ```
+#[cfg(feature = "std")]
+pub fn process_until(
+    reader: impl std::io::Read,
+    config: ProcessConfig<'_>,
+    on_error: impl FnMut(Error) -> ControlFlow<()> + Send,
+    on_success: impl FnMut(TransactionRecord) -> ControlFlow<()> + Send,
+) -> impl Iterator<Item = AccountRecord> {
+    let (accounts, _) = run(reader, &config, on_error, on_success);
+    to_output(accounts.into_iter().flatten(), config)
+}
+
+/// Async variant of [`process()`], consuming the transactions from a [`Stream`](futures_util::Stream)
+/// instead of a CSV reader (requires the `async` feature).
+///
+/// The transactions are applied one by one with the sequential engine as they are yielded by the stream,
+/// so that the input never needs to be buffered in full. The provided records go through the same validation
+/// as parsed CSV rows; errors yielded by the stream (e.g., from decoding the transactions) are passed to
+/// `on_error`. The account records are available once the stream is exhausted. [`ProcessConfig::workers`]
+/// has no effect here.
+///
+/// # Example
+///
+/// ```
+/// # tokio::runtime::Builder::new_current_thread().build().unwrap().block_on(async {
+/// use futures_util::stream;
+/// use rust_decimal_macros::dec;
+/// use tx_engine_rs::{ProcessConfig, TransactionRecord, process_stream};
+///
+/// let transactions = stream::iter([
+///     Ok(TransactionRecord::Deposit { client: 1, tx: 1, amount: dec!(2.5) }),
+///     Ok(TransactionRecord::Withdrawal { client: 1, tx: 2, amount: dec!(1.0) }),
+/// ]);
+///
+/// let records: Vec<_> = process_stream(transactions, ProcessConfig::default(), |_| {}, |_| {})
+///     .await
+///     .collect();
+/// assert_eq!(records[0].available, dec!(1.5));
+/// # });
+/// ```
+#[cfg(feature = "async")]
+pub async fn process_stream<'a>(
+    stream: impl futures_util::Stream<Item = Result<TransactionRecord, Error>>,
+    config: ProcessConfig<'a>,
+    on_error: impl FnMut(Error),
+    on_success: impl FnMut(TransactionRecord),
+) -> impl Iterator<Item = AccountRecord> + 'a {
+    use futures_util::StreamExt;
 
-    let results = parse_transactions(reader);
-    let accounts = engine::process_transactions_parallel(
+    let transactions = stream
+        .map(|result| result.and_then(|record| input::validate_record(record, &config.parse)));
+    let accounts = engine::process_transaction_stream(
+        transactions,
+        continuing(on_error),
+        continuing(on_success),
+        &config,
+    )
+    .await;
+    to_output(accounts, config)
+}
+
+/// Variant of [`process_with_config()`] processing transactions constructed programmatically (requires the `model`
+/// feature) instead of parsing them from CSV.
+///
+/// The transactions go through the same validation as parsed CSV rows beyond the one of their constructors, e.g., an
+/// amount with more than four decimal places or a reserved client id is reported to `on_error`.
+///
+/// # Example
+///
+/// ```
+/// use rust_decimal_macros::dec;
+/// use tx_engine_rs::{ProcessConfig, process_transactions_iter};
+/// use tx_engine_rs::ledger::{ClientId, Deposit, Dispute, Transaction, TxId};
+///
+/// let client = ClientId::new(1);
+/// let transactions = [
+///     Transaction::Deposit(Deposit::new(client, TxId::new(1), dec!(2.5)).unwrap()),
+///     Transaction::Dispute(Dispute::new(client, TxId::new(1))),
+/// ];
+///
+/// let records: Vec<_> =
+///     process_transactions_iter(transactions, ProcessConfig::default(), |_| {}, |_| {}).collect();
+/// assert_eq!(records[0].held, dec!(2.5));
+/// ```
+#[cfg(feature = "model")]
+#[must_use = "this iterator is lazy and must be consumed to process the account states"]
+pub fn process_transactions_iter(
+    transactions: impl IntoIterator<Item = ledger::Transaction>,
+    config: ProcessConfig<'_>,
+    on_error: impl FnMut(Error) + Send,
+    on_success: impl FnMut(TransactionRecord) + Send,
+) -> impl Iterator<Item = AccountRecord> {
+    let results = transactions
+        .into_iter()
+        .map(|tx| input::validate_record(TransactionRecord::from_domain(&tx), &config.parse));
+    let (accounts, _) = run_transactions(
         results,
-        on_error,
-        on_success,
-        num_workers,
-        channel_capacity,
+        &config,
+        continuing(on_error),
+        continuing(on_success),
     );
-    output::to_account_records(accounts)
+    to_output(accounts.into_iter().flatten(), config)
+}
+
+/// Parses and validates a single CSV row, e.g., to check the rows of an integration individually without running
+/// the engine. The row has no header and is mapped by position: type, client, tx, amount. It is validated like a row
+/// passed to [`process()`], but not applied to any account, so that, e.g., a withdrawal exceeding a balance is
+/// accepted.
+///
+/// # Example
+///
+/// ```
+/// use rust_decimal_macros::dec;
+/// use tx_engine_rs::{TransactionRecord, parse_line};
+///
+/// let record = parse_line("deposit, 1, 7, 2.5").unwrap();
+/// assert_eq!(record, TransactionRecord::Deposit { client: 1, tx: 7, amount: dec!(2.5) });
+///
+/// assert!(parse_line("deposit, 1, 8, -2.5").is_err());
+/// ```
+#[cfg(feature = "std")]
+pub fn parse_line(line: &str) -> Result<TransactionRecord, Error> {
+    input::parse_line(line, &input::ParseOptions::default())
+        .map(|tx| TransactionRecord::from_domain(&tx))
+}
+
+/// Parses and validates the rows of a CSV input like [`process()`] does, without applying them to any account, e.g.,
+/// to check an input up front or to measure the parsing on its own. The results are produced lazily, in input order.
+///
+/// # Example
+///
+/// ```
+/// use tx_engine_rs::parse_records;
+///
+/// let input = "type, client, tx, amount\ndeposit, 1, 1, 2.5\ndeposit, 1, 2, -1.0\n";
+/// let results: Vec<_> = parse_records(input.as_bytes()).collect();
+///
+/// assert!(results[0].is_ok());
+/// assert!(results[1].is_err());
+/// ```
+#[cfg(feature = "std")]
+pub fn parse_records(
+    reader: impl std::io::Read,
+) -> impl Iterator<Item = Result<TransactionRecord, Error>> {
+    parse_transactions(reader, &input::ParseOptions::default())
+        .map(|result| result.map(|tx| TransactionRecord::from_domain(&tx)))
+}
+
+/// Variant of [`process_with_config()`] reading the transactions in the compact binary encoding written by
+/// [`write_transactions_binary()`] instead of CSV, e.g., to replay a pre-encoded input without the cost of parsing it.
+/// The records are validated like the rows of a CSV input; the options concerning the CSV syntax (e.g., the
+/// delimiter) do not apply.
+///
+/// Every transaction is a fixed-width record of 15 bytes, with the integers in little-endian byte order: the type
+/// (1 byte: 0 deposit, 1 withdrawal, 2 dispute, 3 resolve, 4 chargeback, 5 open, 6 unlock), the client (`u16`), the
+/// tx (`u32`), and the amount scaled by 10^4 (`i64`, zero for the types without an amount). An I/O error of the
+/// reader, or an input ending within a record, is reported as [`Error::Binary`] and ends the input.
+///
+/// # Example
+///
+/// ```
+/// use rust_decimal_macros::dec;
+/// use tx_engine_rs::{ProcessConfig, TransactionRecord, process_binary, write_transactions_binary};
+///
+/// let mut input = Vec::new();
+/// write_transactions_binary(
+///     [
+///         TransactionRecord::Deposit { client: 1, tx: 1, amount: dec!(2.5) },
+///         TransactionRecord::Withdrawal { client: 1, tx: 2, amount: dec!(1.0) },
+///     ],
+///     &mut input,
+/// )
+/// .unwrap();
+///
+/// let records: Vec<_> =
+///     process_binary(input.as_slice(), ProcessConfig::default(), |_| {}, |_| {}).collect();
+/// assert_eq!(records[0].available, dec!(1.5));
+/// ```
+#[cfg(feature = "std")]
+#[must_use = "this iterator is lazy and must be consumed to process the account states"]
+pub fn process_binary(
+    reader: impl std::io::Read,
+    config: ProcessConfig<'_>,
+    on_error: impl FnMut(Error) + Send,
+    on_success: impl FnMut(TransactionRecord) + Send,
+) -> impl Iterator<Item = AccountRecord> {
+    let results = input::binary::parse_transactions_binary(reader, &config.parse);
+    let (accounts, _) = run_transactions(
+        results,
+        &config,
+        continuing(on_error),
+        continuing(on_success),
+    );
+    to_output(accounts.into_iter().flatten(), config)
+}
+
+/// Decodes and validates the records of a binary input like [`process_binary()`] does, without applying them to any
+/// account. The results are produced lazily, in input order.
+#[cfg(feature = "std")]
+pub fn parse_records_binary(
+    reader: impl std::io::Read,
+) -> impl Iterator<Item = Result<TransactionRecord, Error>> {
+    input::binary::parse_transactions_binary(reader, &input::ParseOptions::default())
+        .map(|result| result.map(|tx| TransactionRecord::from_domain(&tx)))
+}
+
+/// Runs the configured engine on the transactions provided by the reader, see [`run_transactions`]
+#[cfg(feature = "std")]
+fn run(
+    reader: impl std::io::Read,
+    config: &ProcessConfig<'_>,
+    on_error: impl FnMut(Error) -> ControlFlow<()> + Send,
+    on_success: impl FnMut(TransactionRecord) -> ControlFlow<()> + Send,
+) -> (Vec<HashMap<ClientId, AccountState>>, ProcessStats) {
+    let results = parse_transactions(reader, &config.parse);
+    run_transactions(results, config, on_error, on_success)
+}
+
+/// Runs the configured engine on the given (parsing results of) transactions and returns the final account states (in
+/// one partition, unless the partitions of the parallel workers are kept as configured), together with the statistics
+/// of the run, which are also passed to the `on_complete` hook.
+#[cfg(feature = "std")]
+fn run_transactions(
+    results: impl IntoIterator<Item = Result<domain::Transaction, Error>>,
+    config: &ProcessConfig<'_>,
+    mut on_error: impl FnMut(Error) -> ControlFlow<()> + Send,
+    mut on_success: impl FnMut(TransactionRecord) -> ControlFlow<()> + Send,
+) -> (Vec<HashMap<ClientId, AccountState>>, ProcessStats) {
+    let results = limit_rows(results, config.max_rows);
+    // counted in separate closures, as the callbacks may run on separate threads
+    let mut error_stats = ProcessStats::default();
+    let mut accepted = 0;
+    let mut checksum = 0u64;
+    let on_error = |e: Error| {
+        error_stats.record_error(&e);
+        on_error(e)
+    };
+    let on_success = |tx: TransactionRecord| {
+        accepted += 1;
+        checksum = checksum.wrapping_add(stats::transaction_hash(&tx));
+        on_success(tx)
+    };
+
+    let (partitions, backpressure_waits, workers) = match config.workers {
+        None => (
+            vec![engine::process_transactions(
+                results, on_error, on_success, config,
+            )],
+            0,
+            0,
+        ),
+        Some(requested) => {
+            let num_workers = config.effective_workers().expect("workers are configured");
+            if requested == 0 {
+                tracing::warn!("num_workers set to 0, defaulting to 1");
+            } else if num_workers < requested {
+                tracing::warn!(
+                    "num_workers clamped from {requested} to the available parallelism of {num_workers}"
+                );
+            }
+            let (num_workers, results) =
+                fit_workers(results, num_workers, config.min_rows_per_worker);
+            let (partitions, backpressure) = engine::process_transactions_parallel(
+                results,
+                on_error,
+                on_success,
+                num_workers,
+                config.channel_capacity.resolve(num_workers),
+                config,
+            );
+            let total = backpressure.iter().sum();
+            fire(&config.hooks.on_backpressure, || backpressure);
+            if config.stream_worker_output {
+                (partitions, total, num_workers)
+            } else {
+                (
+                    vec![partitions.into_iter().flatten().collect()],
+                    total,
+                    num_workers,
+                )
+            }
+        }
+    };
+
+    let stats = ProcessStats {
+        accepted,
+        accounts: partitions.iter().map(HashMap::len).sum::<usize>() as u64,
+        checksum,
+        workers: workers as u64,
+        backpressure_waits,
+        ..error_stats
+    };
+    fire(&config.hooks.on_complete, || stats);
+    (partitions, stats)
+}
+
+/// Reduces the number of workers for a small input if `min_rows_per_worker` is set: if the input ends within the
+/// `min_rows_per_worker` rows per worker read ahead, one worker per `min_rows_per_worker` rows (at least one) and per
+/// distinct client is used. Returns the number of workers and all results, including the ones read ahead.
+#[cfg(feature = "std")]
+fn fit_workers(
+    results: impl Iterator<Item = Result<domain::Transaction, Error>>,
+    num_workers: usize,
+    min_rows_per_worker: Option<usize>,
+) -> (
+    usize,
+    impl Iterator<Item = Result<domain::Transaction, Error>>,
+) {
+    let mut results = results;
+    let Some(min_rows) = min_rows_per_worker else {
+        return (num_workers, Vec::new().into_iter().chain(results));
+    };
+    let read_ahead = min_rows.saturating_mul(num_workers);
+    let prefix: Vec<_> = results.by_ref().take(read_ahead).collect();
+    if prefix.len() == read_ahead {
+        return (num_workers, prefix.into_iter().chain(results));
+    }
+
+    let clients: HashSet<ClientId> = prefix
+        .iter()
+        .filter_map(|result| result.as_ref().ok())
+        .map(domain::Transaction::client_id)
+        .collect();
+    let fitted = (prefix.len() / min_rows.max(1))
+        .min(clients.len())
+        .clamp(1, num_workers);
+    if fitted < num_workers {
+        tracing::info!(
+            "num_workers reduced from {num_workers} to {fitted} for {} rows of {} clients",
+            prefix.len(),
+            clients.len()
+        );
+    }
+    (fitted, prefix.into_iter().chain(results))
+}
+
+/// Passes on at most `max_rows` results (all if `None`). If there are more, a single [`Error::Truncated`] follows and
+/// the remaining results are skipped.
+#[cfg(feature = "std")]
+fn limit_rows<T>(
+    results: impl IntoIterator<Item = Result<T, Error>>,
+    max_rows: Option<usize>,
+) -> impl Iterator<Item = Result<T, Error>> {
+    let mut results = results.into_iter();
+    let mut remaining = max_rows;
+    let mut truncated = false;
+    std::iter::from_fn(move || {
+        if truncated {
+            return None;
+        }
+        match &mut remaining {
+            None => results.next(),
+            Some(0) => {
+                // the row beyond the limit is only read to tell whether there is one
+                let _ = results.next()?;
+                truncated = true;
+                Some(Err(Error::Truncated {
+                    max_rows: max_rows.unwrap_or_default(),
+                }))
+            }
+            Some(remaining) => {
+                *remaining -= 1;
+                results.next()
+            }
+        }
+    })
+}
+
+/// Converts the final account states into the output records, applying the sorting, the settlement pass, the check for
+/// precision loss, and rounding if configured.
+#[cfg(feature = "std")]
+pub(crate) fn to_output<'a>(
+    accounts: impl IntoIterator<Item = (ClientId, AccountState)> + 'a,
+    config: ProcessConfig<'a>,
+) -> impl Iterator<Item = AccountRecord> + 'a {
+    let settle = config.settle_open_disputes;
+    let rounding = config.rounding;
+    let on_settled = config.hooks.on_settled_account;
+    let on_precision_loss = config.hooks.on_precision_loss;
+    let include_num_transactions = config.include_num_transactions;
+    let output_scale = rounding.unwrap_or(input::MAX_AMOUNT_SCALE);
+
+    let accounts: Box<dyn Iterator<Item = (ClientId, AccountState)> + 'a> = if config.sort_by_client
+    {
+        let mut sorted: Vec<_> = accounts.into_iter().collect();
+        sorted.sort_unstable_by_key(|(client_id, _)| u16::from(*client_id));
+        Box::new(sorted.into_iter())
+    } else {
+        Box::new(accounts.into_iter())
+    };
+
+    accounts
+        .map(move |(client_id, state)| {
+            let record = AccountRecord::with_details(client_id, &state, include_num_transactions);
+            fire(&on_settled, || record.settled(&state));
+            if settle {
+                record.settled(&state)
+            } else {
+                record
+            }
+        })
+        .inspect(move |record| {
+            if on_precision_loss.is_some() && record.rounded(output_scale) != *record {
+                fire(&on_precision_loss, || *record);
+            }
+        })
+        .map(move |record| match rounding {
+            Some(decimal_places) => record.rounded(decimal_places),
+            None => record,
+        })
 }
@@ -1,32 +1,181 @@
-use anyhow::Result;
-use std::{env, fs::File};
-use tx_engine_rs::{Error, TransactionRecord, process, setup_logging};
+use anyhow::{Context, Result};
+use std::{
+    env,
+    fs::File,
+    io::{self, IsTerminal, Read, Write},
+};
+use tracing::Level;
+use tx_engine_rs::{
+    Error, OutputFormat, ProcessConfig, TransactionRecord, process_with_stats,
+    setup_logging_with_default, write_records,
+};
+
+const USAGE: &str = "Usage: tx-engine-rs [<input.csv> | -] [<output.csv> | -o <output.csv>] [--parallel <workers>] [--channel-capacity <capacity>] [--summary] [-v | -vv]";
 
 fn main() -> Result<()> {
-    setup_logging();
+    let args = Args::parse(env::args().skip(1))?;
+    setup_logging_with_default(args.log_level());
 
-    let reader = get_reader()?;
-    let writer = get_writer();
+    let reader = get_reader(args.input.as_deref())?;
+    let writer = get_writer(args.output.as_deref())?;
+    let format = get_output_format()?;
 
-    let mut wtr = csv::Writer::from_writer(writer);
-    for record in process(reader, handle_tx_error, handle_tx_success) {
-        wtr.serialize(&record)?;
+    let (records, stats) =
+        process_with_stats(reader, args.config(), handle_tx_error, handle_tx_success);
+    let mut locked = 0;
+    let written = write_records(
+        records.inspect(|record| locked += u64::from(record.locked)),
+        writer,
+        format,
+    );
+    match written {
+        // the reader of the output is gone (e.g., `head`), which is not an error of the run
+        Err(error) if error.kind() == io::ErrorKind::BrokenPipe => return Ok(()),
+        written => written?,
     }
-    wtr.flush()?;
 
+    if args.summary {
+        eprintln!(
+            "processed {} txs, {} rejected, {} accounts, {locked} locked",
+            stats.accepted + stats.rejected(),
+            stats.rejected(),
+            stats.accounts
+        );
+    }
     Ok(())
 }
 
-fn get_reader() -> Result<impl std::io::Read> {
-    let path = env::args()
-        .nth(1)
-        .ok_or_else(|| anyhow::anyhow!("Usage: tx-engine-rs <input.csv>"))?;
-    let file = File::open(&path)?;
-    Ok(file)
+/// The command line arguments. Without `--parallel`, the transactions are processed sequentially, which keeps the
+/// output reproducible.
+struct Args {
+    /// The path of the input file; the input is read from stdin if it is `-` or missing
+    input: Option<String>,
+    /// The path of the output file; the output is written to stdout if it is missing
+    output: Option<String>,
+    workers: Option<usize>,
+    channel_capacity: Option<usize>,
+    /// Whether a one-line summary of the run is printed to stderr at completion
+    summary: bool,
+    /// The number of `-v` flags, raising the log level unless `RUST_LOG` is set
+    verbosity: usize,
+}
+
+impl Args {
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Self> {
+        let mut input = None;
+        let mut output = None;
+        let mut workers = None;
+        let mut channel_capacity = None;
+        let mut summary = false;
+        let mut verbosity = 0;
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--parallel" => workers = Some(parse_flag_value(&arg, args.next())?),
+                "--channel-capacity" => {
+                    channel_capacity = Some(parse_flag_value(&arg, args.next())?)
+                }
+                "--summary" => summary = true,
+                "-v" => verbosity += 1,
+                "-vv" => verbosity += 2,
+                "-o" | "--output" => output = Some(flag_value(&arg, args.next())?),
+                // `-` alone is a path (stdin or stdout), any other dash-prefixed argument a flag
+                flag if flag.starts_with('-') && flag != "-" => {
+                    anyhow::bail!("unknown flag {flag}\n{USAGE}")
+                }
+                _ if input.is_none() => input = Some(arg),
+                _ if output.is_none() => output = Some(arg),
+                _ => anyhow::bail!("unexpected argument {arg}\n{USAGE}"),
+            }
+        }
+
+        Ok(Self {
+            input,
+            output,
+            workers,
+            channel_capacity,
+            summary,
+            verbosity,
+        })
+    }
+
+    /// The default log level: `info`, `debug` with `-v`, and `trace` with `-vv`
+    fn log_level(&self) -> Level {
+        match self.verbosity {
+            0 => Level::INFO,
+            1 => Level::DEBUG,
+            _ => Level::TRACE,
+        }
+    }
+
+    fn config(&self) -> ProcessConfig<'static> {
+        let mut config = ProcessConfig::default();
+        if let Some(workers) = self.workers {
+            config = config.workers(workers);
+        }
+        if let Some(capacity) = self.channel_capacity {
+            config = config.channel_capacity(capacity);
+        }
+        config
+    }
+}
+
+fn flag_value(flag: &str, value: Option<String>) -> Result<String> {
+    value.with_context(|| format!("missing value for {flag}\n{USAGE}"))
 }
 
-fn get_writer() -> impl std::io::Write {
-    std::io::stdout()
+fn parse_flag_value(flag: &str, value: Option<String>) -> Result<usize> {
+    let value = flag_value(flag, value)?;
+    value
+        .parse()
+        .with_context(|| format!("invalid value {value} for {flag}\n{USAGE}"))
+}
+
+/// The output format is set by the `OUTPUT_FORMAT` env variable (`csv` or `jsonl`, default is `csv`).
+fn get_output_format() -> Result<OutputFormat> {
+    match env::var("OUTPUT_FORMAT") {
+        Ok(format) => format.parse().map_err(anyhow::Error::msg),
+        Err(_) => Ok(OutputFormat::default()),
+    }
+}
+
+/// Input files with a `.gz` extension are decompressed transparently (requires the `gzip` feature). The input is read
+/// from stdin if the path is `-`, or if it is missing and stdin is piped (rather than a terminal).
+fn get_reader(path: Option<&str>) -> Result<Box<dyn Read>> {
+    let path = match path {
+        Some("-") => return Ok(Box::new(std::io::stdin().lock())),
+        None if !std::io::stdin().is_terminal() => return Ok(Box::new(std::io::stdin().lock())),
+        None => anyhow::bail!(USAGE),
+        Some(path) => path,
+    };
+    let file = File::open(path)?;
+    if path.ends_with(".gz") {
+        gzip_reader(file)
+    } else {
+        Ok(Box::new(file))
+    }
+}
+
+#[cfg(feature = "gzip")]
+fn gzip_reader(file: File) -> Result<Box<dyn Read>> {
+    Ok(Box::new(flate2::read::MultiGzDecoder::new(file)))
+}
+
+#[cfg(not(feature = "gzip"))]
+fn gzip_reader(_file: File) -> Result<Box<dyn Read>> {
+    anyhow::bail!("reading gzip-compressed input requires the `gzip` feature")
+}
+
+/// The output is written to the file at the given path (created or truncated), or to stdout if there is none. The
+/// file is closed when the writer is dropped, also if writing the records failed.
+fn get_writer(path: Option<&str>) -> Result<Box<dyn Write>> {
+    match path {
+        Some(path) => {
+            let file = File::create(path).with_context(|| format!("cannot create {path}"))?;
+            Ok(Box::new(file))
+        }
+        None => Ok(Box::new(io::stdout())),
+    }
 }
 
 fn handle_tx_error(error: Error) {
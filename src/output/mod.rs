@@ -1,32 +1,243 @@
-use std::collections::HashMap;
+use std::collections::HashSet;
 use std::fmt;
+use std::io::{self, Write};
+use std::str::FromStr;
 
 use serde::Serialize;
 
-use crate::domain::{AccountState, ClientId, Money, Transaction};
+use crate::domain::{AccountState, ClientId, LockReason, Money, Transaction};
 
 #[cfg(test)]
 mod tests;
 
-pub(crate) fn to_account_records(
-    accounts: HashMap<ClientId, AccountState>,
-) -> impl Iterator<Item = AccountRecord> {
-    accounts
+/// The format in which account records are written by [`write_records`]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// CSV with a header row
+    #[default]
+    Csv,
+    /// Newline-delimited JSON: one object per record. Monetary fields are serialized as
+    /// JSON strings (e.g., `"available":"1.5"`) so that no precision is lost by consumers
+    /// parsing JSON numbers as floats.
+    JsonLines,
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "csv" => Ok(OutputFormat::Csv),
+            "jsonl" => Ok(OutputFormat::JsonLines),
+            other => Err(format!(
+                "unknown output format '{other}' (expected 'csv' or 'jsonl')"
+            )),
+        }
+    }
+}
+
+/// Writes the account records to the writer in the given format. An IO error of the writer is returned as is, so that
+/// its kind can be told apart (e.g., `BrokenPipe` if the reading end of a pipe was closed).
+pub fn write_records(
+    records: impl IntoIterator<Item = AccountRecord>,
+    writer: impl Write,
+    format: OutputFormat,
+) -> io::Result<()> {
+    match format {
+        OutputFormat::Csv => {
+            let mut wtr = csv::Writer::from_writer(writer);
+            for record in records {
+                wtr.serialize(CsvRow::from(&record)).map_err(io_error)?;
+            }
+            wtr.flush()
+        }
+        OutputFormat::JsonLines => {
+            let mut writer = io::BufWriter::new(writer);
+            for record in records {
+                serde_json::to_writer(&mut writer, &record)?;
+                writer.write_all(b"\n")?;
+            }
+            writer.flush()
+        }
+    }
+}
+
+/// Unwraps an IO error of the CSV writer, which the CSV error would otherwise wrap with the kind `Other`
+fn io_error(error: csv::Error) -> io::Error {
+    if !error.is_io_error() {
+        return error.into();
+    }
+    let csv::ErrorKind::Io(error) = error.into_kind() else {
+        unreachable!("checked to be an IO error");
+    };
+    error
+}
+
+/// A field of an [`AccountRecord`] which can be written as a column by [`write_columns`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Column {
+    Client,
+    Available,
+    Held,
+    Total,
+    Locked,
+    /// The reason of the lock as `<reason>:<tx id>` (e.g., `chargeback:2`); empty for unlocked accounts
+    LockReason,
+    /// Empty for records without the number of transactions
+    NumTransactions,
+}
+
+impl Column {
+    /// The default header of the column, as in the output of [`write_records`]
+    pub fn default_header(self) -> &'static str {
+        match self {
+            Column::Client => "client",
+            Column::Available => "available",
+            Column::Held => "held",
+            Column::Total => "total",
+            Column::Locked => "locked",
+            Column::LockReason => "lock_reason",
+            Column::NumTransactions => "num_transactions",
+        }
+    }
+
+    fn value(self, record: &AccountRecord) -> String {
+        match self {
+            Column::Client => record.client.to_string(),
+            Column::Available => record.available.to_string(),
+            Column::Held => record.held.to_string(),
+            Column::Total => record.total.to_string(),
+            Column::Locked => record.locked.to_string(),
+            Column::LockReason => match record.lock_reason {
+                Some(LockReason::Chargeback { tx_id }) => format!("chargeback:{tx_id}"),
+                None => String::new(),
+            },
+            Column::NumTransactions => record
+                .num_transactions
+                .map(|n| n.to_string())
+                .unwrap_or_default(),
+        }
+    }
+}
+
+/// The columns written by [`write_columns`], in order, with their headers. The default spec reproduces the columns
+/// of the CSV output of [`write_records`].
+///
+/// # Example
+///
+/// ```
+/// use tx_engine_rs::{Column, ColumnSpec, process, write_columns};
+///
+/// let input = "type, client, tx, amount\ndeposit, 1, 1, 1.50\n";
+/// let spec = ColumnSpec::new()
+///     .column(Column::Client, "client_id")
+///     .column(Column::Available, "avail")
+///     .column(Column::Locked, "frozen");
+///
+/// let mut buffer = Vec::new();
+/// write_columns(process(input.as_bytes(), |_| {}, |_| {}), &mut buffer, &spec).unwrap();
+///
+/// assert_eq!(String::from_utf8(buffer).unwrap(), "client_id,avail,frozen\n1,1.50,false\n");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ColumnSpec {
+    columns: Vec<(Column, String)>,
+}
+
+impl ColumnSpec {
+    /// A spec without columns, to be extended with [`column`](Self::column)
+    pub fn new() -> Self {
+        Self {
+            columns: Vec::new(),
+        }
+    }
+
+    /// Appends the column, written under the given header
+    pub fn column(mut self, column: Column, header: impl Into<String>) -> Self {
+        self.columns.push((column, header.into()));
+        self
+    }
+}
+
+impl Default for ColumnSpec {
+    fn default() -> Self {
+        [
+            Column::Client,
+            Column::Available,
+            Column::Held,
+            Column::Total,
+            Column::Locked,
+        ]
         .into_iter()
-        .map(|(id, state)| AccountRecord::new(id, state))
+        .fold(Self::new(), |spec, column| {
+            spec.column(column, column.default_header())
+        })
+    }
+}
+
+/// Writes the account records as CSV with the columns of the spec only, under the headers of the spec. Monetary
+/// values are written with the precision they carry, as by [`write_records`]. The header row is written even if there
+/// are no records.
+pub fn write_columns(
+    records: impl IntoIterator<Item = AccountRecord>,
+    writer: impl Write,
+    spec: &ColumnSpec,
+) -> io::Result<()> {
+    let mut wtr = csv::Writer::from_writer(writer);
+    wtr.write_record(spec.columns.iter().map(|(_, header)| header))
+        .map_err(io_error)?;
+    for record in records {
+        wtr.write_record(spec.columns.iter().map(|(column, _)| column.value(&record)))
+            .map_err(io_error)?;
+    }
+    wtr.flush()
 }
 
-#[derive(Serialize, Debug, PartialEq, Eq)]
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq)]
 pub struct AccountRecord {
     pub client: u16,
     pub available: Money,
     pub held: Money,
     pub total: Money,
     pub locked: bool,
+    /// Why the account is locked; `None` for unlocked accounts. Only part of the JSON output, in which it is omitted
+    /// for unlocked accounts.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lock_reason: Option<LockReason>,
+    /// The number of accepted transactions applied to the account. Only set (and serialized) if enabled via
+    /// [`ProcessConfig::include_num_transactions`](crate::ProcessConfig::include_num_transactions).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub num_transactions: Option<u64>,
+}
+
+/// The columns of the CSV output, which are fixed regardless of the locks; the optional `num_transactions` column is
+/// appended if the records carry the number of transactions
+#[derive(Serialize)]
+struct CsvRow {
+    client: u16,
+    available: Money,
+    held: Money,
+    total: Money,
+    locked: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    num_transactions: Option<u64>,
+}
+
+impl From<&AccountRecord> for CsvRow {
+    fn from(record: &AccountRecord) -> Self {
+        Self {
+            client: record.client,
+            available: record.available,
+            held: record.held,
+            total: record.total,
+            locked: record.locked,
+            num_transactions: record.num_transactions,
+        }
+    }
 }
 
 impl AccountRecord {
-    fn new(client_id: ClientId, account_state: AccountState) -> Self {
+    pub(crate) fn new(client_id: ClientId, account_state: &AccountState) -> Self {
         let total = account_state.available_funds() + account_state.held_funds();
         Self {
             client: client_id.into(),
@@ -34,18 +245,168 @@ impl AccountRecord {
             held: account_state.held_funds(),
             total,
             locked: account_state.is_locked(),
+            lock_reason: account_state.lock_reason(),
+            num_transactions: None,
         }
     }
+
+    /// Creates the record of the account, carrying its number of transactions if requested
+    pub(crate) fn with_details(
+        client_id: ClientId,
+        account_state: &AccountState,
+        include_num_transactions: bool,
+    ) -> Self {
+        Self {
+            num_transactions: include_num_transactions.then(|| account_state.num_transactions()),
+            ..Self::new(client_id, account_state)
+        }
+    }
+
+    /// Returns the record of the account state as it looks after settling all open disputes in the client's favor
+    /// (see [`AccountState::settled_available_funds`]): no funds are held anymore.
+    pub(crate) fn settled(&self, account_state: &AccountState) -> Self {
+        let available = account_state.settled_available_funds();
+        Self {
+            available,
+            held: Money::ZERO,
+            total: available,
+            ..*self
+        }
+    }
+
+    /// Returns the record with its monetary fields rounded to the given number of decimal places.
+    /// The total is rounded from the exact sum, so that it may differ from the sum of the rounded parts.
+    pub(crate) fn rounded(&self, decimal_places: u32) -> Self {
+        Self {
+            available: self.available.round_dp(decimal_places),
+            held: self.held.round_dp(decimal_places),
+            total: self.total.round_dp(decimal_places),
+            ..*self
+        }
+    }
+}
+
+impl fmt::Display for AccountRecord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let AccountRecord {
+            client,
+            available,
+            held,
+            total,
+            locked,
+            ..
+        } = self;
+        write!(
+            f,
+            "Account {{ client: {client}, available: {available}, held: {held}, total: {total}, locked: {locked} }}"
+        )
+    }
+}
+
+/// Sums of the monetary fields over a set of account records, see [`aggregate()`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct LedgerTotals {
+    pub available: Money,
+    pub held: Money,
+    pub total: Money,
+}
+
+/// Sums up the funds of all accounts, e.g., for a sanity check of a run.
+///
+/// Since the engine moves funds only between `available` and `held` of an account, the `total` equals the sum of
+/// the accepted deposits minus the accepted withdrawals and the charged-back funds. Note that the records should
+/// not be rounded for an exact comparison.
+pub fn aggregate(records: &[AccountRecord]) -> LedgerTotals {
+    records
+        .iter()
+        .fold(LedgerTotals::default(), |totals, record| LedgerTotals {
+            available: totals.available + record.available,
+            held: totals.held + record.held,
+            total: totals.total + record.total,
+        })
+}
+
+/// Filters on the account records returned by [`process()`](crate::process) and its variants, e.g., to only serialize
+/// the locked accounts. The filters are lazy: like the iterator they wrap, they do nothing unless consumed.
+///
+/// # Example
+///
+/// ```
+/// use tx_engine_rs::{AccountFilter, process};
+///
+/// let input = "type, client, tx, amount\ndeposit, 1, 1, 1.0\ndeposit, 2, 2, 2.0\ndispute, 2, 2,\n";
+/// let clients: Vec<u16> = process(input.as_bytes(), |_| {}, |_| {})
+///     .with_held()
+///     .map(|record| record.client)
+///     .collect();
+///
+/// assert_eq!(clients, vec![2]);
+/// ```
+pub trait AccountFilter: Iterator<Item = AccountRecord> + Sized {
+    /// Keeps the accounts locked by a chargeback
+    #[must_use = "iterator adapters are lazy and do nothing unless consumed"]
+    fn locked_only(self) -> impl Iterator<Item = AccountRecord> {
+        self.filter(|record| record.locked)
+    }
+
+    /// Keeps the accounts with funds held by open disputes
+    #[must_use = "iterator adapters are lazy and do nothing unless consumed"]
+    fn with_held(self) -> impl Iterator<Item = AccountRecord> {
+        self.filter(|record| !record.held.is_zero())
+    }
+
+    /// Keeps the accounts of the given clients
+    #[must_use = "iterator adapters are lazy and do nothing unless consumed"]
+    fn with_client_in(
+        self,
+        clients: impl IntoIterator<Item = u16>,
+    ) -> impl Iterator<Item = AccountRecord> {
+        let clients: HashSet<u16> = clients.into_iter().collect();
+        self.filter(move |record| clients.contains(&record.client))
+    }
 }
 
+impl<I: Iterator<Item = AccountRecord>> AccountFilter for I {}
+
 /// Public DTO representing a successfully processed transaction.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum TransactionRecord {
-    Deposit { client: u16, tx: u32, amount: Money },
-    Withdrawal { client: u16, tx: u32, amount: Money },
-    Dispute { client: u16, tx: u32 },
-    Resolve { client: u16, tx: u32 },
-    Chargeback { client: u16, tx: u32 },
+    Deposit {
+        client: u16,
+        tx: u64,
+        amount: Money,
+    },
+    Withdrawal {
+        client: u16,
+        tx: u64,
+        amount: Money,
+    },
+    Dispute {
+        client: u16,
+        tx: u64,
+    },
+    /// The `amount` is only set for a partial settlement (see
+    /// [`partial_settlements`](crate::ProcessConfig::partial_settlements))
+    Resolve {
+        client: u16,
+        tx: u64,
+        amount: Option<Money>,
+    },
+    /// The `amount` is only set for a partial settlement (see
+    /// [`partial_settlements`](crate::ProcessConfig::partial_settlements))
+    Chargeback {
+        client: u16,
+        tx: u64,
+        amount: Option<Money>,
+    },
+    Open {
+        client: u16,
+        tx: u64,
+    },
+    Unlock {
+        client: u16,
+        tx: u64,
+    },
 }
 
 impl TransactionRecord {
@@ -68,15 +429,72 @@ impl TransactionRecord {
             Transaction::Resolve(r) => TransactionRecord::Resolve {
                 client: r.client_id().into(),
                 tx: r.resolved_tx_id().into(),
+                amount: r.amount(),
             },
             Transaction::Chargeback(c) => TransactionRecord::Chargeback {
                 client: c.client_id().into(),
                 tx: c.reverted_tx_id().into(),
+                amount: c.amount(),
+            },
+            Transaction::Open(o) => TransactionRecord::Open {
+                client: o.client_id().into(),
+                tx: o.tx_id().into(),
+            },
+            Transaction::Unlock(u) => TransactionRecord::Unlock {
+                client: u.client_id().into(),
+                tx: u.tx_id().into(),
             },
         }
     }
 }
 
+/// The balances of an account before and after an applied transaction, reported to
+/// [`ProcessConfig::on_mutation`](crate::ProcessConfig::on_mutation). The balances are `(available, held)` pairs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Mutation {
+    pub client: u16,
+    pub transaction: TransactionRecord,
+    pub before: (Money, Money),
+    pub after: (Money, Money),
+}
+
+/// The direction in which funds moved between the balances of an account, see [`FundsMoved`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FundsDirection {
+    /// Funds held by a dispute
+    AvailableToHeld,
+    /// Funds released by a resolve
+    HeldToAvailable,
+}
+
+/// Funds moved between the available and the held balance of an account by a dispute or resolve of a deposit,
+/// reported to [`ProcessConfig::on_funds_moved`](crate::ProcessConfig::on_funds_moved). `tx` is the id of the
+/// disputed or resolved deposit, and `amount` is the moved amount (less than the deposit if only part of it is held).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FundsMoved {
+    pub client: u16,
+    pub tx: u64,
+    pub direction: FundsDirection,
+    pub amount: Money,
+}
+
+/// A suspicious but valid pattern in the transactions of a client, e.g., for flagging the client for a compliance
+/// review, reported to [`ProcessConfig::on_warning`](crate::ProcessConfig::on_warning). Further heuristics may be
+/// added as variants.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Warning {
+    /// Every deposit of the client which can still be disputed is under dispute, and there are at least
+    /// [`RAPID_DISPUTE_THRESHOLD`](Self::RAPID_DISPUTE_THRESHOLD) of them. Reported on every dispute of the client
+    /// while the pattern holds; `count` is the number of deposits under dispute.
+    RapidDisputeRate { client: u16, count: usize },
+}
+
+impl Warning {
+    /// The number of deposits under dispute from which [`RapidDisputeRate`](Self::RapidDisputeRate) is reported
+    pub const RAPID_DISPUTE_THRESHOLD: usize = 3;
+}
+
 impl fmt::Display for TransactionRecord {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
@@ -95,11 +513,37 @@ impl fmt::Display for TransactionRecord {
             TransactionRecord::Dispute { client, tx } => {
                 write!(f, "Dispute {{ client: {client}, tx: {tx} }}")
             }
-            TransactionRecord::Resolve { client, tx } => {
-                write!(f, "Resolve {{ client: {client}, tx: {tx} }}")
+            TransactionRecord::Resolve {
+                client,
+                tx,
+                amount: None,
+            } => write!(f, "Resolve {{ client: {client}, tx: {tx} }}"),
+            TransactionRecord::Resolve {
+                client,
+                tx,
+                amount: Some(amount),
+            } => write!(
+                f,
+                "Resolve {{ client: {client}, tx: {tx}, amount: {amount} }}"
+            ),
+            TransactionRecord::Chargeback {
+                client,
+                tx,
+                amount: None,
+            } => write!(f, "Chargeback {{ client: {client}, tx: {tx} }}"),
+            TransactionRecord::Chargeback {
+                client,
+                tx,
+                amount: Some(amount),
+            } => write!(
+                f,
+                "Chargeback {{ client: {client}, tx: {tx}, amount: {amount} }}"
+            ),
+            TransactionRecord::Open { client, tx } => {
+                write!(f, "Open {{ client: {client}, tx: {tx} }}")
             }
-            TransactionRecord::Chargeback { client, tx } => {
-                write!(f, "Chargeback {{ client: {client}, tx: {tx} }}")
+            TransactionRecord::Unlock { client, tx } => {
+                write!(f, "Unlock {{ client: {client}, tx: {tx} }}")
             }
         }
     }
@@ -2,6 +2,17 @@ use super::*;
 use rust_decimal_macros::dec;
 use std::collections::HashMap;
 
+use crate::domain::{Deposit, Policy, TxId, Withdrawal};
+
+fn to_account_records(
+    accounts: HashMap<ClientId, AccountState>,
+    include_num_transactions: bool,
+) -> impl Iterator<Item = AccountRecord> {
+    accounts
+        .into_iter()
+        .map(move |(id, state)| AccountRecord::with_details(id, &state, include_num_transactions))
+}
+
 #[test]
 fn single_account_converts_correctly() {
     let client_id = 1u16;
@@ -16,7 +27,7 @@ fn single_account_converts_correctly() {
         AccountState::new(available, held, locked),
     );
 
-    let records: Vec<_> = to_account_records(accounts).collect();
+    let records: Vec<_> = to_account_records(accounts, false).collect();
     assert_eq!(records.len(), 1);
     assert_eq!(
         records[0],
@@ -26,6 +37,8 @@ fn single_account_converts_correctly() {
             held,
             total,
             locked,
+            lock_reason: locked.then_some(LockReason::Chargeback { tx_id: 0 }),
+            num_transactions: None,
         }
     );
 }
@@ -39,7 +52,7 @@ fn total_is_sum_of_available_and_held() {
     let mut accounts = HashMap::new();
     accounts.insert(ClientId::new(1), AccountState::new(available, held, false));
 
-    let record = to_account_records(accounts).next().unwrap();
+    let record = to_account_records(accounts, false).next().unwrap();
     assert_eq!(record.total, expected_total);
 }
 
@@ -53,14 +66,14 @@ fn locked_account_preserves_locked_flag() {
         AccountState::new(dec!(0.0), dec!(0.0), locked),
     );
 
-    let record = to_account_records(accounts).next().unwrap();
+    let record = to_account_records(accounts, false).next().unwrap();
     assert_eq!(record.locked, locked);
 }
 
 #[test]
 fn empty_map_yields_no_records() {
     let accounts = HashMap::new();
-    let records: Vec<_> = to_account_records(accounts).collect();
+    let records: Vec<_> = to_account_records(accounts, false).collect();
     assert!(records.is_empty());
 }
 
@@ -76,6 +89,296 @@ fn multiple_accounts_yield_one_record_each() {
         AccountState::new(dec!(2.0), dec!(0.0), false),
     );
 
-    let records: Vec<_> = to_account_records(accounts).collect();
+    let records: Vec<_> = to_account_records(accounts, false).collect();
     assert_eq!(records.len(), 2);
 }
+
+#[test]
+fn account_record_is_displayed_with_all_fields() {
+    let record = AccountRecord {
+        client: 1,
+        available: dec!(10),
+        held: dec!(0.5),
+        total: dec!(10.5),
+        locked: false,
+        lock_reason: None,
+        num_transactions: None,
+    };
+
+    assert_eq!(
+        record.to_string(),
+        "Account { client: 1, available: 10, held: 0.5, total: 10.5, locked: false }"
+    );
+}
+
+#[test]
+fn records_can_be_retained_while_being_written() {
+    let mut accounts = HashMap::new();
+    accounts.insert(
+        ClientId::new(1),
+        AccountState::new(dec!(1.5), dec!(2.0), true),
+    );
+    let records: Vec<_> = to_account_records(accounts, false).collect();
+
+    let retained = records.clone();
+    let mut buffer = Vec::new();
+    write_records(records, &mut buffer, OutputFormat::Csv).unwrap();
+
+    assert!(!buffer.is_empty());
+    assert_eq!(
+        retained,
+        [AccountRecord {
+            client: 1,
+            available: dec!(1.5),
+            held: dec!(2.0),
+            total: dec!(3.5),
+            locked: true,
+            lock_reason: Some(LockReason::Chargeback { tx_id: 0 }),
+            num_transactions: None,
+        }]
+    );
+}
+
+#[test]
+fn aggregate_sums_the_funds_of_all_records() {
+    let mut accounts = HashMap::new();
+    accounts.insert(
+        ClientId::new(1),
+        AccountState::new(dec!(1.5), dec!(2.0), false),
+    );
+    accounts.insert(
+        ClientId::new(2),
+        AccountState::new(dec!(3.0), dec!(0.25), true),
+    );
+    let records: Vec<_> = to_account_records(accounts, false).collect();
+
+    assert_eq!(
+        aggregate(&records),
+        LedgerTotals {
+            available: dec!(4.5),
+            held: dec!(2.25),
+            total: dec!(6.75),
+        }
+    );
+    assert_eq!(aggregate(&[]), LedgerTotals::default());
+}
+
+#[test]
+fn settled_record_releases_held_deposits_and_drops_held_withdrawals() {
+    let policy = Policy {
+        dispute_withdrawals: true,
+        ..Policy::default()
+    };
+    let (client_id, deposit_id, withdrawal_id) = (ClientId::new(1), TxId::new(1), TxId::new(2));
+    let mut state = AccountState::default();
+    state
+        .deposit(
+            Deposit::new(client_id, deposit_id, dec!(10.0)).unwrap(),
+            &policy,
+        )
+        .unwrap();
+    state
+        .withdraw(
+            Withdrawal::new(client_id, withdrawal_id, dec!(4.0)).unwrap(),
+            &policy,
+        )
+        .unwrap();
+    state.dispute(withdrawal_id, &policy).unwrap();
+
+    let record = AccountRecord::new(client_id, &state);
+    assert_eq!((record.available, record.held), (dec!(6.0), dec!(4.0)));
+    // the withdrawal stands, as if it was resolved
+    assert_eq!(
+        record.settled(&state),
+        AccountRecord {
+            client: 1,
+            available: dec!(6.0),
+            held: dec!(0),
+            total: dec!(6.0),
+            locked: false,
+            lock_reason: None,
+            num_transactions: None,
+        }
+    );
+
+    // the held funds of a disputed deposit are released
+    let second_deposit = TxId::new(3);
+    state
+        .deposit(
+            Deposit::new(client_id, second_deposit, dec!(2.0)).unwrap(),
+            &policy,
+        )
+        .unwrap();
+    state.dispute(second_deposit, &policy).unwrap();
+    let record = AccountRecord::new(client_id, &state);
+    assert_eq!((record.available, record.held), (dec!(6.0), dec!(6.0)));
+    assert_eq!(record.settled(&state).available, dec!(8.0));
+}
+
+#[test]
+fn rounded_record_rounds_monetary_fields() {
+    let mut accounts = HashMap::new();
+    accounts.insert(
+        ClientId::new(1),
+        AccountState::new(dec!(1.23456), dec!(0.00005), false),
+    );
+
+    let record = to_account_records(accounts, false).next().unwrap();
+    let rounded = record.rounded(4);
+    assert_eq!(rounded.available, dec!(1.2346));
+    assert_eq!(rounded.held, dec!(0.0000));
+    assert_eq!(rounded.total, dec!(1.2346));
+    assert_eq!(rounded.client, record.client);
+}
+
+#[test]
+fn json_lines_output_writes_one_object_per_record_with_string_amounts() {
+    let mut accounts = HashMap::new();
+    accounts.insert(
+        ClientId::new(7),
+        AccountState::new(dec!(1.5), dec!(0.0001), false),
+    );
+
+    let mut buffer = Vec::new();
+    write_records(
+        to_account_records(accounts, false),
+        &mut buffer,
+        OutputFormat::JsonLines,
+    )
+    .unwrap();
+
+    assert_eq!(
+        String::from_utf8(buffer).unwrap(),
+        "{\"client\":7,\"available\":\"1.5\",\"held\":\"0.0001\",\"total\":\"1.5001\",\"locked\":false}\n"
+    );
+}
+
+#[test]
+fn output_format_is_parsed_from_its_name() {
+    assert_eq!("csv".parse(), Ok(OutputFormat::Csv));
+    assert_eq!("jsonl".parse(), Ok(OutputFormat::JsonLines));
+    assert!("xml".parse::<OutputFormat>().is_err());
+}
+
+fn filter_input() -> Vec<AccountRecord> {
+    let mut accounts = HashMap::new();
+    accounts.insert(
+        ClientId::new(1),
+        AccountState::new(dec!(1.0), dec!(0), false),
+    );
+    accounts.insert(
+        ClientId::new(2),
+        AccountState::new(dec!(0), dec!(2.0), false),
+    );
+    accounts.insert(
+        ClientId::new(3),
+        AccountState::new(dec!(3.0), dec!(0), true),
+    );
+    let mut records: Vec<_> = to_account_records(accounts, false).collect();
+    records.sort_by_key(|r| r.client);
+    records
+}
+
+fn clients(records: impl Iterator<Item = AccountRecord>) -> Vec<u16> {
+    records.map(|r| r.client).collect()
+}
+
+#[test]
+fn locked_only_keeps_the_locked_accounts() {
+    assert_eq!(clients(filter_input().into_iter().locked_only()), vec![3]);
+}
+
+#[test]
+fn with_held_keeps_the_accounts_with_held_funds() {
+    assert_eq!(clients(filter_input().into_iter().with_held()), vec![2]);
+}
+
+#[test]
+fn with_client_in_keeps_the_given_clients() {
+    let records = filter_input().into_iter().with_client_in([1, 3, 4]);
+    assert_eq!(clients(records), vec![1, 3]);
+}
+
+#[test]
+fn filters_can_be_combined() {
+    let records = filter_input()
+        .into_iter()
+        .with_client_in([2, 3])
+        .locked_only();
+    assert_eq!(clients(records), vec![3]);
+}
+
+fn columns_output(spec: &ColumnSpec) -> String {
+    let mut buffer = Vec::new();
+    write_columns(filter_input(), &mut buffer, spec).unwrap();
+    String::from_utf8(buffer).unwrap()
+}
+
+#[test]
+fn default_column_spec_matches_the_csv_output() {
+    let mut buffer = Vec::new();
+    write_records(filter_input(), &mut buffer, OutputFormat::Csv).unwrap();
+
+    assert_eq!(
+        columns_output(&ColumnSpec::default()),
+        String::from_utf8(buffer).unwrap()
+    );
+}
+
+#[test]
+fn columns_are_renamed_and_selected() {
+    let spec = ColumnSpec::new()
+        .column(Column::Client, "client_id")
+        .column(Column::Available, "avail")
+        .column(Column::Held, "held")
+        .column(Column::Locked, "frozen");
+
+    assert_eq!(
+        columns_output(&spec),
+        "client_id,avail,held,frozen\n1,1.0,0,false\n2,0,2.0,false\n3,3.0,0,true\n"
+    );
+}
+
+#[test]
+fn columns_keep_the_precision_and_order_of_the_spec() {
+    let mut accounts = HashMap::new();
+    accounts.insert(
+        ClientId::new(7),
+        AccountState::new(dec!(1.2345), dec!(0.5000), false),
+    );
+    let spec = ColumnSpec::new()
+        .column(Column::Total, "total")
+        .column(Column::Client, "id")
+        .column(Column::NumTransactions, "count");
+
+    let mut buffer = Vec::new();
+    write_columns(to_account_records(accounts, false), &mut buffer, &spec).unwrap();
+
+    assert_eq!(
+        String::from_utf8(buffer).unwrap(),
+        "total,id,count\n1.7345,7,\n"
+    );
+}
+
+#[test]
+fn lock_reason_column_names_the_chargeback_of_locked_accounts() {
+    let mut accounts = HashMap::new();
+    accounts.insert(ClientId::new(1), AccountState::new(dec!(0), dec!(0), true));
+    accounts.insert(
+        ClientId::new(2),
+        AccountState::new(dec!(1.0), dec!(0), false),
+    );
+    let mut records: Vec<_> = to_account_records(accounts, false).collect();
+    records.sort_by_key(|record| record.client);
+    let spec = ColumnSpec::new()
+        .column(Column::Client, "client")
+        .column(Column::LockReason, Column::LockReason.default_header());
+
+    let mut buffer = Vec::new();
+    write_columns(records, &mut buffer, &spec).unwrap();
+
+    assert_eq!(
+        String::from_utf8(buffer).unwrap(),
+        "client,lock_reason\n1,chargeback:0\n2,\n"
+    );
+}
@@ -0,0 +1,87 @@
+//! Module defining the summary statistics of a processing run
+
+use crate::{Error, TransactionRecord};
+
+/// Summary of a processing run, returned by [`process_with_stats()`](crate::process_with_stats).
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ProcessStats {
+    /// Number of transactions successfully applied
+    pub accepted: u64,
+    /// Number of transactions rejected for violating domain invariants
+    pub validation_errors: u64,
+    /// Number of transactions rejected for conflicting with the account state, including locked accounts and the
+    /// shards of failed workers
+    pub processing_errors: u64,
+    /// Number of rows which could not be parsed as CSV (or records which could not be decoded from binary input),
+    /// including an invalid header
+    pub csv_errors: u64,
+    /// Number of accounts produced
+    pub accounts: u64,
+    /// Order-independent checksum of the successfully applied transactions: the wrapping sum of a hash per
+    /// transaction. Two runs applying the same transactions have the same checksum, regardless of the order in which
+    /// they were applied, so that the sequential and the parallel engine agree. Amounts are compared by value
+    /// (`1.0` and `1.00` hash the same).
+    pub checksum: u64,
+    /// Number of worker threads used by the parallel engine (see
+    /// [`ProcessConfig::effective_workers`](crate::ProcessConfig::effective_workers)), which is lower for a small
+    /// input if [`min_rows_per_worker`](crate::ProcessConfig::min_rows_per_worker) is set. Zero for sequential
+    /// processing.
+    pub workers: u64,
+    /// Number of times the dispatcher of the parallel engine found a worker's channel full and had to wait,
+    /// summed over all workers. Always zero for sequential processing.
+    pub backpressure_waits: u64,
+    /// Number of worker threads of the parallel engine which panicked (see [`Error::WorkerFailed`]). Not counted as
+    /// rejected transactions.
+    pub worker_failures: u64,
+    /// Whether the input had more rows than the configured [`max_rows`](crate::ProcessConfig::max_rows), i.e.,
+    /// whether rows were skipped (see [`Error::Truncated`])
+    pub truncated: bool,
+}
+
+impl ProcessStats {
+    /// Total number of rejected transactions, regardless of the reason
+    pub fn rejected(&self) -> u64 {
+        self.validation_errors + self.processing_errors + self.csv_errors
+    }
+
+    pub(crate) fn record_error(&mut self, error: &Error) {
+        match error {
+            Error::Malformed { source, .. } => self.record_error(source),
+            Error::Csv { .. } | Error::Binary { .. } | Error::Header { .. } => self.csv_errors += 1,
+            Error::Validation { .. } => self.validation_errors += 1,
+            Error::Processing { .. }
+            | Error::AccountLocked { .. }
+            | Error::ShardUnavailable { .. } => self.processing_errors += 1,
+            Error::WorkerFailed { .. } => self.worker_failures += 1,
+            Error::Truncated { .. } => self.truncated = true,
+            // an error of the run rather than of a transaction
+            Error::NotConserved { .. } => {}
+        }
+    }
+}
+
+/// Deterministic hash of a transaction (64-bit FNV-1a over a canonical encoding), stable across runs and platforms.
+pub(crate) fn transaction_hash(record: &TransactionRecord) -> u64 {
+    const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+    let (tag, client, tx, amount) = match *record {
+        TransactionRecord::Deposit { client, tx, amount } => (0u8, client, tx, Some(amount)),
+        TransactionRecord::Withdrawal { client, tx, amount } => (1, client, tx, Some(amount)),
+        TransactionRecord::Dispute { client, tx } => (2, client, tx, None),
+        TransactionRecord::Resolve { client, tx, amount } => (3, client, tx, amount),
+        TransactionRecord::Chargeback { client, tx, amount } => (4, client, tx, amount),
+        TransactionRecord::Open { client, tx } => (5, client, tx, None),
+        TransactionRecord::Unlock { client, tx } => (6, client, tx, None),
+    };
+    let amount = amount.map(|amount| amount.normalize().serialize());
+
+    [tag]
+        .iter()
+        .chain(&client.to_le_bytes())
+        .chain(&tx.to_le_bytes())
+        .chain(amount.iter().flatten())
+        .fold(FNV_OFFSET_BASIS, |hash, byte| {
+            (hash ^ u64::from(*byte)).wrapping_mul(FNV_PRIME)
+        })
+}
@@ -1,4 +1,4 @@
-//! Module for telemetry functionality such as logging
+//! Module for telemetry functionality such as logging and metrics
 
 use tracing::debug;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
@@ -6,8 +6,14 @@ use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 /// Sets up logging. The log level is taken from the `RUST_LOG` env variable (default is `info`).
 /// The logging format (pretty/json) is set by the `LOG_FORMAT` env variable.
 pub fn setup_logging() {
-    let env_filter =
-        tracing_subscriber::EnvFilter::try_from_default_env().unwrap_or_else(|_| "info".into());
+    setup_logging_with_default(tracing::Level::INFO);
+}
+
+/// Variant of [`setup_logging()`] with the given log level as the default, e.g., from a verbosity flag. The
+/// `RUST_LOG` env variable still takes precedence if it is set.
+pub fn setup_logging_with_default(level: tracing::Level) {
+    let env_filter = tracing_subscriber::EnvFilter::try_from_default_env()
+        .unwrap_or_else(|_| level.as_str().to_ascii_lowercase().into());
 
     let format = std::env::var("LOG_FORMAT").unwrap_or_else(|_| "pretty".to_string());
 
@@ -32,3 +38,25 @@ pub fn setup_logging() {
     }
     debug!("Debug mode is enabled. Sensitive data might be visible.");
 }
+
+// Counters exported via the `metrics` facade if the `metrics` feature is enabled. Without the feature, the
+// recording functions are empty and compile out.
+
+/// Counts a successfully applied transaction (`tx_accepted_total`).
+pub(crate) fn record_accepted() {
+    #[cfg(feature = "metrics")]
+    metrics::counter!("tx_accepted_total").increment(1);
+}
+
+/// Counts a rejected transaction (`tx_rejected_total`), labeled by the `kind` of the error.
+#[cfg_attr(not(feature = "metrics"), allow(unused_variables))]
+pub(crate) fn record_rejected(error: &crate::Error) {
+    #[cfg(feature = "metrics")]
+    metrics::counter!("tx_rejected_total", "kind" => error.kind_label()).increment(1);
+}
+
+/// Counts an account locked by a chargeback (`accounts_locked_total`).
+pub(crate) fn record_locked() {
+    #[cfg(feature = "metrics")]
+    metrics::counter!("accounts_locked_total").increment(1);
+}
@@ -0,0 +1,205 @@
+//! Integration tests for the compact binary input encoding
+
+use std::io::ErrorKind;
+
+use rstest::rstest;
+use rust_decimal_macros::dec;
+use tx_engine_rs::{
+    AccountRecord, Error, ProcessConfig, TransactionRecord, parse_records, parse_records_binary,
+    process_binary, process_with_config, write_transactions_binary,
+};
+
+fn encode(records: impl IntoIterator<Item = TransactionRecord>) -> Vec<u8> {
+    let mut encoded = Vec::new();
+    write_transactions_binary(records, &mut encoded).unwrap();
+    encoded
+}
+
+#[test]
+fn encoded_transactions_decode_to_equal_records() {
+    let records = vec![
+        TransactionRecord::Deposit {
+            client: 1,
+            tx: 1,
+            amount: dec!(2.5),
+        },
+        TransactionRecord::Withdrawal {
+            client: 1,
+            tx: 2,
+            amount: dec!(0.0001),
+        },
+        TransactionRecord::Dispute { client: 1, tx: 1 },
+        TransactionRecord::Resolve {
+            client: 1,
+            tx: 1,
+            amount: None,
+        },
+        TransactionRecord::Chargeback {
+            client: 1,
+            tx: 1,
+            amount: None,
+        },
+        TransactionRecord::Open {
+            client: u16::MAX,
+            tx: u64::from(u32::MAX),
+        },
+        TransactionRecord::Unlock { client: 2, tx: 3 },
+        TransactionRecord::Deposit {
+            client: 3,
+            tx: 4,
+            amount: dec!(922337203685477.5807),
+        },
+    ];
+
+    let encoded = encode(records.clone());
+    assert_eq!(encoded.len(), 15 * records.len());
+
+    let decoded: Vec<TransactionRecord> = parse_records_binary(encoded.as_slice())
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert_eq!(decoded, records);
+}
+
+#[test]
+fn records_are_little_endian_with_the_amount_scaled() {
+    let encoded = encode([TransactionRecord::Deposit {
+        client: 0x0102,
+        tx: 0x0304_0506,
+        amount: dec!(1.5),
+    }]);
+
+    assert_eq!(
+        encoded,
+        [
+            0, 0x02, 0x01, 0x06, 0x05, 0x04, 0x03, 0x98, 0x3a, 0, 0, 0, 0, 0, 0
+        ]
+    );
+}
+
+#[rstest]
+fn binary_input_matches_csv_input(#[values(None, Some(2))] workers: Option<usize>) {
+    let csv = std::fs::read(
+        std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/data/representative.csv"),
+    )
+    .unwrap();
+    let encoded = encode(parse_records(csv.as_slice()).flatten());
+    let config = || {
+        let config = ProcessConfig::default().sort_by_client(true);
+        match workers {
+            Some(workers) => config.workers(workers),
+            None => config,
+        }
+    };
+
+    let from_binary: Vec<AccountRecord> =
+        process_binary(encoded.as_slice(), config(), |_| {}, |_| {}).collect();
+    let from_csv: Vec<AccountRecord> =
+        process_with_config(csv.as_slice(), config(), |_| {}, |_| {}).collect();
+
+    assert!(!from_binary.is_empty());
+    assert_eq!(from_binary, from_csv);
+}
+
+#[test]
+fn invalid_records_are_reported_and_skipped() {
+    let mut encoded = encode([TransactionRecord::Deposit {
+        client: 1,
+        tx: 1,
+        amount: dec!(1.0),
+    }]);
+    // an unknown type, and a dispute carrying an amount
+    encoded.extend([7, 1, 0, 2, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+    encoded.extend([2, 1, 0, 1, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0]);
+    encoded.extend(encode([TransactionRecord::Withdrawal {
+        client: 1,
+        tx: 3,
+        amount: dec!(0.5),
+    }]));
+
+    let mut errors = Vec::new();
+    let records: Vec<AccountRecord> = process_binary(
+        encoded.as_slice(),
+        ProcessConfig::default(),
+        |e| errors.push(e.to_string()),
+        |_| {},
+    )
+    .collect();
+
+    assert_eq!(
+        errors,
+        [
+            "validation error — client: 1, tx: 2: unknown transaction type 7",
+            "validation error — client: 1, tx: 1: an amount must not be provided with a dispute transaction",
+        ]
+    );
+    assert_eq!(records[0].available, dec!(0.5));
+}
+
+#[test]
+fn input_ending_within_a_record_ends_the_decoding() {
+    let mut encoded = encode([TransactionRecord::Deposit {
+        client: 1,
+        tx: 1,
+        amount: dec!(1.0),
+    }]);
+    encoded.extend([0, 1, 0]);
+
+    let results: Vec<_> = parse_records_binary(encoded.as_slice()).collect();
+
+    assert_eq!(results.len(), 2);
+    assert!(results[0].is_ok());
+    match &results[1] {
+        Err(Error::Binary { record, source }) => {
+            assert_eq!(*record, 1);
+            assert_eq!(source.kind(), ErrorKind::UnexpectedEof);
+        }
+        other => panic!("expected a binary input error, got {other:?}"),
+    }
+}
+
+#[rstest]
+#[case::tx_beyond_u32(TransactionRecord::Dispute { client: 1, tx: u64::from(u32::MAX) + 1 })]
+#[case::amount_too_precise(TransactionRecord::Deposit { client: 1, tx: 1, amount: dec!(0.00001) })]
+#[case::amount_too_large(TransactionRecord::Deposit {
+    client: 1,
+    tx: 1,
+    amount: dec!(922337203685477.5808),
+})]
+fn unencodable_transactions_are_rejected(#[case] record: TransactionRecord) {
+    let mut encoded = Vec::new();
+
+    let error = write_transactions_binary([record], &mut encoded).unwrap_err();
+
+    assert_eq!(error.kind(), ErrorKind::InvalidInput);
+    assert!(encoded.is_empty());
+}
+
+#[test]
+fn partial_settlements_keep_their_amount() {
+    let encoded = encode([
+        TransactionRecord::Deposit {
+            client: 1,
+            tx: 1,
+            amount: dec!(10.0),
+        },
+        TransactionRecord::Dispute { client: 1, tx: 1 },
+        TransactionRecord::Resolve {
+            client: 1,
+            tx: 1,
+            amount: Some(dec!(3.0)),
+        },
+    ]);
+    assert_eq!(&encoded[37..45], 30_000i64.to_le_bytes());
+
+    let config = ProcessConfig::default().partial_settlements(true);
+    let records: Vec<AccountRecord> = process_binary(
+        encoded.as_slice(),
+        config,
+        |e| panic!("unexpected error: {e}"),
+        |_| {},
+    )
+    .collect();
+
+    assert_eq!(records[0].available, dec!(3.0));
+    assert_eq!(records[0].held, dec!(7.0));
+}
@@ -1,7 +1,14 @@
 //! "Manual" integration tests targeted mainly on the chargeback mechanic
 
+use std::sync::Mutex;
+
+use rstest::rstest;
+
 use rust_decimal_macros::dec;
-use tx_engine_rs::{AccountRecord, Error, TransactionRecord, process};
+use tx_engine_rs::{
+    AccountRecord, Error, LockReason, Mutation, OutputFormat, ProcessConfig, ProcessingKind,
+    TransactionRecord, aggregate, process, process_with_config, write_records,
+};
 
 #[test]
 fn deposit_dispute_then_chargeback() {
@@ -17,6 +24,8 @@ chargeback, 1, 1,";
         held: dec!(0),
         total: dec!(0),
         locked: true,
+        lock_reason: Some(LockReason::Chargeback { tx_id: 1 }),
+        num_transactions: None,
     };
 
     let mut errors: Vec<Error> = Vec::new();
@@ -47,7 +56,11 @@ chargeback, 1, 1,";
     );
     assert_eq!(
         successful_txs[2],
-        TransactionRecord::Chargeback { client: 1, tx: 1 }
+        TransactionRecord::Chargeback {
+            client: 1,
+            tx: 1,
+            amount: None
+        }
     );
 }
 
@@ -64,6 +77,8 @@ chargeback, 1, 3,";
         held: dec!(0),
         total: dec!(10.0),
         locked: false,
+        lock_reason: None,
+        num_transactions: None,
     };
 
     let mut errors: Vec<Error> = Vec::new();
@@ -106,6 +121,8 @@ chargeback, 1, 1,";
         held: dec!(0),
         total: dec!(10.0),
         locked: false,
+        lock_reason: None,
+        num_transactions: None,
     };
 
     let mut errors: Vec<Error> = Vec::new();
@@ -150,6 +167,8 @@ chargeback, 1, 1,";
         held: dec!(0),
         total: dec!(0),
         locked: true,
+        lock_reason: Some(LockReason::Chargeback { tx_id: 1 }),
+        num_transactions: None,
     };
 
     let mut errors: Vec<Error> = Vec::new();
@@ -164,7 +183,8 @@ chargeback, 1, 1,";
     assert_eq!(records.len(), 1);
     assert_eq!(records[0], expected);
 
-    // Deposit, dispute, first chargeback succeed; second chargeback errors (account frozen)
+    // Deposit, dispute, first chargeback succeed; second chargeback errors (reported as already charged back rather
+    // than as a locked account)
     assert_eq!(successful_txs.len(), 3);
     assert_eq!(errors.len(), 1);
     assert!(
@@ -173,10 +193,11 @@ chargeback, 1, 1,";
             Error::Processing {
                 client_id: 1,
                 tx_id: 1,
+                kind: ProcessingKind::AlreadyChargedBack,
                 ..
             }
         ),
-        "expected a processing error for the duplicate chargeback"
+        "expected an already-charged-back error for the duplicate chargeback"
     );
 }
 
@@ -195,6 +216,8 @@ deposit, 1, 2, 5.0";
         held: dec!(0),
         total: dec!(0),
         locked: true,
+        lock_reason: Some(LockReason::Chargeback { tx_id: 1 }),
+        num_transactions: None,
     };
 
     let mut errors: Vec<Error> = Vec::new();
@@ -215,13 +238,12 @@ deposit, 1, 2, 5.0";
     assert!(
         matches!(
             &errors[0],
-            Error::Processing {
+            Error::AccountLocked {
                 client_id: 1,
                 tx_id: 2,
-                ..
             }
         ),
-        "expected a processing error for deposit on frozen account"
+        "expected an account-locked error for deposit on frozen account"
     );
 }
 
@@ -241,6 +263,8 @@ withdrawal, 1, 3, 3.0";
         held: dec!(0),
         total: dec!(5),
         locked: true,
+        lock_reason: Some(LockReason::Chargeback { tx_id: 1 }),
+        num_transactions: None,
     };
 
     let mut errors: Vec<Error> = Vec::new();
@@ -261,13 +285,12 @@ withdrawal, 1, 3, 3.0";
     assert!(
         matches!(
             &errors[0],
-            Error::Processing {
+            Error::AccountLocked {
                 client_id: 1,
                 tx_id: 3,
-                ..
             }
         ),
-        "expected a processing error for withdrawal on frozen account"
+        "expected an account-locked error for withdrawal on frozen account"
     );
 }
 
@@ -287,6 +310,8 @@ dispute, 1, 2,";
         held: dec!(0),
         total: dec!(5),
         locked: true,
+        lock_reason: Some(LockReason::Chargeback { tx_id: 1 }),
+        num_transactions: None,
     };
 
     let mut errors: Vec<Error> = Vec::new();
@@ -307,13 +332,12 @@ dispute, 1, 2,";
     assert!(
         matches!(
             &errors[0],
-            Error::Processing {
+            Error::AccountLocked {
                 client_id: 1,
                 tx_id: 2,
-                ..
             }
         ),
-        "expected a processing error for dispute on frozen account"
+        "expected an account-locked error for dispute on frozen account"
     );
 }
 
@@ -332,6 +356,8 @@ chargeback, 1, 1,";
         held: dec!(0),
         total: dec!(20),
         locked: true,
+        lock_reason: Some(LockReason::Chargeback { tx_id: 1 }),
+        num_transactions: None,
     };
 
     let mut errors: Vec<Error> = Vec::new();
@@ -351,6 +377,30 @@ chargeback, 1, 1,";
     assert_eq!(successful_txs.len(), 4);
 }
 
+#[test]
+fn ledger_total_only_shrinks_by_the_charged_back_funds() {
+    let input = "\
+type, client, tx, amount
+deposit, 1, 1, 10.0
+deposit, 2, 2, 7.5
+withdrawal, 1, 3, 2.5
+deposit, 3, 4, 4.0
+dispute, 2, 2,
+dispute, 3, 4,
+resolve, 3, 4,
+dispute, 3, 4,
+chargeback, 2, 2,
+withdrawal, 3, 5, 100.0";
+
+    let records: Vec<AccountRecord> = process(input.as_bytes(), |_| {}, |_| {}).collect();
+    let totals = aggregate(&records);
+
+    // deposits 21.5, withdrawals 2.5, charged back 7.5
+    assert_eq!(totals.total, dec!(11.5));
+    assert_eq!(totals.held, dec!(4.0));
+    assert_eq!(totals.available + totals.held, totals.total);
+}
+
 #[test]
 fn frozen_account_rejects_chargeback_on_other_dispute() {
     let input = "\
@@ -368,6 +418,8 @@ chargeback, 1, 2,";
         held: dec!(20),
         total: dec!(20),
         locked: true,
+        lock_reason: Some(LockReason::Chargeback { tx_id: 1 }),
+        num_transactions: None,
     };
 
     let mut errors: Vec<Error> = Vec::new();
@@ -389,12 +441,321 @@ chargeback, 1, 2,";
     assert!(
         matches!(
             &errors[0],
-            Error::Processing {
+            Error::AccountLocked {
                 client_id: 1,
                 tx_id: 2,
-                ..
             }
         ),
-        "expected a processing error for chargeback on frozen account"
+        "expected an account-locked error for chargeback on frozen account"
+    );
+}
+
+#[derive(Debug)]
+enum LockEvent {
+    Locked(AccountRecord),
+    Rejected(Error),
+}
+
+const LOCK_THEN_REJECT_INPUT: &str = "\
+type, client, tx, amount
+deposit, 1, 1, 10.0
+deposit, 1, 2, 5.0
+dispute, 1, 1,
+chargeback, 1, 1,
+deposit, 1, 3, 7.0";
+
+fn assert_lock_snapshot_precedes_rejection(events: Vec<LockEvent>) {
+    assert_eq!(
+        events.len(),
+        2,
+        "expected one lock and one rejection: {events:?}"
+    );
+    let LockEvent::Locked(snapshot) = &events[0] else {
+        panic!("expected the lock snapshot first, got: {events:?}");
+    };
+    assert_eq!(
+        *snapshot,
+        AccountRecord {
+            client: 1,
+            available: dec!(5.0),
+            held: dec!(0),
+            total: dec!(5.0),
+            locked: true,
+            lock_reason: Some(LockReason::Chargeback { tx_id: 1 }),
+            num_transactions: None,
+        }
+    );
+    assert!(
+        matches!(
+            &events[1],
+            LockEvent::Rejected(Error::AccountLocked {
+                client_id: 1,
+                tx_id: 3,
+            })
+        ),
+        "expected the deposit on the locked account to be rejected after the lock"
+    );
+}
+
+#[test]
+fn locked_account_is_reported_at_lock_time() {
+    let events = Mutex::new(Vec::new());
+
+    let config = ProcessConfig::default().on_locked_account(|record| {
+        events.lock().unwrap().push(LockEvent::Locked(record));
+    });
+    let records: Vec<AccountRecord> = process_with_config(
+        LOCK_THEN_REJECT_INPUT.as_bytes(),
+        config,
+        |e| events.lock().unwrap().push(LockEvent::Rejected(e)),
+        |_| {},
+    )
+    .collect();
+
+    assert_eq!(records.len(), 1);
+    assert_lock_snapshot_precedes_rejection(events.into_inner().unwrap());
+}
+
+#[test]
+fn locked_account_is_reported_at_lock_time_parallel() {
+    let locked = Mutex::new(Vec::new());
+    let mut errors: Vec<Error> = Vec::new();
+
+    let config = ProcessConfig::default()
+        .workers(2)
+        .channel_capacity(16)
+        .on_locked_account(|record| {
+            locked.lock().unwrap().push(record);
+        });
+    let records: Vec<AccountRecord> = process_with_config(
+        LOCK_THEN_REJECT_INPUT.as_bytes(),
+        config,
+        |e| errors.push(e),
+        |_| {},
+    )
+    .collect();
+
+    assert_eq!(records.len(), 1);
+    // error delivery runs on a separate callback thread in the parallel engine,
+    // so only the lock snapshot itself can be compared
+    let locked = locked.into_inner().unwrap();
+    let events = locked
+        .into_iter()
+        .map(LockEvent::Locked)
+        .chain(errors.into_iter().map(LockEvent::Rejected))
+        .collect();
+    assert_lock_snapshot_precedes_rejection(events);
+}
+
+#[rstest]
+#[case::sequential(None)]
+#[case::parallel(Some(2))]
+fn lock_is_reported_once_per_account(#[case] workers: Option<usize>) {
+    let input = "\
+type, client, tx, amount
+deposit, 1, 1, 10.0
+deposit, 2, 2, 5.0
+dispute, 1, 1,
+chargeback, 1, 1,
+chargeback, 1, 1,
+resolve, 1, 1,
+deposit, 1, 3, 1.0
+dispute, 2, 2,
+chargeback, 2, 2,";
+
+    let locked_clients = Mutex::new(Vec::new());
+    let mut config = ProcessConfig::default()
+        .on_locked_account(|record| locked_clients.lock().unwrap().push(record.client));
+    if let Some(workers) = workers {
+        config = config.workers(workers).channel_capacity(4);
+    }
+    let mut errors = 0;
+    let _ = process_with_config(input.as_bytes(), config, |_| errors += 1, |_| {}).count();
+
+    let mut locked_clients = locked_clients.into_inner().unwrap();
+    locked_clients.sort();
+    assert_eq!(locked_clients, vec![1, 2]);
+    // the transactions on the locked account are still rejected, without reporting the lock again
+    assert_eq!(errors, 3);
+}
+
+#[rstest]
+fn mutations_of_a_chargeback_sequence_are_logged(#[values(None, Some(2))] workers: Option<usize>) {
+    let input = "\
+type, client, tx, amount
+deposit, 1, 1, 10.0
+withdrawal, 1, 2, 20.0
+dispute, 1, 1,
+chargeback, 1, 1,";
+
+    let mutations = Mutex::new(Vec::new());
+    let mut config =
+        ProcessConfig::default().on_mutation(|mutation| mutations.lock().unwrap().push(mutation));
+    if let Some(workers) = workers {
+        config = config.workers(workers);
+    }
+    let _ = process_with_config(input.as_bytes(), config, |_| {}, |_| {}).count();
+
+    // the rejected withdrawal is not logged
+    assert_eq!(
+        mutations.into_inner().unwrap(),
+        vec![
+            Mutation {
+                client: 1,
+                transaction: TransactionRecord::Deposit {
+                    client: 1,
+                    tx: 1,
+                    amount: dec!(10.0),
+                },
+                before: (dec!(0), dec!(0)),
+                after: (dec!(10.0), dec!(0)),
+            },
+            Mutation {
+                client: 1,
+                transaction: TransactionRecord::Dispute { client: 1, tx: 1 },
+                before: (dec!(10.0), dec!(0)),
+                after: (dec!(0), dec!(10.0)),
+            },
+            Mutation {
+                client: 1,
+                transaction: TransactionRecord::Chargeback {
+                    client: 1,
+                    tx: 1,
+                    amount: None
+                },
+                before: (dec!(0), dec!(10.0)),
+                after: (dec!(0), dec!(0)),
+            },
+        ]
+    );
+}
+
+#[test]
+fn lock_reason_names_the_charged_back_transaction() {
+    let input = "\
+type, client, tx, amount
+deposit, 1, 1, 10.0
+deposit, 1, 2, 5.0
+deposit, 2, 3, 1.0
+dispute, 1, 2,
+chargeback, 1, 2,";
+
+    let mut records: Vec<AccountRecord> = process(input.as_bytes(), |_| {}, |_| {}).collect();
+    records.sort_by_key(|r| r.client);
+
+    assert_eq!(
+        records[0].lock_reason,
+        Some(LockReason::Chargeback { tx_id: 2 })
+    );
+    assert_eq!(records[1].lock_reason, None);
+
+    let mut buffer = Vec::new();
+    write_records(records, &mut buffer, OutputFormat::JsonLines).unwrap();
+    assert_eq!(
+        String::from_utf8(buffer).unwrap(),
+        "{\"client\":1,\"available\":\"10.0\",\"held\":\"0.0\",\"total\":\"10.0\",\"locked\":true,\
+         \"lock_reason\":{\"reason\":\"chargeback\",\"tx_id\":2}}\n\
+         {\"client\":2,\"available\":\"1.0\",\"held\":\"0\",\"total\":\"1.0\",\"locked\":false}\n"
+    );
+}
+
+#[test]
+fn csv_output_keeps_its_columns_for_locked_accounts() {
+    let input = "\
+type, client, tx, amount
+deposit, 1, 1, 10.0
+dispute, 1, 1,
+chargeback, 1, 1,";
+
+    let records = process(input.as_bytes(), |_| {}, |_| {});
+    let mut buffer = Vec::new();
+    write_records(records, &mut buffer, OutputFormat::Csv).unwrap();
+
+    assert_eq!(
+        String::from_utf8(buffer).unwrap(),
+        "client,available,held,total,locked\n1,0.0,0.0,0.0,true\n"
+    );
+}
+
+#[rstest]
+fn admin_unlock_restores_deposits(#[values(None, Some(2))] workers: Option<usize>) {
+    let input = "\
+type, client, tx, amount
+deposit, 1, 1, 10.0
+deposit, 1, 2, 3.0
+dispute, 1, 1,
+chargeback, 1, 1,
+unlock, 1, 3,
+deposit, 1, 4, 2.0";
+
+    let mut config = ProcessConfig::default().allow_admin_unlock(true);
+    if let Some(workers) = workers {
+        config = config.workers(workers);
+    }
+    let mut errors: Vec<Error> = Vec::new();
+    let records: Vec<AccountRecord> =
+        process_with_config(input.as_bytes(), config, |e| errors.push(e), |_| {}).collect();
+
+    assert!(errors.is_empty(), "expected no errors, got: {errors:?}");
+    assert_eq!(
+        records,
+        vec![AccountRecord {
+            client: 1,
+            available: dec!(5.0),
+            held: dec!(0.0),
+            total: dec!(5.0),
+            locked: false,
+            lock_reason: None,
+            num_transactions: None,
+        }]
+    );
+}
+
+#[test]
+fn unlock_of_an_unlocked_account_is_a_no_op() {
+    let input = "\
+type, client, tx, amount
+deposit, 1, 1, 10.0
+unlock, 1, 2,";
+
+    let config = ProcessConfig::default().allow_admin_unlock(true);
+    let mut errors: Vec<Error> = Vec::new();
+    let mut successes: Vec<TransactionRecord> = Vec::new();
+    let records: Vec<AccountRecord> = process_with_config(
+        input.as_bytes(),
+        config,
+        |e| errors.push(e),
+        |tx| successes.push(tx),
+    )
+    .collect();
+
+    assert!(errors.is_empty(), "expected no errors, got: {errors:?}");
+    assert_eq!(
+        successes.last(),
+        Some(&TransactionRecord::Unlock { client: 1, tx: 2 })
+    );
+    assert_eq!(records.len(), 1);
+    assert_eq!(records[0].available, dec!(10.0));
+    assert!(!records[0].locked);
+}
+
+#[test]
+fn unlock_is_rejected_unless_allowed() {
+    let input = "\
+type, client, tx, amount
+deposit, 1, 1, 10.0
+dispute, 1, 1,
+chargeback, 1, 1,
+unlock, 1, 2,";
+
+    let mut errors: Vec<Error> = Vec::new();
+    let records: Vec<AccountRecord> =
+        process(input.as_bytes(), |e| errors.push(e), |_| {}).collect();
+
+    assert_eq!(errors.len(), 1);
+    assert_eq!(
+        errors[0].processing_kind(),
+        Some(ProcessingKind::UnlockNotAllowed)
     );
+    assert!(records[0].locked);
 }
@@ -0,0 +1,531 @@
+//! Integration tests for the configuration of a processing run via `ProcessConfig`
+
+use rstest::rstest;
+use rust_decimal_macros::dec;
+use tx_engine_rs::{
+    AccountRecord, ChannelCapacity, DEFAULT_CHANNEL_CAPACITY, DisputePolicy, Error, OutputFormat,
+    ProcessConfig, process, process_with_config, process_with_stats, write_records,
+};
+
+const INPUT: &str = "\
+type, client, tx, amount
+deposit, 1, 1, 1.0005
+deposit, 2, 2, 2.5
+withdrawal, 1, 3, 0.5
+dispute, 2, 2,";
+
+fn sorted(mut records: Vec<AccountRecord>) -> Vec<AccountRecord> {
+    records.sort_by_key(|r| r.client);
+    records
+}
+
+#[test]
+fn default_config_matches_process() {
+    let expected = sorted(process(INPUT.as_bytes(), |_| {}, |_| {}).collect());
+    let actual = sorted(
+        process_with_config(INPUT.as_bytes(), ProcessConfig::default(), |_| {}, |_| {}).collect(),
+    );
+
+    assert_eq!(actual, expected);
+}
+
+#[test]
+fn configured_workers_match_sequential_processing() {
+    let expected = sorted(process(INPUT.as_bytes(), |_| {}, |_| {}).collect());
+
+    let config = ProcessConfig::default().workers(3).channel_capacity(1);
+    let actual = sorted(process_with_config(INPUT.as_bytes(), config, |_| {}, |_| {}).collect());
+
+    assert_eq!(actual, expected);
+}
+
+#[test]
+fn zero_workers_fall_back_to_one() {
+    let config = ProcessConfig::default().workers(0);
+    let records: Vec<AccountRecord> =
+        process_with_config(INPUT.as_bytes(), config, |_| {}, |_| {}).collect();
+
+    assert_eq!(records.len(), 2);
+}
+
+#[test]
+fn rounding_applies_to_output_records() {
+    let config = ProcessConfig::default().rounding(2);
+    let records = sorted(process_with_config(INPUT.as_bytes(), config, |_| {}, |_| {}).collect());
+
+    assert_eq!(records[0].available, dec!(0.50));
+    assert_eq!(records[0].total, dec!(0.50));
+}
+
+#[rstest]
+#[case::four_decimal_places(None, vec![])]
+#[case::two_decimal_places(Some(2), vec![(1, dec!(1.2345))])]
+fn records_losing_precision_in_the_output_are_reported(
+    #[case] rounding: Option<u32>,
+    #[case] expected: Vec<(u16, rust_decimal::Decimal)>,
+) {
+    let input = "\
+type, client, tx, amount
+deposit, 1, 1, 1.2345
+deposit, 2, 2, 1.25
+deposit, 3, 3, 10.0000";
+
+    let lossy = std::sync::Mutex::new(Vec::new());
+    let mut config = ProcessConfig::default().on_precision_loss(|record| {
+        lossy
+            .lock()
+            .unwrap()
+            .push((record.client, record.available))
+    });
+    if let Some(decimal_places) = rounding {
+        config = config.rounding(decimal_places);
+    }
+    let records = sorted(process_with_config(input.as_bytes(), config, |_| {}, |_| {}).collect());
+
+    assert_eq!(records.len(), 3);
+    assert_eq!(lossy.into_inner().unwrap(), expected);
+}
+
+#[test]
+fn tab_delimited_input_matches_comma_delimited_input() {
+    let expected = sorted(process(INPUT.as_bytes(), |_| {}, |_| {}).collect());
+
+    let tsv = INPUT.replace(',', "\t");
+    let config = ProcessConfig::default().delimiter(b'\t');
+    let actual = sorted(process_with_config(tsv.as_bytes(), config, |_| {}, |_| {}).collect());
+
+    assert_eq!(actual, expected);
+}
+
+#[test]
+fn client_zero_is_reserved_by_default() {
+    let input = "\
+type, client, tx, amount
+deposit, 0, 1, 5.0
+deposit, 1, 2, 5.0";
+
+    let mut errors = Vec::new();
+    let records: Vec<AccountRecord> =
+        process(input.as_bytes(), |e| errors.push(e), |_| {}).collect();
+
+    assert_eq!(records.len(), 1);
+    assert_eq!(records[0].client, 1);
+    assert!(matches!(
+        errors.as_slice(),
+        [Error::Validation {
+            client_id: 0,
+            tx_id: 1,
+            ..
+        }]
+    ));
+}
+
+#[test]
+fn reserved_clients_are_configurable() {
+    let input = "\
+type, client, tx, amount
+deposit, 0, 1, 5.0
+deposit, 7, 2, 5.0";
+
+    let config = ProcessConfig::default().reserved_clients(5..=9);
+    let mut errors = Vec::new();
+    let records: Vec<AccountRecord> =
+        process_with_config(input.as_bytes(), config, |e| errors.push(e), |_| {}).collect();
+
+    assert_eq!(records.len(), 1);
+    assert_eq!(records[0].client, 0);
+    assert!(matches!(
+        errors.as_slice(),
+        [Error::Validation { client_id: 7, .. }]
+    ));
+}
+
+#[test]
+fn commented_input_matches_input_without_comments() {
+    let expected = sorted(process(INPUT.as_bytes(), |_| {}, |_| {}).collect());
+
+    let commented: String = INPUT
+        .lines()
+        .flat_map(|line| ["# annotation", line])
+        .collect::<Vec<_>>()
+        .join("\n");
+    let config = ProcessConfig::default().comment_char(b'#');
+    let mut errors = Vec::new();
+    let actual = sorted(
+        process_with_config(commented.as_bytes(), config, |e| errors.push(e), |_| {}).collect(),
+    );
+
+    assert!(errors.is_empty(), "expected no errors, got: {errors:?}");
+    assert_eq!(actual, expected);
+}
+
+#[test]
+fn trailing_timestamp_column_is_ignored_if_flexible() {
+    let input = "\
+type, client, tx, amount
+deposit, 1, 1, 10.0, 1704103200
+withdrawal, 1, 2, 2.5, 1704106800
+deposit, 2, 3, 1.0, 1704110400";
+
+    let config = ProcessConfig::default().flexible_columns(true);
+    let records = sorted(
+        process_with_config(
+            input.as_bytes(),
+            config,
+            |e| panic!("unexpected error: {e}"),
+            |_| {},
+        )
+        .collect(),
+    );
+
+    assert_eq!(records.len(), 2);
+    assert_eq!(records[0].available, dec!(7.5));
+    assert_eq!(records[1].available, dec!(1.0));
+}
+
+#[test]
+fn ordering_is_enforced_globally_by_the_parallel_engine() {
+    let input = "\
+type, client, tx, amount, timestamp
+deposit, 1, 1, 10.0, 1000
+deposit, 2, 2, 5.0, 1001
+deposit, 3, 3, 1.0, 999
+withdrawal, 1, 4, 2.0, 1002";
+
+    let config = ProcessConfig::default().enforce_ordering(true).workers(2);
+    let errors = std::sync::Mutex::new(Vec::new());
+    let records = sorted(
+        process_with_config(
+            input.as_bytes(),
+            config,
+            |e| errors.lock().unwrap().push(e),
+            |_| {},
+        )
+        .collect(),
+    );
+
+    // the deposit of client 3 is out of order across clients
+    assert_eq!(
+        records.iter().map(|r| r.client).collect::<Vec<_>>(),
+        vec![1, 2]
+    );
+    assert_eq!(records[0].available, dec!(8.0));
+    assert!(matches!(
+        errors.into_inner().unwrap().as_slice(),
+        [Error::Validation {
+            client_id: 3,
+            tx_id: 3,
+            ..
+        }]
+    ));
+}
+
+#[test]
+fn streamed_worker_output_groups_the_clients_by_worker() {
+    let input: String = std::iter::once("type, client, tx, amount\n".to_string())
+        .chain((1..=8).map(|client| format!("deposit, {client}, {client}, 1.0\n")))
+        .collect();
+    let expected = sorted(process(input.as_bytes(), |_| {}, |_| {}).collect());
+
+    let config = ProcessConfig::default()
+        .workers(2)
+        .stream_worker_output(true);
+    let records: Vec<AccountRecord> =
+        process_with_config(input.as_bytes(), config, |_| {}, |_| {}).collect();
+
+    // the clients are sharded by `client % workers`; the shard changes once between the two groups
+    let shard_changes = records
+        .windows(2)
+        .filter(|pair| pair[0].client % 2 != pair[1].client % 2)
+        .count();
+    assert_eq!(shard_changes, 1);
+    assert_eq!(sorted(records), expected);
+}
+
+#[rstest]
+#[case::fixed(ChannelCapacity::Fixed(7), 4, 7)]
+#[case::default(ChannelCapacity::default(), 4, DEFAULT_CHANNEL_CAPACITY)]
+#[case::auto_small_input(ChannelCapacity::Auto { estimated_rows: 1_000 }, 4, 64)]
+#[case::auto_large_input(ChannelCapacity::Auto { estimated_rows: 1_000_000 }, 4, 31_250)]
+#[case::auto_huge_input(ChannelCapacity::Auto { estimated_rows: 1_000_000_000 }, 4, 65_536)]
+#[case::auto_zero_workers(ChannelCapacity::Auto { estimated_rows: 8_000 }, 0, 1_000)]
+fn channel_capacity_is_resolved_per_worker_count(
+    #[case] capacity: ChannelCapacity,
+    #[case] workers: usize,
+    #[case] expected: usize,
+) {
+    assert_eq!(capacity.resolve(workers), expected);
+}
+
+#[test]
+fn auto_channel_capacity_matches_sequential_processing() {
+    let expected = sorted(process(INPUT.as_bytes(), |_| {}, |_| {}).collect());
+
+    let config = ProcessConfig::default()
+        .workers(2)
+        .channel_capacity(ChannelCapacity::Auto { estimated_rows: 4 });
+    let actual = sorted(process_with_config(INPUT.as_bytes(), config, |_| {}, |_| {}).collect());
+
+    assert_eq!(actual, expected);
+}
+
+#[test]
+fn accepted_transactions_are_counted_per_account() {
+    let input = "\
+type, client, tx, amount
+deposit, 1, 1, 10.0
+dispute, 1, 1,
+resolve, 1, 1,
+withdrawal, 1, 2, 3.0
+withdrawal, 1, 3, 30.0
+deposit, 2, 4, 1.0";
+
+    let config = ProcessConfig::default()
+        .include_num_transactions(true)
+        .sort_by_client(true);
+    let records: Vec<AccountRecord> =
+        process_with_config(input.as_bytes(), config, |_| {}, |_| {}).collect();
+
+    // the rejected withdrawal is not counted
+    assert_eq!(records[0].num_transactions, Some(4));
+    assert_eq!(records[1].num_transactions, Some(1));
+
+    let mut csv = Vec::new();
+    write_records(records, &mut csv, OutputFormat::Csv).unwrap();
+    assert_eq!(
+        String::from_utf8(csv).unwrap(),
+        "client,available,held,total,locked,num_transactions\n1,7.0,0.0,7.0,false,4\n2,1.0,0,1.0,false,1\n"
+    );
+}
+
+#[test]
+fn transactions_are_not_counted_by_default() {
+    let records: Vec<AccountRecord> = process(INPUT.as_bytes(), |_| {}, |_| {}).collect();
+
+    assert!(records.iter().all(|r| r.num_transactions.is_none()));
+}
+
+#[rstest]
+fn rows_beyond_max_rows_are_skipped(#[values(None, Some(2))] workers: Option<usize>) {
+    let input: String = std::iter::once("type, client, tx, amount".to_string())
+        .chain((1..=10).map(|tx| format!("deposit, 1, {tx}, 1.0")))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let mut config = ProcessConfig::default().max_rows(5);
+    if let Some(workers) = workers {
+        config = config.workers(workers);
+    }
+    let mut errors: Vec<Error> = Vec::new();
+    let (records, stats) = process_with_stats(input.as_bytes(), config, |e| errors.push(e), |_| {});
+    let records: Vec<_> = records.collect();
+
+    assert_eq!(stats.accepted, 5);
+    assert!(stats.truncated);
+    assert_eq!(stats.rejected(), 0);
+    assert_eq!(records[0].available, dec!(5.0));
+    assert_eq!(errors.len(), 1, "expected one error, got: {errors:?}");
+    assert!(matches!(errors[0], Error::Truncated { max_rows: 5 }));
+}
+
+#[test]
+fn input_within_max_rows_is_not_truncated() {
+    let config = ProcessConfig::default().max_rows(4);
+    let mut errors: Vec<Error> = Vec::new();
+    let (records, stats) = process_with_stats(INPUT.as_bytes(), config, |e| errors.push(e), |_| {});
+
+    assert_eq!(records.count(), 2);
+    assert!(!stats.truncated);
+    assert!(errors.is_empty(), "expected no errors, got: {errors:?}");
+}
+
+#[rstest]
+fn progress_is_reported_per_interval(#[values(None, Some(3))] workers: Option<usize>) {
+    // one malformed row, which counts as processed as well
+    let input: String = std::iter::once("type, client, tx, amount".to_string())
+        .chain((1..=100).map(|tx| match tx {
+            50 => "deposit, 1, x, 1.0".to_string(),
+            _ => format!("deposit, {}, {tx}, 1.0", tx % 7 + 1),
+        }))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let mut reported = Vec::new();
+    let mut config = ProcessConfig::default().on_progress(25, |processed| reported.push(processed));
+    if let Some(workers) = workers {
+        config = config.workers(workers);
+    }
+    let records = process_with_config(input.as_bytes(), config, |_| {}, |_| {}).count();
+
+    assert_eq!(records, 7);
+    assert_eq!(reported, vec![25, 50, 75, 100]);
+}
+
+/// The outcomes of a run in the order in which they were reported, errors by their display
+fn reported_outcomes(input: &str, config: ProcessConfig<'_>) -> (Vec<String>, Vec<AccountRecord>) {
+    let outcomes = std::sync::Mutex::new(Vec::new());
+    let records = process_with_config(
+        input.as_bytes(),
+        config.sort_by_client(true),
+        |e| outcomes.lock().unwrap().push(format!("error: {e}")),
+        |tx| outcomes.lock().unwrap().push(format!("success: {tx}")),
+    )
+    .collect();
+    (outcomes.into_inner().unwrap(), records)
+}
+
+#[test]
+fn deterministic_parallel_run_reports_like_the_sequential_one() {
+    // clients of different workers alternate, with dispute chains, a lock, and a malformed row in between
+    let input = "\
+type, client, tx, amount
+deposit, 1, 1, 10.0
+deposit, 2, 2, 5.0
+dispute, 1, 1,
+deposit, 3, 3, 1.0
+withdrawal, 2, 4, 6.0
+deposit, 1, x, 1.0
+chargeback, 1, 1,
+dispute, 2, 2,
+deposit, 1, 5, 2.0
+resolve, 2, 2,
+withdrawal, 3, 6, 1.0
+deposit, 4, 7, 3.0";
+
+    let sequential = reported_outcomes(input, ProcessConfig::default());
+    for _ in 0..10 {
+        let config = ProcessConfig::default().workers(3).deterministic(true);
+        assert_eq!(reported_outcomes(input, config), sequential);
+    }
+}
+
+#[rstest]
+fn normalized_balances_keep_their_values(#[values(None, Some(2))] workers: Option<usize>) {
+    // repeated dispute/resolve cycles of deposits with different scales, and a withdrawal in between (the zero
+    // deposit and the withdrawal of the first cycle are rejected)
+    let mut input = String::from("type, client, tx, amount\n");
+    for cycle in 0..50 {
+        let tx = 2 * cycle + 1;
+        input += &format!("deposit, 1, {tx}, 0.{cycle:04}\ndeposit, 2, {tx}, 1.5\n");
+        input += &format!("dispute, 1, {tx},\nresolve, 1, {tx},\ndispute, 2, {tx},\n");
+        input += &format!("withdrawal, 1, {}, 0.0001\n", tx + 1);
+    }
+    let mut config = ProcessConfig::default();
+    if let Some(workers) = workers {
+        config = config.workers(workers);
+    }
+
+    let expected = sorted(process(input.as_bytes(), |_| {}, |_| {}).collect());
+    let actual = sorted(
+        process_with_config(input.as_bytes(), config.normalize_scale(4), |_| {}, |_| {}).collect(),
+    );
+
+    assert_eq!(actual, expected);
+    assert_eq!(actual[0].available, dec!(0.1176));
+    assert_eq!(actual[1].held, dec!(75.0));
+    for record in &actual {
+        assert_eq!(
+            (record.available.scale(), record.held.scale()),
+            (4, 4),
+            "{record:?}"
+        );
+    }
+}
+
+#[rstest]
+#[case::one_worker_per_client(1, 2)]
+#[case::one_worker_per_min_rows(3, 1)]
+#[case::at_least_one_worker(100, 1)]
+#[case::no_read_ahead(0, 8)]
+fn small_input_uses_fewer_workers(#[case] min_rows: usize, #[case] expected_workers: u64) {
+    let input = "\
+type, client, tx, amount
+deposit, 1, 1, 10.0
+deposit, 2, 2, 5.0
+withdrawal, 1, 3, 2.0
+deposit, 1, x, 1.0
+dispute, 2, 2,";
+    let workers = std::sync::Mutex::new(0);
+    let config = ProcessConfig::default()
+        .workers(8)
+        .min_rows_per_worker(min_rows)
+        .on_backpressure(|waits| *workers.lock().unwrap() = waits.len());
+
+    let (records, stats) = process_with_stats(input.as_bytes(), config, |_| {}, |_| {});
+
+    assert_eq!(
+        sorted(records.collect()),
+        sorted(process(input.as_bytes(), |_| {}, |_| {}).collect())
+    );
+    assert_eq!(stats.workers, expected_workers);
+    assert_eq!(*workers.lock().unwrap() as u64, expected_workers);
+}
+
+#[rstest]
+fn funds_are_conserved_across_all_kinds_of_transactions(
+    #[values(None, Some(3))] workers: Option<usize>,
+) {
+    // a clamped deposit, a partly held dispute, and chargebacks of a deposit and of a withdrawal
+    let input = "\
+type, client, tx, amount
+deposit, 1, 1, 10.0
+deposit, 1, 2, 200.0
+withdrawal, 1, 3, 4.0
+dispute, 1, 3,
+chargeback, 1, 3,
+deposit, 2, 4, 5.0
+withdrawal, 2, 5, 3.0
+dispute, 2, 4,
+chargeback, 2, 4,
+deposit, 3, 6, 1.5
+dispute, 3, 6,
+resolve, 3, 6,
+open, 4, 7,";
+    let mut config = ProcessConfig::default()
+        .verify_conservation(true)
+        .dispute_withdrawals(true)
+        .dispute_policy(DisputePolicy::PartialHold)
+        .max_balance(dec!(100.0))
+        .clamp_to_max_balance(true);
+    if let Some(workers) = workers {
+        config = config.workers(workers);
+    }
+
+    let errors = std::sync::Mutex::new(Vec::new());
+    let records = sorted(
+        process_with_config(
+            input.as_bytes(),
+            config,
+            |e| errors.lock().unwrap().push(e),
+            |_| {},
+        )
+        .collect(),
+    );
+
+    assert!(errors.into_inner().unwrap().is_empty());
+    let totals: Vec<_> = records.iter().map(|record| record.total).collect();
+    assert_eq!(totals, vec![dec!(100.0), dec!(0.0), dec!(1.5), dec!(0)]);
+}
+
+#[test]
+fn rounded_balances_are_reported_as_not_conserved() {
+    let input = "\
+type, client, tx, amount
+deposit, 1, 1, 1.25
+deposit, 2, 2, 2.25";
+    let config = ProcessConfig::default()
+        .verify_conservation(true)
+        .normalize_scale(1);
+
+    let mut errors = Vec::new();
+    let _ = process_with_config(input.as_bytes(), config, |e| errors.push(e), |_| {}).count();
+
+    assert_eq!(errors.len(), 1);
+    assert!(
+        matches!(
+            errors[0],
+            Error::NotConserved { expected, actual } if expected == dec!(3.50) && actual == dec!(3.6)
+        ),
+        "{errors:?}"
+    );
+}
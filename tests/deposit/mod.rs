@@ -1,6 +1,11 @@
 //! Integration tests for deposit transactions
 
-use tx_engine_rs::{AccountRecord, Error, TransactionRecord, process};
+use rstest::rstest;
+use rust_decimal::Decimal;
+use tx_engine_rs::{
+    AccountRecord, Error, ProcessConfig, ProcessingKind, TransactionRecord, process,
+    process_with_config,
+};
 
 use rust_decimal_macros::dec;
 
@@ -17,6 +22,8 @@ deposit, 1, 1, 1.5";
         held: dec!(0),
         total: dec!(1.5),
         locked: false,
+        lock_reason: None,
+        num_transactions: None,
     };
 
     // Act
@@ -101,3 +108,312 @@ deposit, 1, 1, -5.0";
         "expected a validation error for client 1, tx 1"
     );
 }
+
+#[test]
+fn transaction_ids_beyond_u32_are_supported() {
+    let input = "\
+type, client, tx, amount
+deposit, 1, 9876543210, 2.0
+deposit, 1, 4294967296, 1.0
+dispute, 1, 9876543210,";
+
+    let mut successes: Vec<TransactionRecord> = Vec::new();
+    let records: Vec<AccountRecord> = process(
+        input.as_bytes(),
+        |e| panic!("unexpected error: {e}"),
+        |tx| successes.push(tx),
+    )
+    .collect();
+
+    assert_eq!(records[0].available, dec!(1.0));
+    assert_eq!(records[0].held, dec!(2.0));
+    assert_eq!(
+        successes[2],
+        TransactionRecord::Dispute {
+            client: 1,
+            tx: 9_876_543_210,
+        }
+    );
+}
+
+#[test]
+fn transaction_id_beyond_u64_is_a_csv_error() {
+    let input = "\
+type, client, tx, amount
+deposit, 1, 18446744073709551616, 1.0";
+
+    let mut errors: Vec<Error> = Vec::new();
+    let records: Vec<AccountRecord> =
+        process(input.as_bytes(), |e| errors.push(e), |_| {}).collect();
+
+    assert!(records.is_empty());
+    assert_eq!(errors.len(), 1);
+    assert!(
+        matches!(&errors[0], Error::Csv { line: Some(2), .. }),
+        "unexpected error: {:?}",
+        errors[0]
+    );
+}
+
+#[rstest]
+#[case::exactly_at_the_cap(dec!(10.00), None)]
+#[case::one_cent_over_the_cap(dec!(10.01), Some(ProcessingKind::BalanceCapExceeded))]
+fn deposit_is_checked_against_the_max_balance(
+    #[case] amount: Decimal,
+    #[case] expected_error: Option<ProcessingKind>,
+) {
+    // the held funds count towards the balance as well
+    let input = format!(
+        "\
+type, client, tx, amount
+deposit, 1, 1, 3.0
+deposit, 1, 2, 2.0
+dispute, 1, 2,
+deposit, 1, 3, {amount}"
+    );
+    let config = ProcessConfig::default().max_balance(dec!(15));
+
+    let mut errors: Vec<Error> = Vec::new();
+    let records: Vec<AccountRecord> =
+        process_with_config(input.as_bytes(), config, |e| errors.push(e), |_| {}).collect();
+
+    let kinds: Vec<_> = errors.iter().map(Error::processing_kind).collect();
+    match expected_error {
+        None => {
+            assert!(kinds.is_empty(), "unexpected errors: {errors:?}");
+            assert_eq!(records[0].total, dec!(15));
+        }
+        Some(kind) => {
+            assert_eq!(kinds, vec![Some(kind)]);
+            assert_eq!(records[0].total, dec!(5));
+        }
+    }
+}
+
+#[rstest]
+fn rejected_first_deposit_creates_no_account(#[values(None, Some(2))] workers: Option<usize>) {
+    let input = "\
+type, client, tx, amount
+deposit, 1, 1, 20.0
+deposit, 2, 2, 5.0
+withdrawal, 1, 3, 1.0";
+    let mut config = ProcessConfig::default().max_balance(dec!(10));
+    if let Some(workers) = workers {
+        config = config.workers(workers);
+    }
+
+    let mut errors: Vec<Error> = Vec::new();
+    let records: Vec<AccountRecord> =
+        process_with_config(input.as_bytes(), config, |e| errors.push(e), |_| {}).collect();
+
+    let kinds: Vec<_> = errors.iter().map(Error::processing_kind).collect();
+    assert_eq!(
+        kinds,
+        vec![
+            Some(ProcessingKind::BalanceCapExceeded),
+            Some(ProcessingKind::NoAccount)
+        ]
+    );
+    assert_eq!(
+        records
+            .iter()
+            .map(|record| record.client)
+            .collect::<Vec<_>>(),
+        vec![2]
+    );
+}
+
+#[test]
+fn deposit_exceeding_the_max_balance_is_clamped_if_configured() {
+    let input = "\
+type, client, tx, amount
+deposit, 1, 1, 8.0
+deposit, 1, 2, 5.0
+dispute, 1, 2,
+deposit, 1, 3, 1.0";
+    let config = ProcessConfig::default()
+        .max_balance(dec!(10))
+        .clamp_to_max_balance(true);
+
+    let mut errors: Vec<Error> = Vec::new();
+    let records: Vec<AccountRecord> =
+        process_with_config(input.as_bytes(), config, |e| errors.push(e), |_| {}).collect();
+
+    // only the credited 2.0 are held by the dispute; the account at the cap rejects the last deposit
+    assert_eq!(records[0].available, dec!(8.0));
+    assert_eq!(records[0].held, dec!(2.0));
+    assert_eq!(errors.len(), 1);
+    assert_eq!(
+        errors[0].processing_kind(),
+        Some(ProcessingKind::BalanceCapExceeded)
+    );
+}
+
+#[rstest]
+fn balances_close_to_the_maximum_do_not_overflow(#[values(None, Some(2))] workers: Option<usize>) {
+    // the held and the available funds fit on their own, but not their total
+    let input = format!(
+        "\
+type, client, tx, amount
+deposit, 1, 1, {max}
+dispute, 1, 1,
+deposit, 1, 2, {half}
+deposit, 1, 3, {half}
+deposit, 1, 4, 1.0",
+        max = Decimal::MAX,
+        half = Decimal::MAX / dec!(2)
+    );
+    let mut config = ProcessConfig::default();
+    if let Some(workers) = workers {
+        config = config.workers(workers);
+    }
+
+    let mut errors: Vec<Error> = Vec::new();
+    let records: Vec<AccountRecord> =
+        process_with_config(input.as_bytes(), config, |e| errors.push(e), |_| {}).collect();
+
+    let kinds: Vec<_> = errors.iter().map(Error::processing_kind).collect();
+    assert_eq!(kinds, vec![Some(ProcessingKind::ArithmeticOverflow); 3]);
+    assert_eq!(records[0].held, Decimal::MAX);
+    assert_eq!(records[0].total, Decimal::MAX);
+}
+
+#[rstest]
+#[case::rejected_by_default(false)]
+#[case::allowed(true)]
+fn zero_deposit_follows_the_zero_amount_policy(#[case] allow_zero_amount: bool) {
+    let input = "\
+type, client, tx, amount
+deposit, 1, 1, 0.0
+deposit, 1, 2, -1.0";
+
+    let mut errors: Vec<Error> = Vec::new();
+    let mut successes = Vec::new();
+    let config = ProcessConfig::default().allow_zero_amount(allow_zero_amount);
+    let records: Vec<AccountRecord> = process_with_config(
+        input.as_bytes(),
+        config,
+        |e| errors.push(e),
+        |tx| successes.push(tx),
+    )
+    .collect();
+
+    // negative amounts are invalid regardless of the policy
+    assert!(matches!(
+        errors.last(),
+        Some(Error::Validation { tx_id: 2, .. })
+    ));
+    if allow_zero_amount {
+        assert_eq!(errors.len(), 1);
+        assert_eq!(
+            successes,
+            vec![TransactionRecord::Deposit {
+                client: 1,
+                tx: 1,
+                amount: dec!(0.0),
+            }]
+        );
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].total, Decimal::ZERO);
+    } else {
+        assert_eq!(errors.len(), 2);
+        assert!(matches!(errors[0], Error::Validation { tx_id: 1, .. }));
+        assert!(successes.is_empty());
+        assert!(records.is_empty());
+    }
+}
+
+#[test]
+fn negative_deposit_reverses_a_prior_credit_if_enabled() {
+    let input = "\
+type, client, tx, amount
+deposit, 1, 1, 10.0
+deposit, 1, 2, -4.0";
+
+    let mut errors: Vec<Error> = Vec::new();
+    let mut successes = Vec::new();
+    let config = ProcessConfig::default().negative_as_reversal(true);
+    let records: Vec<AccountRecord> = process_with_config(
+        input.as_bytes(),
+        config,
+        |e| errors.push(e),
+        |tx| successes.push(tx),
+    )
+    .collect();
+
+    assert!(errors.is_empty(), "expected no errors, got: {errors:?}");
+    assert_eq!(
+        successes[1],
+        TransactionRecord::Withdrawal {
+            client: 1,
+            tx: 2,
+            amount: dec!(4.0),
+        }
+    );
+    assert_eq!(records[0].available, dec!(6.0));
+    assert_eq!(records[0].total, dec!(6.0));
+}
+
+#[test]
+fn negative_deposit_must_not_overdraw_the_account() {
+    let input = "\
+type, client, tx, amount
+deposit, 1, 1, 10.0
+deposit, 1, 2, -12.0";
+
+    let mut errors: Vec<Error> = Vec::new();
+    let config = ProcessConfig::default().negative_as_reversal(true);
+    let records: Vec<AccountRecord> =
+        process_with_config(input.as_bytes(), config, |e| errors.push(e), |_| {}).collect();
+
+    assert_eq!(errors.len(), 1);
+    assert_eq!(
+        errors[0].processing_kind(),
+        Some(ProcessingKind::InsufficientFunds)
+    );
+    assert_eq!(records[0].available, dec!(10.0));
+}
+
+#[test]
+fn negative_deposit_must_not_overdraw_the_account_within_the_overdraft_limit() {
+    let input = "\
+type, client, tx, amount
+deposit, 1, 1, 10.0
+deposit, 1, 2, -12.0
+withdrawal, 1, 3, 12.0";
+
+    let mut errors: Vec<Error> = Vec::new();
+    let config = ProcessConfig::default()
+        .negative_as_reversal(true)
+        .overdraft_limit(dec!(5.0));
+    let records: Vec<AccountRecord> =
+        process_with_config(input.as_bytes(), config, |e| errors.push(e), |_| {}).collect();
+
+    assert_eq!(errors.len(), 1);
+    assert!(matches!(errors[0], Error::Processing { tx_id: 2, .. }));
+    assert_eq!(
+        errors[0].processing_kind(),
+        Some(ProcessingKind::InsufficientFunds)
+    );
+    // the regular withdrawal may still use the overdraft
+    assert_eq!(records[0].available, dec!(-2.0));
+}
+
+#[test]
+fn negative_withdrawal_stays_invalid_with_reversals() {
+    let input = "\
+type, client, tx, amount
+deposit, 1, 1, 10.0
+withdrawal, 1, 2, -1.0";
+
+    let mut errors: Vec<Error> = Vec::new();
+    let config = ProcessConfig::default().negative_as_reversal(true);
+    let records: Vec<AccountRecord> =
+        process_with_config(input.as_bytes(), config, |e| errors.push(e), |_| {}).collect();
+
+    assert!(matches!(
+        errors.as_slice(),
+        [Error::Validation { tx_id: 2, .. }]
+    ));
+    assert_eq!(records[0].available, dec!(10.0));
+}
@@ -0,0 +1,71 @@
+//! Integration tests for the log of accepted deposits returned by `process_with_deposit_log`
+
+use rstest::rstest;
+use rust_decimal_macros::dec;
+use tx_engine_rs::{
+    DepositEntry, DepositLog, DepositStatus, ProcessConfig, process_with_deposit_log,
+};
+
+const INPUT: &str = "\
+type, client, tx, amount
+deposit, 1, 1, 10.0
+deposit, 1, 2, 2.5
+deposit, 2, 3, 4.0
+deposit, 3, 4, 1.0
+dispute, 1, 1,
+resolve, 1, 1,
+dispute, 2, 3,
+chargeback, 2, 3,
+dispute, 3, 4,
+withdrawal, 1, 5, 1.0
+deposit, 2, 6, 7.0";
+
+fn deposit_log(workers: Option<usize>) -> DepositLog {
+    let mut config = ProcessConfig::default();
+    if let Some(workers) = workers {
+        config = config.workers(workers);
+    }
+    let (records, deposits) = process_with_deposit_log(INPUT.as_bytes(), config, |_| {}, |_| {});
+    assert_eq!(records.count(), 3);
+    deposits
+}
+
+#[rstest]
+fn final_status_of_each_deposit_is_reconstructed(#[values(None, Some(2))] workers: Option<usize>) {
+    let deposits = deposit_log(workers);
+
+    let entry = |client, amount, status| {
+        Some(DepositEntry {
+            client,
+            amount,
+            status,
+        })
+    };
+    assert_eq!(
+        deposits.get(1),
+        entry(1, dec!(10.0), DepositStatus::Resolved)
+    );
+    assert_eq!(
+        deposits.get(2),
+        entry(1, dec!(2.5), DepositStatus::Accepted)
+    );
+    // forgotten by the account state, but retained by the log
+    assert_eq!(
+        deposits.get(3),
+        entry(2, dec!(4.0), DepositStatus::ChargedBack)
+    );
+    assert_eq!(
+        deposits.get(4),
+        entry(3, dec!(1.0), DepositStatus::Disputed)
+    );
+}
+
+#[test]
+fn only_accepted_deposits_are_logged() {
+    let deposits = deposit_log(None);
+
+    // the withdrawal is not a deposit, and the deposit to the locked account is rejected
+    assert_eq!(deposits.get(5), None);
+    assert_eq!(deposits.get(6), None);
+    assert_eq!(deposits.len(), 4);
+}
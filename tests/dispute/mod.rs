@@ -1,7 +1,13 @@
 //! "Manual" integration tests targeted mainly on the dispute mechanic
 
+use std::sync::Mutex;
+
+use rstest::rstest;
 use rust_decimal_macros::dec;
-use tx_engine_rs::{AccountRecord, Error, TransactionRecord, process};
+use tx_engine_rs::{
+    AccountRecord, DisputePolicy, Error, FundsDirection, FundsMoved, LockReason, ProcessConfig,
+    ProcessingKind, TransactionRecord, Warning, process_str, process_with_config,
+};
 
 #[test]
 fn deposit_then_dispute() {
@@ -16,16 +22,14 @@ dispute, 1, 1,";
         held: dec!(10.0),
         total: dec!(10.0),
         locked: false,
+        lock_reason: None,
+        num_transactions: None,
     };
 
     let mut errors: Vec<Error> = Vec::new();
     let mut successful_txs: Vec<TransactionRecord> = Vec::new();
-    let records: Vec<AccountRecord> = process(
-        input.as_bytes(),
-        |e| errors.push(e),
-        |tx| successful_txs.push(tx),
-    )
-    .collect();
+    let records: Vec<AccountRecord> =
+        process_str(input, |e| errors.push(e), |tx| successful_txs.push(tx)).collect();
 
     assert!(errors.is_empty(), "expected no errors, got: {errors:?}");
     assert_eq!(records.len(), 1);
@@ -59,16 +63,14 @@ dispute, 1, 3,";
         held: dec!(0),
         total: dec!(10.0),
         locked: false,
+        lock_reason: None,
+        num_transactions: None,
     };
 
     let mut errors: Vec<Error> = Vec::new();
     let mut successful_txs: Vec<TransactionRecord> = Vec::new();
-    let records: Vec<AccountRecord> = process(
-        input.as_bytes(),
-        |e| errors.push(e),
-        |tx| successful_txs.push(tx),
-    )
-    .collect();
+    let records: Vec<AccountRecord> =
+        process_str(input, |e| errors.push(e), |tx| successful_txs.push(tx)).collect();
 
     assert_eq!(records.len(), 1);
     assert_eq!(records[0], expected);
@@ -91,10 +93,15 @@ dispute, 1, 3,";
             Error::Processing {
                 client_id: 1,
                 tx_id: 3,
+                kind: ProcessingKind::UnknownTransaction,
                 ..
             }
         ),
-        "expected a processing error for the dispute on nonexistent tx 3"
+        "expected an unknown transaction error for the dispute on nonexistent tx 3"
+    );
+    assert_eq!(
+        errors[0].to_string(),
+        "processing conflict — client: 1, tx: 3: dispute referencing unknown transaction"
     );
 }
 
@@ -112,16 +119,14 @@ dispute, 1, 2,";
         held: dec!(0),
         total: dec!(5.0),
         locked: false,
+        lock_reason: None,
+        num_transactions: None,
     };
 
     let mut errors: Vec<Error> = Vec::new();
     let mut successful_txs: Vec<TransactionRecord> = Vec::new();
-    let records: Vec<AccountRecord> = process(
-        input.as_bytes(),
-        |e| errors.push(e),
-        |tx| successful_txs.push(tx),
-    )
-    .collect();
+    let records: Vec<AccountRecord> =
+        process_str(input, |e| errors.push(e), |tx| successful_txs.push(tx)).collect();
 
     assert_eq!(records.len(), 1);
     assert_eq!(records[0], expected);
@@ -142,6 +147,83 @@ dispute, 1, 2,";
     );
 }
 
+/// Runs the input with disputes on withdrawals enabled, expecting every transaction to succeed.
+fn process_with_withdrawal_disputes(input: &str) -> Vec<AccountRecord> {
+    let config = ProcessConfig::default().dispute_withdrawals(true);
+    process_with_config(
+        input.as_bytes(),
+        config,
+        |e| panic!("unexpected error: {e}"),
+        |_| {},
+    )
+    .collect()
+}
+
+#[test]
+fn dispute_a_withdrawal_when_enabled() {
+    let input = "\
+type, client, tx, amount
+deposit, 1, 1, 10.0
+withdrawal, 1, 2, 4.0
+dispute, 1, 2,";
+
+    let expected = AccountRecord {
+        client: 1,
+        available: dec!(6.0),
+        held: dec!(4.0),
+        total: dec!(10.0),
+        locked: false,
+        lock_reason: None,
+        num_transactions: None,
+    };
+
+    assert_eq!(process_with_withdrawal_disputes(input), vec![expected]);
+}
+
+#[test]
+fn resolve_a_disputed_withdrawal_keeps_the_withdrawal() {
+    let input = "\
+type, client, tx, amount
+deposit, 1, 1, 10.0
+withdrawal, 1, 2, 4.0
+dispute, 1, 2,
+resolve, 1, 2,";
+
+    let expected = AccountRecord {
+        client: 1,
+        available: dec!(6.0),
+        held: dec!(0),
+        total: dec!(6.0),
+        locked: false,
+        lock_reason: None,
+        num_transactions: None,
+    };
+
+    assert_eq!(process_with_withdrawal_disputes(input), vec![expected]);
+}
+
+#[test]
+fn chargeback_a_disputed_withdrawal_reverses_the_debit() {
+    let input = "\
+type, client, tx, amount
+deposit, 1, 1, 10.0
+withdrawal, 1, 2, 4.0
+dispute, 1, 2,
+chargeback, 1, 2,";
+
+    let expected = AccountRecord {
+        client: 1,
+        available: dec!(10.0),
+        held: dec!(0),
+        total: dec!(10.0),
+        locked: true,
+        lock_reason: Some(LockReason::Chargeback { tx_id: 2 }),
+        num_transactions: None,
+    };
+
+    assert_eq!(process_with_withdrawal_disputes(input), vec![expected]);
+}
+
 #[test]
 fn dispute_insufficient_funds() {
     let input = "\
@@ -157,16 +239,14 @@ dispute, 1, 1,";
         held: dec!(0),
         total: dec!(2.0),
         locked: false,
+        lock_reason: None,
+        num_transactions: None,
     };
 
     let mut errors: Vec<Error> = Vec::new();
     let mut successful_txs: Vec<TransactionRecord> = Vec::new();
-    let records: Vec<AccountRecord> = process(
-        input.as_bytes(),
-        |e| errors.push(e),
-        |tx| successful_txs.push(tx),
-    )
-    .collect();
+    let records: Vec<AccountRecord> =
+        process_str(input, |e| errors.push(e), |tx| successful_txs.push(tx)).collect();
 
     assert_eq!(records.len(), 1);
     assert_eq!(records[0], expected);
@@ -180,10 +260,17 @@ dispute, 1, 1,";
             Error::Processing {
                 client_id: 1,
                 tx_id: 1,
+                kind: ProcessingKind::DisputeFundsWithdrawn,
                 ..
             }
         ),
-        "expected a processing error for dispute with insufficient available funds"
+        "expected a processing error for dispute with insufficient available funds, got: {:?}",
+        errors[0]
+    );
+    // distinguishable from an overdraft by the kind alone
+    assert_ne!(
+        errors[0].processing_kind(),
+        Some(ProcessingKind::InsufficientFunds)
     );
 }
 
@@ -201,16 +288,14 @@ dispute, 1, 1,";
         held: dec!(10.0),
         total: dec!(10.0),
         locked: false,
+        lock_reason: None,
+        num_transactions: None,
     };
 
     let mut errors: Vec<Error> = Vec::new();
     let mut successful_txs: Vec<TransactionRecord> = Vec::new();
-    let records: Vec<AccountRecord> = process(
-        input.as_bytes(),
-        |e| errors.push(e),
-        |tx| successful_txs.push(tx),
-    )
-    .collect();
+    let records: Vec<AccountRecord> =
+        process_str(input, |e| errors.push(e), |tx| successful_txs.push(tx)).collect();
 
     assert_eq!(records.len(), 1);
     assert_eq!(records[0], expected);
@@ -224,13 +309,73 @@ dispute, 1, 1,";
             Error::Processing {
                 client_id: 1,
                 tx_id: 1,
+                kind: ProcessingKind::AlreadyDisputed,
                 ..
             }
         ),
-        "expected a processing error for the duplicate dispute"
+        "expected an already disputed error for the duplicate dispute"
+    );
+    assert_eq!(
+        errors[0].to_string(),
+        "processing conflict — client: 1, tx: 1: dispute referencing a transaction already under dispute"
     );
 }
 
+#[rstest]
+fn retried_disputes_are_no_ops_if_idempotent(
+    #[values(false, true)] idempotent: bool,
+    #[values(None, Some(2))] workers: Option<usize>,
+) {
+    let input = "\
+type, client, tx, amount
+deposit, 1, 1, 10.0
+dispute, 1, 1,
+dispute, 1, 1,
+deposit, 2, 2, 4.0
+dispute, 2, 2,
+chargeback, 2, 2,
+chargeback, 2, 2,
+resolve, 1, 1,
+resolve, 1, 1,";
+
+    let locked = Mutex::new(0);
+    let errors = Mutex::new(Vec::new());
+    let mut config = ProcessConfig::default()
+        .idempotent_disputes(idempotent)
+        .sort_by_client(true)
+        .on_locked_account(|_| *locked.lock().unwrap() += 1);
+    if let Some(workers) = workers {
+        config = config.workers(workers);
+    }
+    let records: Vec<AccountRecord> = process_with_config(
+        input.as_bytes(),
+        config,
+        |e| errors.lock().unwrap().push(e.processing_kind()),
+        |_| {},
+    )
+    .collect();
+
+    // the retries leave the balances as they are in both modes
+    assert_eq!(
+        (records[0].available, records[0].held),
+        (dec!(10.0), dec!(0))
+    );
+    assert_eq!((records[1].total, records[1].locked), (dec!(0), true));
+    assert_eq!(locked.into_inner().unwrap(), 1);
+    let mut errors = errors.into_inner().unwrap();
+    errors.sort_by_key(|kind| format!("{kind:?}"));
+    let expected = if idempotent {
+        vec![]
+    } else {
+        vec![
+            Some(ProcessingKind::AlreadyChargedBack),
+            Some(ProcessingKind::AlreadyDisputed),
+            Some(ProcessingKind::AlreadyResolved),
+        ]
+    };
+    assert_eq!(errors, expected);
+}
+
 #[test]
 fn two_deposits_dispute_first() {
     let input = "\
@@ -245,16 +390,14 @@ dispute, 1, 1,";
         held: dec!(10.0),
         total: dec!(15.0),
         locked: false,
+        lock_reason: None,
+        num_transactions: None,
     };
 
     let mut errors: Vec<Error> = Vec::new();
     let mut successful_txs: Vec<TransactionRecord> = Vec::new();
-    let records: Vec<AccountRecord> = process(
-        input.as_bytes(),
-        |e| errors.push(e),
-        |tx| successful_txs.push(tx),
-    )
-    .collect();
+    let records: Vec<AccountRecord> =
+        process_str(input, |e| errors.push(e), |tx| successful_txs.push(tx)).collect();
 
     assert!(errors.is_empty(), "expected no errors, got: {errors:?}");
     assert_eq!(records.len(), 1);
@@ -263,3 +406,497 @@ dispute, 1, 1,";
     // All three succeed: two deposits + one dispute
     assert_eq!(successful_txs.len(), 3);
 }
+
+const OPEN_DISPUTE_INPUT: &str = "\
+type, client, tx, amount
+deposit, 1, 1, 10.0
+deposit, 1, 2, 4.0
+dispute, 1, 1,";
+
+#[test]
+fn settlement_moves_held_funds_of_open_dispute_to_available() {
+    let expected = AccountRecord {
+        client: 1,
+        available: dec!(14.0),
+        held: dec!(0),
+        total: dec!(14.0),
+        locked: false,
+        lock_reason: None,
+        num_transactions: None,
+    };
+
+    let config = ProcessConfig::default().settle_open_disputes(true);
+    let records: Vec<AccountRecord> = process_with_config(
+        OPEN_DISPUTE_INPUT.as_bytes(),
+        config,
+        |e| panic!("unexpected error: {e}"),
+        |_| {},
+    )
+    .collect();
+
+    assert_eq!(records, vec![expected]);
+}
+
+#[rstest]
+fn settlement_keeps_disputed_withdrawals_standing(#[values(None, Some(2))] workers: Option<usize>) {
+    let input = "\
+type, client, tx, amount
+deposit, 1, 1, 10.0
+withdrawal, 1, 2, 4.0
+dispute, 1, 2,";
+    let mut config = ProcessConfig::default()
+        .dispute_withdrawals(true)
+        .settle_open_disputes(true);
+    if let Some(workers) = workers {
+        config = config.workers(workers);
+    }
+
+    let records: Vec<AccountRecord> = process_with_config(
+        input.as_bytes(),
+        config,
+        |e| panic!("unexpected error: {e}"),
+        |_| {},
+    )
+    .collect();
+
+    // settled like a resolve of the withdrawal, which does not return the withdrawn funds
+    assert_eq!(records[0].available, dec!(6.0));
+    assert_eq!(records[0].held, dec!(0));
+    assert_eq!(records[0].total, dec!(6.0));
+}
+
+#[test]
+fn settled_records_are_reported_alongside_raw_records() {
+    let raw_expected = AccountRecord {
+        client: 1,
+        available: dec!(4.0),
+        held: dec!(10.0),
+        total: dec!(14.0),
+        locked: false,
+        lock_reason: None,
+        num_transactions: None,
+    };
+    let settled_expected = AccountRecord {
+        client: 1,
+        available: dec!(14.0),
+        held: dec!(0),
+        total: dec!(14.0),
+        locked: false,
+        lock_reason: None,
+        num_transactions: None,
+    };
+
+    let settled = Mutex::new(Vec::new());
+    let config =
+        ProcessConfig::default().on_settled_account(|record| settled.lock().unwrap().push(record));
+    let records: Vec<AccountRecord> = process_with_config(
+        OPEN_DISPUTE_INPUT.as_bytes(),
+        config,
+        |e| panic!("unexpected error: {e}"),
+        |_| {},
+    )
+    .collect();
+
+    assert_eq!(records, vec![raw_expected]);
+    assert_eq!(settled.into_inner().unwrap(), vec![settled_expected]);
+}
+
+#[test]
+fn dispute_past_the_retention_window_is_rejected() {
+    let input = "\
+type, client, tx, amount
+deposit, 1, 1, 1.0
+deposit, 1, 2, 2.0
+deposit, 1, 3, 4.0
+dispute, 1, 1,
+dispute, 1, 2,";
+
+    let expected = AccountRecord {
+        client: 1,
+        available: dec!(5.0),
+        held: dec!(2.0),
+        total: dec!(7.0),
+        locked: false,
+        lock_reason: None,
+        num_transactions: None,
+    };
+
+    let mut errors: Vec<Error> = Vec::new();
+    let config = ProcessConfig::default().max_disputable_deposits(2);
+    let records: Vec<AccountRecord> =
+        process_with_config(input.as_bytes(), config, |e| errors.push(e), |_| {}).collect();
+
+    // the oldest deposit was dropped from the retained ones, but stays credited
+    assert_eq!(records, vec![expected]);
+    assert_eq!(errors.len(), 1);
+    assert!(
+        matches!(
+            &errors[0],
+            Error::Processing {
+                client_id: 1,
+                tx_id: 1,
+                ..
+            }
+        ),
+        "expected a processing error for the dispute of the dropped deposit"
+    );
+}
+
+#[test]
+fn resolved_deposit_counts_as_most_recent_for_the_retention_window() {
+    let input = "\
+type, client, tx, amount
+deposit, 1, 1, 1.0
+deposit, 1, 2, 2.0
+dispute, 1, 1,
+resolve, 1, 1,
+deposit, 1, 3, 4.0
+dispute, 1, 1,
+dispute, 1, 2,";
+
+    let mut errors: Vec<Error> = Vec::new();
+    let config = ProcessConfig::default().max_disputable_deposits(2);
+    let records: Vec<AccountRecord> =
+        process_with_config(input.as_bytes(), config, |e| errors.push(e), |_| {}).collect();
+
+    assert_eq!(records[0].held, dec!(1.0));
+    assert_eq!(records[0].total, dec!(7.0));
+    assert_eq!(errors.len(), 1);
+    assert!(matches!(&errors[0], Error::Processing { tx_id: 2, .. }));
+}
+
+#[test]
+fn settled_transactions_count_against_the_retention_window() {
+    let input = "\
+type, client, tx, amount
+deposit, 1, 1, 10.0
+withdrawal, 1, 2, 1.0
+dispute, 1, 2,
+resolve, 1, 2,
+resolve, 1, 2,
+deposit, 1, 3, 1.0
+deposit, 1, 4, 1.0
+resolve, 1, 2,";
+
+    let mut errors: Vec<Error> = Vec::new();
+    let config = ProcessConfig::default()
+        .dispute_withdrawals(true)
+        .max_disputable_deposits(2);
+    let records: Vec<AccountRecord> =
+        process_with_config(input.as_bytes(), config, |e| errors.push(e), |_| {}).collect();
+
+    // the resolved withdrawal is forgotten once two newer deposits are retained
+    let kinds: Vec<_> = errors.iter().map(Error::processing_kind).collect();
+    assert_eq!(
+        kinds,
+        vec![
+            Some(ProcessingKind::AlreadyResolved),
+            Some(ProcessingKind::UndisputedResolve),
+        ]
+    );
+    assert_eq!(records[0].available, dec!(11.0));
+}
+
+#[test]
+fn disputes_keep_the_balance_invariants() {
+    // a dispute subtracting more than the disputed amount from `available` would push it below zero
+    let input = "\
+type, client, tx, amount
+deposit, 1, 1, 10.0
+withdrawal, 1, 2, 6.0
+dispute, 1, 1,
+deposit, 1, 3, 4.0
+dispute, 1, 3,
+withdrawal, 1, 4, 2.0
+dispute, 1, 4,
+resolve, 1, 3,
+dispute, 1, 3,
+chargeback, 1, 4,";
+
+    let mut errors: Vec<Error> = Vec::new();
+    let config = ProcessConfig::default()
+        .check_invariants(true)
+        .dispute_withdrawals(true)
+        .overdraft_limit(dec!(1.0));
+    let records: Vec<AccountRecord> =
+        process_with_config(input.as_bytes(), config, |e| errors.push(e), |_| {}).collect();
+
+    // only the dispute of the partially withdrawn deposit is rejected
+    assert_eq!(errors.len(), 1, "unexpected errors: {errors:?}");
+    assert!(matches!(&errors[0], Error::Processing { tx_id: 1, .. }));
+    assert_eq!(records[0].available, dec!(4.0));
+    assert_eq!(records[0].held, dec!(4.0));
+    assert!(records[0].locked);
+}
+
+const PARTLY_WITHDRAWN_DEPOSIT: &str = "\
+type, client, tx, amount
+deposit, 1, 1, 10.0
+withdrawal, 1, 2, 4.0
+dispute, 1, 1,";
+
+#[test]
+fn dispute_of_partly_withdrawn_deposit_is_rejected_under_strict_policy() {
+    let mut errors: Vec<Error> = Vec::new();
+    let config = ProcessConfig::default().dispute_policy(DisputePolicy::StrictFullHold);
+    let records: Vec<AccountRecord> = process_with_config(
+        PARTLY_WITHDRAWN_DEPOSIT.as_bytes(),
+        config,
+        |e| errors.push(e),
+        |_| {},
+    )
+    .collect();
+
+    assert_eq!(errors.len(), 1);
+    assert_eq!(
+        errors[0].processing_kind(),
+        Some(ProcessingKind::DisputeFundsWithdrawn)
+    );
+    assert_eq!(records[0].available, dec!(6.0));
+    assert_eq!(records[0].held, dec!(0));
+}
+
+#[test]
+fn dispute_of_partly_withdrawn_deposit_holds_the_rest_under_partial_policy() {
+    let mut errors: Vec<Error> = Vec::new();
+    let config = ProcessConfig::default().dispute_policy(DisputePolicy::PartialHold);
+    let records: Vec<AccountRecord> = process_with_config(
+        PARTLY_WITHDRAWN_DEPOSIT.as_bytes(),
+        config,
+        |e| errors.push(e),
+        |_| {},
+    )
+    .collect();
+
+    assert!(errors.is_empty(), "expected no errors, got: {errors:?}");
+    assert_eq!(records[0].available, dec!(0));
+    assert_eq!(records[0].held, dec!(6.0));
+    assert_eq!(records[0].total, dec!(6.0));
+}
+
+#[rstest]
+#[case::resolve("resolve", dec!(6.0), false)]
+#[case::chargeback("chargeback", dec!(0), true)]
+fn partial_hold_is_settled_by_the_held_amount(
+    #[case] settlement: &str,
+    #[case] available: rust_decimal::Decimal,
+    #[case] locked: bool,
+) {
+    let input = format!("{PARTLY_WITHDRAWN_DEPOSIT}\n{settlement}, 1, 1,\ndeposit, 2, 3, 1.0");
+
+    let config = ProcessConfig::default().dispute_policy(DisputePolicy::PartialHold);
+    let records: Vec<AccountRecord> = process_with_config(
+        input.as_bytes(),
+        config,
+        |e| panic!("unexpected error: {e}"),
+        |_| {},
+    )
+    .collect();
+
+    let client_1 = records.iter().find(|r| r.client == 1).unwrap();
+    assert_eq!(client_1.available, available);
+    assert_eq!(client_1.held, dec!(0));
+    assert_eq!(client_1.locked, locked);
+}
+
+#[test]
+fn partly_held_deposit_is_disputed_anew_with_its_full_amount_after_a_resolve() {
+    // the resolve releases the 6.0 held; the new dispute holds what is available by then
+    let input =
+        format!("{PARTLY_WITHDRAWN_DEPOSIT}\nresolve, 1, 1,\ndeposit, 1, 3, 2.0\ndispute, 1, 1,");
+
+    let config = ProcessConfig::default().dispute_policy(DisputePolicy::PartialHold);
+    let records: Vec<AccountRecord> = process_with_config(
+        input.as_bytes(),
+        config,
+        |e| panic!("unexpected error: {e}"),
+        |_| {},
+    )
+    .collect();
+
+    assert_eq!(records[0].available, dec!(0));
+    assert_eq!(records[0].held, dec!(8.0));
+}
+
+#[rstest]
+#[case::dispute("dispute, 2, 1,")]
+#[case::resolve("resolve, 2, 1,")]
+#[case::chargeback("chargeback, 2, 1,")]
+fn referencing_the_transaction_of_another_client_is_rejected(#[case] row: &str) {
+    let input = format!(
+        "\
+type, client, tx, amount
+deposit, 1, 1, 10.0
+deposit, 2, 2, 5.0
+dispute, 1, 1,
+{row}"
+    );
+
+    let config = ProcessConfig::default().reject_foreign_transactions(true);
+    let mut errors: Vec<Error> = Vec::new();
+    let mut records: Vec<AccountRecord> =
+        process_with_config(input.as_bytes(), config, |e| errors.push(e), |_| {}).collect();
+    records.sort_by_key(|r| r.client);
+
+    assert_eq!(errors.len(), 1, "expected one error, got: {errors:?}");
+    assert_eq!(
+        errors[0].processing_kind(),
+        Some(ProcessingKind::ForeignTransaction)
+    );
+    assert!(
+        errors[0]
+            .to_string()
+            .contains("transaction does not belong to client")
+    );
+    // neither the owner's dispute nor the other client's account are affected
+    assert_eq!(records[0].held, dec!(10.0));
+    assert!(!records[0].locked);
+    assert_eq!(records[1].available, dec!(5.0));
+}
+
+#[test]
+fn own_transaction_takes_precedence_over_a_colliding_foreign_one() {
+    let input = "\
+type, client, tx, amount
+deposit, 1, 1, 10.0
+deposit, 2, 1, 5.0
+dispute, 2, 1,";
+
+    let config = ProcessConfig::default().reject_foreign_transactions(true);
+    let mut errors: Vec<Error> = Vec::new();
+    let mut records: Vec<AccountRecord> =
+        process_with_config(input.as_bytes(), config, |e| errors.push(e), |_| {}).collect();
+    records.sort_by_key(|r| r.client);
+
+    assert!(errors.is_empty(), "expected no errors, got: {errors:?}");
+    assert_eq!(records[0].held, dec!(0));
+    assert_eq!(records[1].held, dec!(5.0));
+}
+
+#[test]
+fn foreign_references_are_unknown_to_both_engines_by_default() {
+    let input = "\
+type, client, tx, amount
+deposit, 1, 1, 10.0
+deposit, 2, 2, 5.0
+deposit, 3, 3, 1.0
+dispute, 2, 1,
+resolve, 3, 2,
+chargeback, 1, 3,";
+    let run = |config: ProcessConfig<'_>| {
+        let mut errors = Vec::new();
+        let records: Vec<AccountRecord> = process_with_config(
+            input.as_bytes(),
+            config.sort_by_client(true),
+            |e| errors.push(e.to_string()),
+            |_| {},
+        )
+        .collect();
+        errors.sort();
+        (records, errors)
+    };
+
+    let sequential = run(ProcessConfig::default());
+    let parallel = run(ProcessConfig::default().workers(3).deterministic(true));
+
+    assert_eq!(sequential, parallel);
+    assert_eq!(sequential.1.len(), 3);
+    assert!(
+        sequential
+            .1
+            .iter()
+            .all(|error| !error.contains("does not belong"))
+    );
+}
+
+#[rstest]
+fn disputes_and_resolves_report_the_moved_funds(#[values(None, Some(2))] workers: Option<usize>) {
+    let input = "\
+type, client, tx, amount
+deposit, 1, 1, 10.0
+deposit, 1, 2, 2.5
+withdrawal, 1, 3, 1.0
+dispute, 1, 2,
+dispute, 1, 3,
+resolve, 1, 2,
+resolve, 1, 3,";
+
+    let moved = Mutex::new(Vec::new());
+    let mut config = ProcessConfig::default()
+        .dispute_withdrawals(true)
+        .on_funds_moved(|event| moved.lock().unwrap().push(event));
+    if let Some(workers) = workers {
+        config = config.workers(workers);
+    }
+    let records: Vec<AccountRecord> =
+        process_with_config(input.as_bytes(), config, |_| {}, |_| {}).collect();
+
+    // the withdrawal's dispute and resolve hold and release funds that are not available
+    assert_eq!(
+        moved.into_inner().unwrap(),
+        vec![
+            FundsMoved {
+                client: 1,
+                tx: 2,
+                direction: FundsDirection::AvailableToHeld,
+                amount: dec!(2.5),
+            },
+            FundsMoved {
+                client: 1,
+                tx: 2,
+                direction: FundsDirection::HeldToAvailable,
+                amount: dec!(2.5),
+            },
+        ]
+    );
+    assert_eq!(records[0].available, dec!(11.5));
+}
+
+#[rstest]
+fn disputing_every_deposit_warns_of_a_rapid_dispute_rate(
+    #[values(None, Some(2))] workers: Option<usize>,
+) {
+    let input = "\
+type, client, tx, amount
+deposit, 1, 1, 1.0
+deposit, 1, 2, 1.0
+deposit, 1, 3, 1.0
+deposit, 2, 4, 1.0
+deposit, 2, 5, 1.0
+deposit, 2, 6, 1.0
+deposit, 2, 7, 1.0
+dispute, 1, 1,
+dispute, 1, 2,
+dispute, 1, 3,
+dispute, 2, 4,
+dispute, 2, 5,
+dispute, 2, 6,
+deposit, 1, 8, 1.0
+dispute, 1, 8,";
+
+    let warnings = Mutex::new(Vec::new());
+    let mut config =
+        ProcessConfig::default().on_warning(|warning| warnings.lock().unwrap().push(warning));
+    if let Some(workers) = workers {
+        config = config.workers(workers);
+    }
+    let records: Vec<AccountRecord> =
+        process_with_config(input.as_bytes(), config, |_| {}, |_| {}).collect();
+
+    // client 2 keeps an undisputed deposit, and the warnings do not affect the processing
+    assert_eq!(
+        warnings.into_inner().unwrap(),
+        vec![
+            Warning::RapidDisputeRate {
+                client: 1,
+                count: 3
+            },
+            Warning::RapidDisputeRate {
+                client: 1,
+                count: 4
+            },
+        ]
+    );
+    assert_eq!(records.len(), 2);
+    assert!(records.iter().all(|record| record.held > dec!(0)));
+}
@@ -0,0 +1,269 @@
+//! Integration tests for the stateful engine handle, queried while the transactions are applied
+
+use rust_decimal_macros::dec;
+use tx_engine_rs::{
+    AccountRecord, Error, LockReason, ProcessConfig, TransactionEngine, TransactionRecord, process,
+};
+
+#[test]
+fn balances_can_be_queried_between_transactions() {
+    let mut engine = TransactionEngine::new(ProcessConfig::default());
+    assert_eq!(engine.available(1), None);
+
+    engine
+        .feed(TransactionRecord::Deposit {
+            client: 1,
+            tx: 1,
+            amount: dec!(10.0),
+        })
+        .unwrap();
+    assert_eq!(engine.available(1), Some(dec!(10.0)));
+    assert_eq!(engine.held(1), Some(dec!(0)));
+
+    engine
+        .feed(TransactionRecord::Dispute { client: 1, tx: 1 })
+        .unwrap();
+    assert_eq!(engine.available(1), Some(dec!(0)));
+    assert_eq!(engine.held(1), Some(dec!(10.0)));
+    assert_eq!(engine.is_locked(1), Some(false));
+
+    engine
+        .feed(TransactionRecord::Chargeback {
+            client: 1,
+            tx: 1,
+            amount: None,
+        })
+        .unwrap();
+    assert_eq!(
+        engine.snapshot(1),
+        Some(AccountRecord {
+            client: 1,
+            available: dec!(0),
+            held: dec!(0),
+            total: dec!(0),
+            locked: true,
+            lock_reason: Some(LockReason::Chargeback { tx_id: 1 }),
+            num_transactions: None,
+        })
+    );
+    assert_eq!(engine.snapshot(2), None);
+}
+
+#[test]
+fn rejected_transactions_leave_the_state_untouched() {
+    let mut engine = TransactionEngine::new(ProcessConfig::default());
+    engine
+        .feed(TransactionRecord::Deposit {
+            client: 1,
+            tx: 1,
+            amount: dec!(1.0),
+        })
+        .unwrap();
+
+    let invalid = engine.feed(TransactionRecord::Deposit {
+        client: 1,
+        tx: 2,
+        amount: dec!(-1.0),
+    });
+    let insufficient = engine.feed(TransactionRecord::Withdrawal {
+        client: 1,
+        tx: 3,
+        amount: dec!(2.0),
+    });
+
+    assert!(matches!(invalid, Err(Error::Validation { tx_id: 2, .. })));
+    assert!(matches!(
+        insufficient,
+        Err(Error::Processing { tx_id: 3, .. })
+    ));
+    assert_eq!(engine.available(1), Some(dec!(1.0)));
+}
+
+#[test]
+fn applied_transactions_are_returned_for_an_interactive_session() {
+    let mut engine = TransactionEngine::new(ProcessConfig::default());
+    let session = [
+        TransactionRecord::Deposit {
+            client: 3,
+            tx: 1,
+            amount: dec!(5.0),
+        },
+        TransactionRecord::Dispute { client: 3, tx: 1 },
+        TransactionRecord::Resolve {
+            client: 3,
+            tx: 1,
+            amount: None,
+        },
+        TransactionRecord::Withdrawal {
+            client: 3,
+            tx: 2,
+            amount: dec!(1.5),
+        },
+    ];
+
+    let mut balances = Vec::new();
+    for tx in session {
+        assert_eq!(engine.apply(tx).unwrap(), tx);
+        let account = engine.snapshot(3).unwrap();
+        balances.push((account.available, account.held));
+    }
+
+    assert_eq!(
+        balances,
+        vec![
+            (dec!(5.0), dec!(0)),
+            (dec!(0.0), dec!(5.0)),
+            (dec!(5.0), dec!(0.0)),
+            (dec!(3.5), dec!(0.0)),
+        ]
+    );
+    assert!(
+        engine
+            .apply(TransactionRecord::Dispute { client: 3, tx: 9 })
+            .is_err()
+    );
+}
+
+#[test]
+fn feeding_batches_matches_processing_the_concatenated_input() {
+    let first_batch = "\
+type, client, tx, amount
+deposit, 1, 1, 10.0
+deposit, 2, 2, 5.0
+withdrawal, 1, 3, 2.0";
+    let second_batch = "\
+type, client, tx, amount
+dispute, 2, 2,
+withdrawal, 2, 4, 1.0
+deposit, 3, 5, 1.5";
+
+    let mut engine = TransactionEngine::new(ProcessConfig::default());
+    let mut errors = Vec::new();
+    engine.feed_reader(first_batch.as_bytes(), |e| errors.push(e), |_| {});
+    assert_eq!(engine.available(1), Some(dec!(8.0)));
+    assert_eq!(engine.available(3), None);
+    engine.feed_reader(second_batch.as_bytes(), |e| errors.push(e), |_| {});
+    let mut records: Vec<AccountRecord> = engine.into_records().collect();
+    records.sort_by_key(|r| r.client);
+
+    let concatenated = format!(
+        "{first_batch}\n{}",
+        second_batch.split_once('\n').unwrap().1
+    );
+    let mut expected_errors = Vec::new();
+    let mut expected: Vec<AccountRecord> =
+        process(concatenated.as_bytes(), |e| expected_errors.push(e), |_| {}).collect();
+    expected.sort_by_key(|r| r.client);
+
+    assert_eq!(records, expected);
+    assert_eq!(errors.len(), 1, "the withdrawal of disputed funds fails");
+    assert_eq!(expected_errors.len(), 1);
+}
+
+const FIRST_HALF: &str = "\
+type, client, tx, amount
+deposit, 1, 1, 10.0
+deposit, 2, 2, 5.0";
+
+const SECOND_HALF: &str = "\
+type, client, tx, amount
+dispute, 1, 1,
+chargeback, 1, 1,
+withdrawal, 2, 3, 1.5";
+
+/// The records of processing both halves with a single engine
+fn uninterrupted_records() -> Vec<AccountRecord> {
+    let mut engine = TransactionEngine::new(ProcessConfig::default());
+    engine.feed_reader(FIRST_HALF.as_bytes(), |e| panic!("{e}"), |_| {});
+    engine.feed_reader(SECOND_HALF.as_bytes(), |e| panic!("{e}"), |_| {});
+    sorted(engine.into_records().collect())
+}
+
+fn sorted(mut records: Vec<AccountRecord>) -> Vec<AccountRecord> {
+    records.sort_by_key(|r| r.client);
+    records
+}
+
+#[test]
+fn resuming_from_a_checkpoint_continues_with_the_disputable_deposits() {
+    let mut engine = TransactionEngine::new(ProcessConfig::default());
+    engine.feed_reader(FIRST_HALF.as_bytes(), |e| panic!("{e}"), |_| {});
+    let snapshot = engine.checkpoint();
+    drop(engine);
+
+    assert_eq!(snapshot.processed(), 2);
+    let mut resumed = TransactionEngine::resume(snapshot, ProcessConfig::default());
+    resumed.feed_reader(SECOND_HALF.as_bytes(), |e| panic!("{e}"), |_| {});
+
+    assert_eq!(
+        sorted(resumed.into_records().collect()),
+        uninterrupted_records()
+    );
+}
+
+#[cfg(feature = "snapshot")]
+#[test]
+fn snapshot_survives_a_serialization_round_trip() {
+    use tx_engine_rs::EngineSnapshot;
+
+    let mut engine = TransactionEngine::new(ProcessConfig::default());
+    engine.feed_reader(FIRST_HALF.as_bytes(), |e| panic!("{e}"), |_| {});
+    let persisted = serde_json::to_string(&engine.checkpoint()).unwrap();
+
+    let snapshot: EngineSnapshot = serde_json::from_str(&persisted).unwrap();
+    let mut resumed = TransactionEngine::resume(snapshot, ProcessConfig::default());
+    resumed.feed_reader(SECOND_HALF.as_bytes(), |e| panic!("{e}"), |_| {});
+
+    assert_eq!(
+        sorted(resumed.into_records().collect()),
+        uninterrupted_records()
+    );
+}
+
+/// An engine resumed from a snapshot whose account holds negative funds, which the engine never produces on its own
+#[cfg(feature = "snapshot")]
+fn engine_with_corrupted_account(config: ProcessConfig<'_>) -> TransactionEngine<'_> {
+    use tx_engine_rs::EngineSnapshot;
+
+    let mut engine = TransactionEngine::new(ProcessConfig::default());
+    engine.feed_reader(
+        "type, client, tx, amount\ndeposit, 1, 1, 10.0".as_bytes(),
+        |e| panic!("{e}"),
+        |_| {},
+    );
+    let persisted = serde_json::to_string(&engine.checkpoint())
+        .unwrap()
+        .replace(r#""held":"0""#, r#""held":"-1""#);
+    let snapshot: EngineSnapshot = serde_json::from_str(&persisted).unwrap();
+    TransactionEngine::resume(snapshot, config)
+}
+
+#[cfg(all(feature = "snapshot", debug_assertions))]
+#[test]
+#[should_panic(expected = "internal logic error: invariant violated")]
+fn invariant_violation_panics_in_debug_builds() {
+    let mut engine = engine_with_corrupted_account(ProcessConfig::default().check_invariants(true));
+    let _ = engine.feed(TransactionRecord::Deposit {
+        client: 1,
+        tx: 2,
+        amount: dec!(1.0),
+    });
+}
+
+#[cfg(all(feature = "snapshot", not(debug_assertions)))]
+#[test]
+fn invariant_violation_rolls_the_transaction_back_in_release_builds() {
+    let mut engine = engine_with_corrupted_account(ProcessConfig::default().check_invariants(true));
+    let result = engine.feed(TransactionRecord::Deposit {
+        client: 1,
+        tx: 2,
+        amount: dec!(1.0),
+    });
+
+    assert_eq!(
+        result.unwrap_err().processing_kind(),
+        Some(tx_engine_rs::ProcessingKind::InvariantViolation)
+    );
+    assert_eq!(engine.available(1), Some(dec!(10.0)));
+    assert_eq!(engine.held(1), Some(dec!(-1)));
+}
@@ -0,0 +1,495 @@
+//! Integration tests for the structured causes of the errors reported to `on_error`
+
+use std::{
+    collections::HashMap,
+    ops::ControlFlow,
+    sync::{
+        Mutex,
+        atomic::{AtomicBool, Ordering},
+    },
+};
+
+use rstest::rstest;
+use rust_decimal::Decimal;
+use tx_engine_rs::{
+    AccountRecord, Error, ProcessConfig, ProcessingKind, TransactionRecord, process,
+    process_parallel, process_until, process_with_stats,
+};
+
+#[rstest]
+#[case::insufficient_funds(
+    "deposit, 1, 1, 1.0\nwithdrawal, 1, 2, 2.0",
+    ProcessingKind::InsufficientFunds
+)]
+#[case::unknown_transaction(
+    "deposit, 1, 1, 1.0\ndispute, 1, 2,",
+    ProcessingKind::UnknownTransaction
+)]
+#[case::already_disputed(
+    "deposit, 1, 1, 1.0\ndispute, 1, 1,\ndispute, 1, 1,",
+    ProcessingKind::AlreadyDisputed
+)]
+#[case::undisputed_resolve(
+    "deposit, 1, 1, 1.0\nresolve, 1, 1,",
+    ProcessingKind::UndisputedResolve
+)]
+#[case::undisputed_chargeback(
+    "deposit, 1, 1, 1.0\nchargeback, 1, 1,",
+    ProcessingKind::UndisputedChargeback
+)]
+#[case::already_resolved(
+    "deposit, 1, 1, 1.0\ndispute, 1, 1,\nresolve, 1, 1,\nchargeback, 1, 1,",
+    ProcessingKind::AlreadyResolved
+)]
+#[case::already_charged_back(
+    "deposit, 1, 1, 1.0\ndispute, 1, 1,\nchargeback, 1, 1,\nresolve, 1, 1,",
+    ProcessingKind::AlreadyChargedBack
+)]
+#[case::dispute_funds_withdrawn(
+    "deposit, 1, 1, 2.0\nwithdrawal, 1, 2, 1.0\ndispute, 1, 1,",
+    ProcessingKind::DisputeFundsWithdrawn
+)]
+#[case::account_locked(
+    "deposit, 1, 1, 1.0\ndispute, 1, 1,\nchargeback, 1, 1,\ndeposit, 1, 2, 1.0",
+    ProcessingKind::AccountLocked
+)]
+#[case::no_account("withdrawal, 1, 1, 1.0", ProcessingKind::NoAccount)]
+#[case::arithmetic_overflow(
+    "deposit, 1, 1, 79228162514264337593543950335\ndeposit, 1, 2, 1.0",
+    ProcessingKind::ArithmeticOverflow
+)]
+fn processing_error_carries_its_kind(#[case] rows: &str, #[case] expected: ProcessingKind) {
+    let input = format!("type, client, tx, amount\n{rows}");
+
+    let mut errors: Vec<Error> = Vec::new();
+    let _ = process(input.as_bytes(), |e| errors.push(e), |_| {}).count();
+
+    assert_eq!(errors.len(), 1, "expected a single error: {errors:?}");
+    assert_eq!(errors[0].processing_kind(), Some(expected));
+}
+
+#[test]
+fn csv_and_validation_errors_have_no_processing_kind() {
+    let input = "\
+type, client, tx, amount
+deposit, 1, not-a-number, 1.0
+deposit, 1, 2, -1.0";
+
+    let mut errors: Vec<Error> = Vec::new();
+    let _ = process(input.as_bytes(), |e| errors.push(e), |_| {}).count();
+
+    assert_eq!(errors.len(), 2);
+    assert!(errors.iter().all(|e| e.processing_kind().is_none()));
+}
+
+#[test]
+fn parallel_outcomes_of_a_client_keep_the_input_order() {
+    let mut rows = vec!["type, client, tx, amount".to_string()];
+    // per client, the expected sequence of (succeeded, tx) outcomes
+    let mut expected: HashMap<u16, Vec<(bool, u64)>> = HashMap::new();
+    for round in 0..20u64 {
+        for client in 1..=8u16 {
+            let base = (round * 8 + u64::from(client)) * 10;
+            let steps = [
+                (
+                    format!("deposit, {client}, {}, 1.0", base + 1),
+                    true,
+                    base + 1,
+                ),
+                (
+                    format!("withdrawal, {client}, {}, 100.0", base + 2),
+                    false,
+                    base + 2,
+                ),
+                (format!("dispute, {client}, {},", base + 1), true, base + 1),
+                (format!("dispute, {client}, {},", base + 3), false, base + 3),
+                (format!("resolve, {client}, {},", base + 1), true, base + 1),
+            ];
+            for (row, succeeded, tx) in steps {
+                rows.push(row);
+                expected.entry(client).or_default().push((succeeded, tx));
+            }
+        }
+    }
+    let input = rows.join("\n");
+
+    let outcomes: Mutex<HashMap<u16, Vec<(bool, u64)>>> = Mutex::new(HashMap::new());
+    let record = |client: u16, succeeded: bool, tx: u64| {
+        outcomes
+            .lock()
+            .unwrap()
+            .entry(client)
+            .or_default()
+            .push((succeeded, tx));
+    };
+    let _ = process_parallel(
+        input.as_bytes(),
+        |e| match e {
+            Error::Processing {
+                client_id, tx_id, ..
+            } => record(client_id, false, tx_id),
+            other => panic!("unexpected error: {other}"),
+        },
+        |tx| {
+            let (client, id) = match tx {
+                TransactionRecord::Deposit { client, tx, .. }
+                | TransactionRecord::Withdrawal { client, tx, .. }
+                | TransactionRecord::Dispute { client, tx }
+                | TransactionRecord::Resolve { client, tx, .. }
+                | TransactionRecord::Chargeback { client, tx, .. }
+                | TransactionRecord::Open { client, tx }
+                | TransactionRecord::Unlock { client, tx } => (client, tx),
+            };
+            record(client, true, id)
+        },
+        3,
+        4,
+    )
+    .count();
+
+    assert_eq!(outcomes.into_inner().unwrap(), expected);
+}
+
+#[test]
+fn row_with_missing_column_is_skipped() {
+    let input = "\
+type, client, tx, amount
+deposit, 1, 1, 1.0
+deposit, 2, 2
+deposit, 3, 3, 3.0";
+
+    let mut errors: Vec<Error> = Vec::new();
+    let mut successes = 0;
+    let records: Vec<AccountRecord> =
+        process(input.as_bytes(), |e| errors.push(e), |_| successes += 1).collect();
+
+    assert_eq!(records.len(), 2);
+    assert_eq!(successes, 2);
+    assert_eq!(errors.len(), 1);
+    assert!(matches!(&errors[0], Error::Csv { line: Some(3), .. }));
+}
+
+#[test]
+fn error_callback_can_abort_the_processing() {
+    let input = "\
+type, client, tx, amount
+deposit, 1, 1, 5.0
+deposit, 2, 2, 1.0
+withdrawal, 1, 3, 10.0
+deposit, 1, 4, 2.0
+deposit, 3, 5, 1.0";
+
+    let mut errors = 0;
+    let mut successes = 0;
+    let records: Vec<AccountRecord> = process_until(
+        input.as_bytes(),
+        ProcessConfig::default(),
+        |_| {
+            errors += 1;
+            ControlFlow::Break(())
+        },
+        |_| {
+            successes += 1;
+            ControlFlow::Continue(())
+        },
+    )
+    .collect();
+
+    assert_eq!((errors, successes), (1, 2));
+    // the accounts reflect the transactions up to the failed withdrawal
+    assert_eq!(records.len(), 2);
+    let client_1 = records.iter().find(|r| r.client == 1).unwrap();
+    assert_eq!(client_1.available.to_string(), "5.0");
+}
+
+#[test]
+fn parallel_engine_stops_after_a_break() {
+    let mut rows = vec![
+        "type, client, tx, amount".to_string(),
+        "deposit, 1, x, 1.0".to_string(),
+    ];
+    rows.extend((1..=10_000).map(|tx| format!("deposit, {}, {tx}, 1.0", tx % 16 + 1)));
+    let input = rows.join("\n");
+
+    let stopped = AtomicBool::new(false);
+    let config = ProcessConfig::default().workers(4).channel_capacity(4);
+    let records: Vec<AccountRecord> = process_until(
+        input.as_bytes(),
+        config,
+        |_| {
+            stopped.store(true, Ordering::Relaxed);
+            ControlFlow::Break(())
+        },
+        |_| {
+            assert!(
+                !stopped.load(Ordering::Relaxed),
+                "no callback is invoked after the break"
+            );
+            ControlFlow::Continue(())
+        },
+    )
+    .collect();
+
+    assert!(stopped.into_inner());
+    let applied: Decimal = records.iter().map(|r| r.total).sum();
+    assert!(applied < Decimal::from(10_000), "the input was not skipped");
+}
+
+#[test]
+fn worker_panic_is_reported_and_the_other_shards_survive() {
+    let input = "\
+type, client, tx, amount
+deposit, 1, 1, 1.0
+deposit, 2, 2, 2.0
+deposit, 3, 3, 3.0
+deposit, 4, 4, 4.0
+dispute, 1, 1,
+chargeback, 1, 1,";
+
+    // clients 1 and 3 are sharded to worker 1, which panics when client 1 is locked
+    let config = ProcessConfig::default()
+        .workers(2)
+        .on_locked_account(|account| panic!("injected panic for client {}", account.client));
+    let mut errors: Vec<Error> = Vec::new();
+    let (records, stats) = process_with_stats(input.as_bytes(), config, |e| errors.push(e), |_| {});
+    let mut clients: Vec<u16> = records.map(|r| r.client).collect();
+    clients.sort();
+
+    assert_eq!(clients, vec![2, 4]);
+    assert_eq!(errors.len(), 2);
+    // the chargeback whose handling panicked is rejected
+    assert!(matches!(
+        errors[0],
+        Error::ShardUnavailable {
+            worker: 1,
+            client_id: 1,
+            tx_id: 1
+        }
+    ));
+    let Error::WorkerFailed { worker, message } = &errors[1] else {
+        panic!("unexpected error: {}", errors[1]);
+    };
+    assert_eq!(*worker, 1);
+    assert_eq!(message, "injected panic for client 1");
+    assert_eq!(stats.worker_failures, 1);
+    assert_eq!(stats.rejected(), 1);
+}
+
+#[test]
+fn transactions_of_a_failed_shard_are_rejected() {
+    let input = "\
+type, client, tx, amount
+deposit, 1, 1, 1.0
+deposit, 2, 2, 2.0
+dispute, 1, 1,
+chargeback, 1, 1,
+deposit, 3, 3, 3.0
+deposit, 2, 4, 4.0
+withdrawal, 1, 5, 1.0";
+
+    // worker 1 (clients 1 and 3) panics on the chargeback, which is rejected together with the later transactions of
+    // the shard; the dispatcher waits for each transaction, so that these are dispatched after the panic
+    let config = ProcessConfig::default()
+        .workers(2)
+        .deterministic(true)
+        .on_locked_account(|account| panic!("injected panic for client {}", account.client));
+    let mut errors: Vec<Error> = Vec::new();
+    let (records, stats) = process_with_stats(input.as_bytes(), config, |e| errors.push(e), |_| {});
+    let records: Vec<AccountRecord> = records.collect();
+
+    let rejected: Vec<(usize, u16, u64)> = errors
+        .iter()
+        .filter_map(|e| match *e {
+            Error::ShardUnavailable {
+                worker,
+                client_id,
+                tx_id,
+            } => Some((worker, client_id, tx_id)),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(rejected, vec![(1, 1, 1), (1, 3, 3), (1, 1, 5)]);
+    assert!(matches!(
+        errors.last(),
+        Some(Error::WorkerFailed { worker: 1, .. })
+    ));
+    assert_eq!(stats.processing_errors, 3);
+    assert_eq!(records.len(), 1);
+    assert_eq!(records[0].client, 2);
+    assert_eq!(records[0].available, Decimal::from(6));
+}
+
+#[test]
+fn transactions_queued_for_a_failed_shard_are_rejected() {
+    let mut input =
+        "type, client, tx, amount\ndeposit, 1, 1, 1.0\ndispute, 1, 1,\nchargeback, 1, 1,\n"
+            .to_string();
+    for tx in 2..=50 {
+        input.push_str(&format!("deposit, {}, {tx}, 1.0\n", tx % 4));
+    }
+
+    // worker 1 (clients 1 and 3) stalls before panicking on the chargeback, so that its channel fills up with the
+    // later transactions of the shard while the dispatcher keeps going
+    let config = ProcessConfig::default()
+        .workers(2)
+        .channel_capacity(2)
+        .on_locked_account(|account| {
+            std::thread::sleep(std::time::Duration::from_millis(50));
+            panic!("injected panic for client {}", account.client)
+        });
+    let mut errors: Vec<Error> = Vec::new();
+    let (records, stats) = process_with_stats(input.as_bytes(), config, |e| errors.push(e), |_| {});
+    let records: Vec<AccountRecord> = records.collect();
+
+    let mut rejected: Vec<u64> = errors
+        .iter()
+        .filter_map(|e| match *e {
+            Error::ShardUnavailable {
+                worker: 1, tx_id, ..
+            } => Some(tx_id),
+            _ => None,
+        })
+        .collect();
+    rejected.sort_unstable();
+    // the chargeback and every later transaction of the shard, none of them dropped
+    let expected: Vec<u64> = std::iter::once(1)
+        .chain((2..=50).filter(|tx| tx % 2 == 1))
+        .collect();
+    assert_eq!(rejected, expected);
+    assert!(matches!(
+        errors.last(),
+        Some(Error::WorkerFailed { worker: 1, .. })
+    ));
+    assert_eq!(stats.processing_errors, expected.len() as u64);
+    assert!(records.iter().all(|record| record.client % 2 == 0));
+}
+
+#[cfg(feature = "error-json")]
+#[test]
+fn errors_serialize_to_flat_json_events() {
+    let input = "\
+type, client, tx, amount
+deposit, 1, x, 1.0
+deposit, 1, 2, -1.0";
+
+    let mut errors: Vec<Error> = Vec::new();
+    let _ = process(input.as_bytes(), |e| errors.push(e), |_| {}).count();
+    let events: Vec<serde_json::Value> = errors
+        .iter()
+        .map(|e| serde_json::to_value(e).unwrap())
+        .collect();
+
+    assert_eq!(events[0]["kind"], "csv");
+    assert_eq!(events[0]["line"], 2);
+    assert!(events[0].get("client_id").is_none());
+    assert_eq!(
+        events[1],
+        serde_json::json!({
+            "kind": "validation",
+            "client_id": 1,
+            "tx_id": 2,
+            "message": "the deposited amount must be positive",
+        })
+    );
+}
+
+#[cfg(feature = "error-json")]
+#[test]
+fn header_error_events_carry_the_line_of_the_header() {
+    let input = "\
+# exported by the ledger
+# columns follow
+type, client, txid, amount
+deposit, 1, 1, 1.0";
+
+    let mut errors: Vec<Error> = Vec::new();
+    let config = ProcessConfig::default().comment_char(b'#');
+    let (records, _) = process_with_stats(input.as_bytes(), config, |e| errors.push(e), |_| {});
+    records.for_each(drop);
+
+    assert!(
+        matches!(errors[0], Error::Header { line: Some(3), .. }),
+        "{errors:?}"
+    );
+    let event = serde_json::to_value(&errors[0]).unwrap();
+    assert_eq!(event["kind"], "header");
+    assert_eq!(event["line"], 3);
+}
+
+#[rstest]
+#[case::checked(true, 1)]
+#[case::unchecked(false, 2)]
+fn mistyped_header_fails_fast_unless_disabled(#[case] check: bool, #[case] expected_errors: u64) {
+    let input = "\
+type, client, txid, amount
+deposit, 1, 1, 1.0
+deposit, 2, 2, 2.0";
+
+    let mut errors: Vec<Error> = Vec::new();
+    let config = ProcessConfig::default().check_header(check);
+    let (records, stats) = process_with_stats(input.as_bytes(), config, |e| errors.push(e), |_| {});
+
+    assert_eq!(records.count(), 0);
+    assert_eq!(stats.csv_errors, expected_errors);
+    assert_eq!(matches!(errors[0], Error::Header { .. }), check);
+    if check {
+        assert_eq!(
+            errors[0].to_string(),
+            "invalid CSV header: missing column(s) tx (found: type, client, txid, amount)"
+        );
+    }
+}
+
+#[rstest]
+fn malformed_rows_carry_their_raw_text(#[values(None, Some(2))] workers: Option<usize>) {
+    let input = "\
+type, client, tx, amount
+deposit, 1, 1, 1.0
+deposit, 1, x, 1.0
+deposit, 2, 3, -1.0
+withdrawal, 1, 4, 5.0
+deposit, 3, 5,\"1,5\"";
+
+    let mut config = ProcessConfig::default().capture_malformed_rows(true);
+    if let Some(workers) = workers {
+        config = config.workers(workers);
+    }
+    let errors = Mutex::new(Vec::new());
+    let (records, stats) = process_with_stats(
+        input.as_bytes(),
+        config,
+        |e| errors.lock().unwrap().push(e),
+        |_| {},
+    );
+    assert_eq!(records.count(), 1);
+    let mut errors = errors.into_inner().unwrap();
+    errors.sort_by_key(|e| e.to_string());
+
+    let malformed: Vec<(Option<u64>, &str, bool)> = errors
+        .iter()
+        .filter_map(|e| match e {
+            Error::Malformed { line, raw, source } => {
+                Some((*line, raw.as_str(), matches!(**source, Error::Csv { .. })))
+            }
+            _ => None,
+        })
+        .collect();
+    assert_eq!(
+        malformed,
+        vec![
+            (Some(3), "deposit,1,x,1.0", true),
+            (Some(6), "deposit,3,5,\"1,5\"", true),
+            (Some(4), "deposit,2,3,-1.0", false),
+        ]
+    );
+    // the conflict with the account state is not a malformed row
+    assert!(matches!(
+        errors[2],
+        Error::Processing {
+            kind: ProcessingKind::InsufficientFunds,
+            ..
+        }
+    ));
+    assert_eq!(stats.csv_errors, 2);
+    assert_eq!(stats.validation_errors, 1);
+}
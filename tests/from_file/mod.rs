@@ -1,7 +1,14 @@
 //! Integration tests testing against the actual crate binary and reading from and writing to a file: Test the full E2E path.
 
+use std::io::Write;
 use std::path::PathBuf;
-use std::process::Command;
+use std::process::{Command, Stdio};
+
+use rstest::rstest;
+use tx_engine_rs::{
+    AccountRecord, OutputFormat, ProcessConfig, process, process_many, process_with_config,
+    process_with_stats, write_records,
+};
 
 #[test]
 fn two_deposits() {
@@ -41,6 +48,355 @@ fn representative_e2e() {
     assert_eq!(normalize_csv(&stdout), normalize_csv(&expected));
 }
 
+#[test]
+fn two_deposits_as_json_lines() {
+    let input_path = fixture_path("two_deposits.csv");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_tx-engine-rs"))
+        .arg(&input_path)
+        .env("OUTPUT_FORMAT", "jsonl")
+        .output()
+        .expect("failed to execute binary");
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let mut lines: Vec<&str> = stdout.lines().collect();
+    lines.sort();
+    assert_eq!(
+        lines,
+        vec![
+            r#"{"client":1,"available":"1.0","held":"0","total":"1.0","locked":false}"#,
+            r#"{"client":2,"available":"2.0","held":"0","total":"2.0","locked":false}"#,
+        ]
+    );
+}
+
+#[test]
+fn unknown_output_format_fails() {
+    let output = Command::new(env!("CARGO_BIN_EXE_tx-engine-rs"))
+        .arg(fixture_path("two_deposits.csv"))
+        .env("OUTPUT_FORMAT", "xml")
+        .output()
+        .expect("failed to execute binary");
+
+    assert!(!output.status.success());
+    assert!(output.stdout.is_empty());
+}
+
+#[cfg(feature = "gzip")]
+#[test]
+fn gzipped_input_matches_uncompressed_input() {
+    let run = |fixture: &str| {
+        let output = Command::new(env!("CARGO_BIN_EXE_tx-engine-rs"))
+            .arg(fixture_path(fixture))
+            .output()
+            .expect("failed to execute binary");
+        assert!(
+            output.status.success(),
+            "binary exited with non-zero status.\nstderr: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+        normalize_csv(&String::from_utf8(output.stdout).unwrap())
+    };
+
+    assert_eq!(run("two_deposits.csv.gz"), run("two_deposits.csv"));
+}
+
+#[cfg(feature = "gzip")]
+#[test]
+fn process_gzip_decompresses_the_input() {
+    use tx_engine_rs::{process, process_gzip};
+
+    let compressed = std::fs::read(fixture_path("two_deposits.csv.gz")).unwrap();
+    let uncompressed = std::fs::read(fixture_path("two_deposits.csv")).unwrap();
+
+    let mut actual: Vec<_> =
+        process_gzip(compressed.as_slice(), |e| panic!("{e}"), |_| {}).collect();
+    let mut expected: Vec<_> =
+        process(uncompressed.as_slice(), |e| panic!("{e}"), |_| {}).collect();
+    actual.sort_by_key(|r| r.client);
+    expected.sort_by_key(|r| r.client);
+
+    assert_eq!(actual, expected);
+}
+
+#[test]
+fn parallel_flag_matches_sequential_output() {
+    let input_path = fixture_path("representative.csv");
+    let expected = std::fs::read_to_string(fixture_path("representative_expected.csv"))
+        .expect("failed to read expected output fixture");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_tx-engine-rs"))
+        .arg(&input_path)
+        .args(["--parallel", "3", "--channel-capacity", "16"])
+        .output()
+        .expect("failed to execute binary");
+
+    assert!(
+        output.status.success(),
+        "stderr: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert_eq!(normalize_csv(&stdout), normalize_csv(&expected));
+}
+
+#[rstest]
+#[case::dash(&["-"])]
+#[case::no_argument(&[])]
+fn piped_input_matches_file_input(#[case] args: &[&str]) {
+    let input_path = fixture_path("representative.csv");
+    let input = std::fs::read(&input_path).expect("failed to read fixture");
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_tx-engine-rs"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("failed to execute binary");
+    // closing stdin (by dropping it) ends the input
+    child.stdin.take().unwrap().write_all(&input).unwrap();
+    let piped = child.wait_with_output().unwrap();
+
+    let from_file = Command::new(env!("CARGO_BIN_EXE_tx-engine-rs"))
+        .arg(&input_path)
+        .output()
+        .expect("failed to execute binary");
+
+    assert!(
+        piped.status.success(),
+        "stderr: {}",
+        String::from_utf8_lossy(&piped.stderr)
+    );
+    assert_eq!(
+        normalize_csv(&String::from_utf8(piped.stdout).unwrap()),
+        normalize_csv(&String::from_utf8(from_file.stdout).unwrap())
+    );
+}
+
+#[test]
+fn closed_output_pipe_exits_cleanly() {
+    // enough accounts for the output to exceed the capacity of the pipe
+    let mut input = String::from("type, client, tx, amount\n");
+    for client in 1..=20_000 {
+        input.push_str(&format!("deposit, {client}, {client}, 1.0\n"));
+    }
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_tx-engine-rs"))
+        .arg("-")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("failed to execute binary");
+    // closes the reading end of the output before anything is written
+    drop(child.stdout.take());
+    // fed from another thread, as the binary logs to stderr while reading the input
+    let mut stdin = child.stdin.take().unwrap();
+    let feeder = std::thread::spawn(move || stdin.write_all(input.as_bytes()));
+    let output = child.wait_with_output().unwrap();
+    feeder.join().unwrap().unwrap();
+
+    assert!(
+        output.status.success(),
+        "stderr: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
+
+#[rstest]
+#[case::positional(&[])]
+#[case::short_flag(&["-o"])]
+#[case::long_flag(&["--output"])]
+fn output_file_matches_stdout(#[case] flag: &[&str]) {
+    let input_path = fixture_path("representative.csv");
+    let output_path = std::env::temp_dir().join(format!(
+        "tx-engine-rs-output-{}-{}.csv",
+        std::process::id(),
+        flag.first()
+            .unwrap_or(&"positional")
+            .trim_start_matches('-')
+    ));
+
+    let to_file = Command::new(env!("CARGO_BIN_EXE_tx-engine-rs"))
+        .arg(&input_path)
+        .args(flag)
+        .arg(&output_path)
+        .output()
+        .expect("failed to execute binary");
+    let to_stdout = Command::new(env!("CARGO_BIN_EXE_tx-engine-rs"))
+        .arg(&input_path)
+        .output()
+        .expect("failed to execute binary");
+    let written = std::fs::read_to_string(&output_path);
+    let _ = std::fs::remove_file(&output_path);
+
+    assert!(
+        to_file.status.success(),
+        "stderr: {}",
+        String::from_utf8_lossy(&to_file.stderr)
+    );
+    assert!(to_file.stdout.is_empty());
+    assert_eq!(
+        normalize_csv(&written.expect("the output file was not written")),
+        normalize_csv(&String::from_utf8(to_stdout.stdout).unwrap())
+    );
+}
+
+#[test]
+fn summary_flag_prints_a_summary_to_stderr_only() {
+    let input_path = fixture_path("representative.csv");
+    let input = std::fs::read(&input_path).expect("failed to read fixture");
+    let (records, stats) =
+        process_with_stats(input.as_slice(), ProcessConfig::default(), |_| {}, |_| {});
+    let locked = records.filter(|record| record.locked).count();
+
+    let plain = Command::new(env!("CARGO_BIN_EXE_tx-engine-rs"))
+        .arg(&input_path)
+        .output()
+        .expect("failed to execute binary");
+    let output = Command::new(env!("CARGO_BIN_EXE_tx-engine-rs"))
+        .arg(&input_path)
+        .arg("--summary")
+        .output()
+        .expect("failed to execute binary");
+
+    assert!(output.status.success());
+    assert_eq!(
+        normalize_csv(&String::from_utf8(output.stdout).unwrap()),
+        normalize_csv(&String::from_utf8(plain.stdout).unwrap())
+    );
+    let summary = format!(
+        "processed {} txs, {} rejected, {} accounts, {locked} locked",
+        stats.accepted + stats.rejected(),
+        stats.rejected(),
+        stats.accounts
+    );
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert_eq!(stderr.lines().last(), Some(summary.as_str()));
+    assert!(
+        !String::from_utf8(plain.stderr)
+            .unwrap()
+            .contains("processed")
+    );
+}
+
+#[rstest]
+#[case::default(&[], None, false)]
+#[case::verbose(&["-v"], None, true)]
+#[case::very_verbose(&["-vv"], None, true)]
+#[case::rust_log_takes_precedence(&["-v"], Some("warn"), false)]
+fn verbosity_flag_raises_the_log_level(
+    #[case] args: &[&str],
+    #[case] rust_log: Option<&str>,
+    #[case] debug_logged: bool,
+) {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_tx-engine-rs"));
+    command.arg(fixture_path("two_deposits.csv")).args(args);
+    match rust_log {
+        Some(filter) => command.env("RUST_LOG", filter),
+        None => command.env_remove("RUST_LOG"),
+    };
+
+    let output = command.output().expect("failed to execute binary");
+
+    assert!(output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert_eq!(stderr.contains("Debug mode is enabled"), debug_logged);
+}
+
+#[rstest]
+#[case::unknown_flag(&["--fast"])]
+#[case::unknown_short_flag(&["-x"])]
+#[case::unknown_verbosity(&["-vvv"])]
+#[case::missing_value(&["--parallel"])]
+#[case::invalid_value(&["--parallel", "many"])]
+#[case::missing_output(&["-o"])]
+#[case::third_positional(&["out.csv", "extra.csv"])]
+fn invalid_arguments_fail_with_usage(#[case] args: &[&str]) {
+    let output = Command::new(env!("CARGO_BIN_EXE_tx-engine-rs"))
+        .arg(fixture_path("two_deposits.csv"))
+        .args(args)
+        .output()
+        .expect("failed to execute binary");
+
+    assert!(!output.status.success());
+    assert!(output.stdout.is_empty());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Usage: tx-engine-rs"));
+}
+
+#[test]
+fn sorted_output_of_both_engines_is_byte_identical() {
+    let input = std::fs::read(fixture_path("representative.csv")).expect("failed to read fixture");
+    let serialize = |config: ProcessConfig<'static>| {
+        let mut output = Vec::new();
+        write_records(
+            process_with_config(input.as_slice(), config, |_| {}, |_| {}),
+            &mut output,
+            OutputFormat::Csv,
+        )
+        .expect("failed to write records");
+        output
+    };
+
+    let sequential = serialize(ProcessConfig::default().sort_by_client(true).rounding(4));
+    for workers in 1..=4 {
+        let parallel = serialize(
+            ProcessConfig::default()
+                .workers(workers)
+                .channel_capacity(2)
+                .sort_by_client(true)
+                .rounding(4),
+        );
+        assert_eq!(
+            String::from_utf8_lossy(&parallel),
+            String::from_utf8_lossy(&sequential),
+            "{workers} workers"
+        );
+    }
+}
+
+#[test]
+fn multiple_files_match_their_concatenation() {
+    let first = std::fs::read_to_string(fixture_path("representative.csv")).unwrap();
+    let second = std::fs::read_to_string(fixture_path("two_deposits.csv")).unwrap();
+    // the first file orders its columns differently
+    let reordered = first
+        .lines()
+        .map(|line| match line.split(',').collect::<Vec<_>>()[..] {
+            [kind, client, tx, amount] => format!("{kind},{tx},{client},{amount}"),
+            _ => line.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    let concatenated = format!(
+        "{}\n{}",
+        first.trim_end(),
+        second.split_once('\n').unwrap().1
+    );
+
+    let mut errors = 0;
+    let mut successes = 0;
+    let sources = [reordered.as_bytes(), b"".as_slice(), second.as_bytes()];
+    let mut merged: Vec<AccountRecord> =
+        process_many(sources, |_| errors += 1, |_| successes += 1).collect();
+    merged.sort_by_key(|r| r.client);
+
+    let mut expected_errors = 0;
+    let mut expected_successes = 0;
+    let mut expected: Vec<AccountRecord> = process(
+        concatenated.as_bytes(),
+        |_| expected_errors += 1,
+        |_| expected_successes += 1,
+    )
+    .collect();
+    expected.sort_by_key(|r| r.client);
+
+    assert_eq!(merged, expected);
+    assert_eq!((errors, successes), (expected_errors, expected_successes));
+}
+
 /// Returns the absolute path to a test fixture file in `tests/data/`.
 fn fixture_path(name: &str) -> PathBuf {
     PathBuf::from(env!("CARGO_MANIFEST_DIR"))
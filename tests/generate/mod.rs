@@ -25,7 +25,7 @@ fn write_expected_csv(scenarios: &[Scenario], path: &std::path::Path) {
     let mut wtr = csv::Writer::from_path(path)
         .unwrap_or_else(|e| panic!("failed to create {}: {e}", path.display()));
     for s in scenarios {
-        wtr.serialize(&s.expected_account)
+        wtr.serialize(s.expected_account)
             .expect("failed to serialize expected account");
     }
     wtr.flush().expect("failed to flush expected CSV");
@@ -45,7 +45,7 @@ fn build_catalog_scenarios(reps: usize) -> Vec<Scenario> {
 
     let mut scenarios = Vec::with_capacity(total_clients);
     let mut client_id = 1u16;
-    let mut tx_id_offset = 1u32;
+    let mut tx_id_offset = 1u64;
 
     for rep in 0..reps {
         for (shape_idx, shape) in shapes.iter().enumerate() {
@@ -58,7 +58,7 @@ fn build_catalog_scenarios(reps: usize) -> Vec<Scenario> {
                 .collect();
 
             let scenario = shape.build(client_id, tx_id_offset, &params);
-            tx_id_offset += scenario.transactions.len() as u32;
+            tx_id_offset += scenario.transactions.len() as u64;
             scenarios.push(scenario);
             client_id += 1;
         }
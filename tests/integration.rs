@@ -1,11 +1,26 @@
 //! Integration tests for the transaction engine.
 
+mod binary;
 mod chargeback;
+mod config;
 mod deposit;
+mod deposit_log;
 mod dispute;
+mod engine;
+mod errors;
 mod from_file;
 mod generate;
+mod ledger;
+#[cfg(feature = "metrics")]
+mod metrics;
+#[cfg(feature = "model")]
+mod model;
+mod open;
 mod resolve;
+mod sharding;
+mod stats;
+#[cfg(feature = "async")]
+mod stream;
 mod withdrawal;
 
 pub(crate) mod scenarios;
@@ -0,0 +1,56 @@
+//! Integration tests for the account state machine on its own, as available without the `std` feature
+
+use rust_decimal_macros::dec;
+use tx_engine_rs::ledger::{
+    Chargeback, ClientId, Deposit, Dispute, Ledger, LockReason, ProcessingKind, Transaction, TxId,
+    Withdrawal,
+};
+use tx_engine_rs::{AccountRecord, process};
+
+#[test]
+fn ledger_applies_transactions_like_the_engine() {
+    let input = "\
+type, client, tx, amount
+deposit, 1, 1, 10.0
+withdrawal, 1, 2, 3.0
+deposit, 2, 3, 4.0
+dispute, 2, 3,
+chargeback, 2, 3,";
+    let (client_1, client_2) = (ClientId::new(1), ClientId::new(2));
+    let transactions = [
+        Transaction::Deposit(Deposit::new(client_1, TxId::new(1), dec!(10.0)).unwrap()),
+        Transaction::Withdrawal(Withdrawal::new(client_1, TxId::new(2), dec!(3.0)).unwrap()),
+        Transaction::Deposit(Deposit::new(client_2, TxId::new(3), dec!(4.0)).unwrap()),
+        Transaction::Dispute(Dispute::new(client_2, TxId::new(3))),
+        Transaction::Chargeback(Chargeback::new(client_2, TxId::new(3))),
+    ];
+
+    let mut ledger = Ledger::new();
+    for tx in &transactions {
+        ledger.apply(tx).unwrap();
+    }
+    let mut expected: Vec<AccountRecord> = process(input.as_bytes(), |_| {}, |_| {}).collect();
+    expected.sort_by_key(|r| r.client);
+
+    for record in expected {
+        let client = ClientId::new(record.client);
+        assert_eq!(ledger.available(client), Some(record.available));
+        assert_eq!(ledger.held(client), Some(record.held));
+        assert_eq!(ledger.lock_reason(client), record.lock_reason);
+    }
+    assert_eq!(
+        ledger.lock_reason(client_2),
+        Some(LockReason::Chargeback { tx_id: 3 })
+    );
+}
+
+#[test]
+fn ledger_rejects_transactions_of_unknown_clients() {
+    let mut ledger = Ledger::new();
+    let dispute = Transaction::Dispute(Dispute::new(ClientId::new(1), TxId::new(1)));
+
+    let rejection = ledger.apply(&dispute).unwrap_err();
+
+    assert_eq!(rejection.kind, ProcessingKind::NoAccount);
+    assert_eq!(ledger.available(ClientId::new(1)), None);
+}
@@ -0,0 +1,65 @@
+//! Integration tests for the counters exported with the `metrics` feature
+
+use std::collections::HashMap;
+
+use metrics_util::debugging::{DebugValue, DebuggingRecorder};
+use tx_engine_rs::process;
+
+/// Processes the input with a recorder local to the current thread and returns the counters, keyed by the metric
+/// name and the value of the `kind` label (if any).
+fn counters_after_processing(input: &str) -> HashMap<(String, Option<String>), u64> {
+    let recorder = DebuggingRecorder::new();
+    let snapshotter = recorder.snapshotter();
+    metrics::with_local_recorder(&recorder, || {
+        let _ = process(input.as_bytes(), |_| {}, |_| {}).count();
+    });
+
+    snapshotter
+        .snapshot()
+        .into_vec()
+        .into_iter()
+        .filter_map(|(key, _, _, value)| {
+            let DebugValue::Counter(count) = value else {
+                return None;
+            };
+            let key = key.key();
+            let kind = key
+                .labels()
+                .find(|label| label.key() == "kind")
+                .map(|label| label.value().to_string());
+            Some(((key.name().to_string(), kind), count))
+        })
+        .collect()
+}
+
+#[test]
+fn counters_track_accepted_rejected_and_locked() {
+    let input = "\
+type, client, tx, amount
+deposit, 1, 1, 10.0
+deposit, 1, 2, -1.0
+withdrawal, 1, 3, 50.0
+withdrawal, 1, 4, 60.0
+dispute, 1, 1,
+chargeback, 1, 1,
+deposit, 1, 5, 1.0";
+
+    let counters = counters_after_processing(input);
+
+    let counter = |name: &str, kind: Option<&str>| {
+        counters
+            .get(&(name.to_string(), kind.map(str::to_string)))
+            .copied()
+    };
+    assert_eq!(counter("tx_accepted_total", None), Some(3));
+    assert_eq!(counter("tx_rejected_total", Some("validation")), Some(1));
+    assert_eq!(
+        counter("tx_rejected_total", Some("insufficient_funds")),
+        Some(2)
+    );
+    assert_eq!(
+        counter("tx_rejected_total", Some("account_locked")),
+        Some(1)
+    );
+    assert_eq!(counter("accounts_locked_total", None), Some(1));
+}
@@ -0,0 +1,97 @@
+//! Integration tests for processing programmatically constructed transactions via the `ledger` types
+
+use rust_decimal_macros::dec;
+use tx_engine_rs::ledger::{ClientId, Deposit, Dispute, Resolve, Transaction, TxId, Withdrawal};
+use tx_engine_rs::{AccountRecord, Error, ProcessConfig, process, process_transactions_iter};
+
+fn sorted(mut records: Vec<AccountRecord>) -> Vec<AccountRecord> {
+    records.sort_by_key(|r| r.client);
+    records
+}
+
+#[test]
+fn constructors_reject_non_positive_amounts() {
+    let (client, tx) = (ClientId::new(1), TxId::new(1));
+
+    assert!(Deposit::new(client, tx, dec!(0)).is_err());
+    assert!(Withdrawal::new(client, tx, dec!(-1.0)).is_err());
+}
+
+#[test]
+fn constructed_transactions_match_parsed_ones() {
+    let input = "\
+type, client, tx, amount
+deposit, 1, 1, 5.0
+deposit, 2, 2, 3.0
+withdrawal, 1, 3, 1.5
+dispute, 2, 2,";
+    let expected = sorted(process(input.as_bytes(), |_| {}, |_| {}).collect());
+
+    let transactions = [
+        Transaction::Deposit(Deposit::new(ClientId::new(1), TxId::new(1), dec!(5.0)).unwrap()),
+        Transaction::Deposit(Deposit::new(ClientId::new(2), TxId::new(2), dec!(3.0)).unwrap()),
+        Transaction::Withdrawal(
+            Withdrawal::new(ClientId::new(1), TxId::new(3), dec!(1.5)).unwrap(),
+        ),
+        Transaction::Dispute(Dispute::new(ClientId::new(2), TxId::new(2))),
+    ];
+    for config in [
+        ProcessConfig::default(),
+        ProcessConfig::default().workers(2),
+    ] {
+        let actual = sorted(
+            process_transactions_iter(
+                transactions,
+                config,
+                |e| panic!("unexpected error: {e}"),
+                |_| {},
+            )
+            .collect(),
+        );
+        assert_eq!(actual, expected);
+    }
+}
+
+#[test]
+fn constructed_transactions_are_validated_like_parsed_ones() {
+    let transactions = [
+        // more than four decimal places
+        Transaction::Deposit(Deposit::new(ClientId::new(1), TxId::new(1), dec!(1.00001)).unwrap()),
+        // reserved client
+        Transaction::Deposit(Deposit::new(ClientId::new(0), TxId::new(2), dec!(1.0)).unwrap()),
+    ];
+
+    let mut errors: Vec<Error> = Vec::new();
+    let records: Vec<AccountRecord> = process_transactions_iter(
+        transactions,
+        ProcessConfig::default(),
+        |e| errors.push(e),
+        |_| {},
+    )
+    .collect();
+
+    assert!(records.is_empty());
+    assert_eq!(errors.len(), 2);
+    assert!(errors.iter().all(|e| matches!(e, Error::Validation { .. })));
+}
+
+#[test]
+fn partial_resolve_is_applied_with_its_amount() {
+    let (client, tx) = (ClientId::new(1), TxId::new(1));
+    let transactions = [
+        Transaction::Deposit(Deposit::new(client, tx, dec!(10.0)).unwrap()),
+        Transaction::Dispute(Dispute::new(client, tx)),
+        Transaction::Resolve(Resolve::partial(client, tx, dec!(3.0)).unwrap()),
+    ];
+
+    let records: Vec<AccountRecord> = process_transactions_iter(
+        transactions,
+        ProcessConfig::default().partial_settlements(true),
+        |e| panic!("unexpected error: {e}"),
+        |_| {},
+    )
+    .collect();
+
+    assert_eq!(records[0].available, dec!(3.0));
+    assert_eq!(records[0].held, dec!(7.0));
+}
@@ -0,0 +1,136 @@
+//! Integration tests for open transactions
+
+use tx_engine_rs::{AccountRecord, Error, TransactionRecord, process};
+
+use rust_decimal_macros::dec;
+
+#[test]
+fn open_creates_an_empty_account() {
+    let input = "\
+type, client, tx, amount
+open, 1, 1,";
+
+    let expected = AccountRecord {
+        client: 1,
+        available: dec!(0),
+        held: dec!(0),
+        total: dec!(0),
+        locked: false,
+        lock_reason: None,
+        num_transactions: None,
+    };
+
+    let mut successful_txs: Vec<TransactionRecord> = Vec::new();
+    let records: Vec<AccountRecord> = process(
+        input.as_bytes(),
+        |e| panic!("unexpected error: {e}"),
+        |tx| successful_txs.push(tx),
+    )
+    .collect();
+
+    assert_eq!(records, vec![expected]);
+    assert_eq!(
+        successful_txs,
+        vec![TransactionRecord::Open { client: 1, tx: 1 }]
+    );
+}
+
+#[test]
+fn open_with_explicit_zero_amount_is_accepted() {
+    let input = "\
+type, client, tx, amount
+open, 1, 1, 0.0";
+
+    let records: Vec<AccountRecord> = process(
+        input.as_bytes(),
+        |e| panic!("unexpected error: {e}"),
+        |_| {},
+    )
+    .collect();
+
+    assert_eq!(records.len(), 1);
+    assert_eq!(records[0].total, dec!(0));
+}
+
+#[test]
+fn open_with_non_zero_amount_is_rejected() {
+    let input = "\
+type, client, tx, amount
+open, 1, 1, 1.0";
+
+    let mut errors: Vec<Error> = Vec::new();
+    let records: Vec<AccountRecord> = process(
+        input.as_bytes(),
+        |e| errors.push(e),
+        |_| panic!("unexpected success"),
+    )
+    .collect();
+
+    assert!(records.is_empty());
+    assert_eq!(errors.len(), 1);
+    assert!(matches!(
+        errors[0],
+        Error::Validation {
+            client_id: 1,
+            tx_id: 1,
+            ..
+        }
+    ));
+}
+
+#[test]
+fn open_of_an_existing_account_is_a_no_op() {
+    let input = "\
+type, client, tx, amount
+deposit, 1, 1, 2.5
+open, 1, 2,
+withdrawal, 1, 3, 1.0";
+
+    let records: Vec<AccountRecord> = process(
+        input.as_bytes(),
+        |e| panic!("unexpected error: {e}"),
+        |_| {},
+    )
+    .collect();
+
+    assert_eq!(records.len(), 1);
+    assert_eq!(records[0].available, dec!(1.5));
+}
+
+#[test]
+fn opened_account_accepts_transactions_of_the_client() {
+    let input = "\
+type, client, tx, amount
+open, 1, 1,
+withdrawal, 1, 2, 1.0
+deposit, 1, 3, 2.0";
+
+    let mut errors: Vec<Error> = Vec::new();
+    let records: Vec<AccountRecord> =
+        process(input.as_bytes(), |e| errors.push(e), |_| {}).collect();
+
+    // the withdrawal is rejected for insufficient funds rather than for a missing account
+    assert_eq!(errors.len(), 1);
+    assert!(errors[0].to_string().contains("insufficient funds"));
+    assert_eq!(records[0].available, dec!(2.0));
+}
+
+#[test]
+fn open_of_a_locked_account_keeps_it_locked() {
+    let input = "\
+type, client, tx, amount
+deposit, 1, 1, 2.0
+dispute, 1, 1,
+chargeback, 1, 1,
+open, 1, 2,";
+
+    let records: Vec<AccountRecord> = process(
+        input.as_bytes(),
+        |e| panic!("unexpected error: {e}"),
+        |_| {},
+    )
+    .collect();
+
+    assert!(records[0].locked);
+    assert_eq!(records[0].total, dec!(0));
+}
@@ -1,7 +1,13 @@
 //! "Manual" integration tests targeted mainly on the resolve mechanic
 
+use std::sync::Mutex;
+
+use rstest::rstest;
 use rust_decimal_macros::dec;
-use tx_engine_rs::{AccountRecord, Error, TransactionRecord, process};
+use tx_engine_rs::{
+    AccountRecord, DisputePolicy, Error, ProcessConfig, ProcessingKind, TransactionEngine,
+    TransactionRecord, process, process_with_config,
+};
 
 #[test]
 fn deposit_dispute_then_resolve() {
@@ -17,6 +23,8 @@ resolve, 1, 1,";
         held: dec!(0),
         total: dec!(10.0),
         locked: false,
+        lock_reason: None,
+        num_transactions: None,
     };
 
     let mut errors: Vec<Error> = Vec::new();
@@ -47,7 +55,11 @@ resolve, 1, 1,";
     );
     assert_eq!(
         successful_txs[2],
-        TransactionRecord::Resolve { client: 1, tx: 1 }
+        TransactionRecord::Resolve {
+            client: 1,
+            tx: 1,
+            amount: None
+        }
     );
 }
 
@@ -64,6 +76,8 @@ resolve, 1, 3,";
         held: dec!(0),
         total: dec!(10.0),
         locked: false,
+        lock_reason: None,
+        num_transactions: None,
     };
 
     let mut errors: Vec<Error> = Vec::new();
@@ -106,6 +120,8 @@ resolve, 1, 1,";
         held: dec!(0),
         total: dec!(10.0),
         locked: false,
+        lock_reason: None,
+        num_transactions: None,
     };
 
     let mut errors: Vec<Error> = Vec::new();
@@ -151,6 +167,8 @@ resolve, 1, 1,";
         held: dec!(0),
         total: dec!(10.0),
         locked: false,
+        lock_reason: None,
+        num_transactions: None,
     };
 
     let mut errors: Vec<Error> = Vec::new();
@@ -174,10 +192,36 @@ resolve, 1, 1,";
             Error::Processing {
                 client_id: 1,
                 tx_id: 1,
+                kind: ProcessingKind::AlreadyResolved,
                 ..
             }
         ),
-        "expected a processing error for the duplicate resolve"
+        "expected an already-resolved error for the duplicate resolve"
+    );
+}
+
+#[test]
+fn resolve_after_re_dispute_is_accepted() {
+    let input = "\
+type, client, tx, amount
+deposit, 1, 1, 10.0
+dispute, 1, 1,
+resolve, 1, 1,
+dispute, 1, 1,
+resolve, 1, 1,
+resolve, 1, 1,";
+
+    let mut errors: Vec<Error> = Vec::new();
+    let records: Vec<AccountRecord> =
+        process(input.as_bytes(), |e| errors.push(e), |_| {}).collect();
+
+    assert_eq!(records[0].available, dec!(10.0));
+    assert_eq!(records[0].held, dec!(0));
+    // only the last resolve is a repetition
+    assert_eq!(errors.len(), 1);
+    assert_eq!(
+        errors[0].processing_kind(),
+        Some(ProcessingKind::AlreadyResolved)
     );
 }
 
@@ -196,6 +240,8 @@ dispute, 1, 1,";
         held: dec!(10.0),
         total: dec!(10.0),
         locked: false,
+        lock_reason: None,
+        num_transactions: None,
     };
 
     let mut errors: Vec<Error> = Vec::new();
@@ -215,6 +261,52 @@ dispute, 1, 1,";
     assert_eq!(successful_txs.len(), 4);
 }
 
+#[rstest]
+#[case::allowed(true, None)]
+#[case::forbidden(false, Some(ProcessingKind::AlreadyResolved))]
+fn re_dispute_after_resolve_is_configurable(
+    #[case] allow: bool,
+    #[case] expected_kind: Option<ProcessingKind>,
+    #[values(None, Some(2))] workers: Option<usize>,
+) {
+    let input = "\
+type, client, tx, amount
+deposit, 1, 1, 10.0
+dispute, 1, 1,
+resolve, 1, 1,
+dispute, 1, 1,";
+    let mut config = ProcessConfig::default().allow_redispute(allow);
+    if let Some(workers) = workers {
+        config = config.workers(workers);
+    }
+
+    let errors = Mutex::new(Vec::new());
+    let records: Vec<AccountRecord> = process_with_config(
+        input.as_bytes(),
+        config,
+        |e| errors.lock().unwrap().push(e),
+        |_| {},
+    )
+    .collect();
+
+    let errors = errors.into_inner().unwrap();
+    assert_eq!(
+        errors.first().and_then(Error::processing_kind),
+        expected_kind
+    );
+    if allow {
+        assert_eq!(records[0].held, dec!(10.0));
+    } else {
+        assert_eq!(errors.len(), 1);
+        assert_eq!(
+            errors[0].to_string(),
+            "processing conflict — client: 1, tx: 1: transaction already resolved"
+        );
+        assert_eq!(records[0].available, dec!(10.0));
+        assert_eq!(records[0].held, dec!(0));
+    }
+}
+
 #[test]
 fn two_deposits_dispute_and_resolve_first() {
     let input = "\
@@ -230,6 +322,8 @@ resolve, 1, 1,";
         held: dec!(0),
         total: dec!(30.0),
         locked: false,
+        lock_reason: None,
+        num_transactions: None,
     };
 
     let mut errors: Vec<Error> = Vec::new();
@@ -248,3 +342,159 @@ resolve, 1, 1,";
     // Two deposits, dispute, resolve — all succeed
     assert_eq!(successful_txs.len(), 4);
 }
+
+#[rstest]
+#[case::partial_resolve("resolve, 1, 1, 4.0", dec!(4.0), dec!(6.0), false)]
+#[case::remainder_charged_back("resolve, 1, 1, 4.0\nchargeback, 1, 1,", dec!(4.0), dec!(0), true)]
+#[case::remainder_resolved("resolve, 1, 1, 4.0\nresolve, 1, 1, 6.0", dec!(10.0), dec!(0), false)]
+#[case::partial_chargeback("chargeback, 1, 1, 4.0", dec!(0), dec!(6.0), true)]
+#[case::remainder_resolved_after_chargeback("chargeback, 1, 1, 4.0\nresolve, 1, 1,", dec!(6.0), dec!(0), true)]
+#[case::remainder_charged_back_after_chargeback("chargeback, 1, 1, 4.0\nchargeback, 1, 1,", dec!(0), dec!(0), true)]
+fn partial_settlement_leaves_the_remainder_held(
+    #[case] settlement: &str,
+    #[case] available: rust_decimal::Decimal,
+    #[case] held: rust_decimal::Decimal,
+    #[case] locked: bool,
+    #[values(None, Some(2))] workers: Option<usize>,
+) {
+    let input = format!(
+        "\
+type, client, tx, amount
+deposit, 1, 1, 10.0
+dispute, 1, 1,
+{settlement}"
+    );
+    let mut config = ProcessConfig::default()
+        .partial_settlements(true)
+        .verify_conservation(true);
+    if let Some(workers) = workers {
+        config = config.workers(workers);
+    }
+
+    let errors = Mutex::new(Vec::new());
+    let records: Vec<AccountRecord> = process_with_config(
+        input.as_bytes(),
+        config,
+        |e| errors.lock().unwrap().push(e),
+        |_| {},
+    )
+    .collect();
+
+    let errors = errors.into_inner().unwrap();
+    assert!(errors.is_empty(), "expected no errors, got: {errors:?}");
+    assert_eq!(records[0].available, available);
+    assert_eq!(records[0].held, held);
+    assert_eq!(records[0].locked, locked);
+}
+
+#[rstest]
+#[case::exceeding_the_held_funds(
+    true,
+    "resolve, 1, 1, 10.5",
+    Some(ProcessingKind::InvalidSettlement)
+)]
+#[case::of_a_withdrawal(true, "resolve, 1, 2, 1.0", Some(ProcessingKind::InvalidSettlement))]
+#[case::disabled(false, "resolve, 1, 1, 4.0", None)]
+fn invalid_partial_settlement_is_rejected(
+    #[case] enabled: bool,
+    #[case] settlement: &str,
+    #[case] expected_kind: Option<ProcessingKind>,
+) {
+    let input = format!(
+        "\
+type, client, tx, amount
+deposit, 1, 1, 20.0
+withdrawal, 1, 2, 10.0
+dispute, 1, 1,
+dispute, 1, 2,
+{settlement}"
+    );
+    let config = ProcessConfig::default()
+        .partial_settlements(enabled)
+        .dispute_policy(DisputePolicy::PartialHold)
+        .dispute_withdrawals(true);
+
+    let mut errors = Vec::new();
+    let records: Vec<AccountRecord> =
+        process_with_config(input.as_bytes(), config, |e| errors.push(e), |_| {}).collect();
+
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].processing_kind(), expected_kind);
+    if !enabled {
+        assert!(matches!(errors[0], Error::Validation { .. }));
+    }
+    assert_eq!(records[0].available, dec!(0));
+    assert_eq!(records[0].held, dec!(20.0));
+}
+
+#[test]
+fn partial_settlements_are_reported_with_their_amount() {
+    let input = "\
+type, client, tx, amount
+deposit, 1, 1, 10.0
+dispute, 1, 1,
+resolve, 1, 1, 3.0
+chargeback, 1, 1, 2.0";
+
+    let mut successful_txs = Vec::new();
+    let records: Vec<AccountRecord> = process_with_config(
+        input.as_bytes(),
+        ProcessConfig::default().partial_settlements(true),
+        |e| panic!("unexpected error: {e}"),
+        |tx| successful_txs.push(tx),
+    )
+    .collect();
+
+    assert_eq!(
+        successful_txs[2..],
+        [
+            TransactionRecord::Resolve {
+                client: 1,
+                tx: 1,
+                amount: Some(dec!(3.0)),
+            },
+            TransactionRecord::Chargeback {
+                client: 1,
+                tx: 1,
+                amount: Some(dec!(2.0)),
+            },
+        ]
+    );
+    assert_eq!(
+        successful_txs[2].to_string(),
+        "Resolve { client: 1, tx: 1, amount: 3.0 }"
+    );
+    assert_eq!(records[0].available, dec!(3.0));
+    assert_eq!(records[0].held, dec!(5.0));
+}
+
+#[test]
+fn partial_settlement_records_are_applied_by_the_engine_handle() {
+    let mut engine = TransactionEngine::new(ProcessConfig::default().partial_settlements(true));
+    engine
+        .feed(TransactionRecord::Deposit {
+            client: 1,
+            tx: 1,
+            amount: dec!(10.0),
+        })
+        .unwrap();
+    engine
+        .feed(TransactionRecord::Dispute { client: 1, tx: 1 })
+        .unwrap();
+
+    let resolve = TransactionRecord::Resolve {
+        client: 1,
+        tx: 1,
+        amount: Some(dec!(3.0)),
+    };
+    assert_eq!(engine.apply(resolve).unwrap(), resolve);
+    assert_eq!(engine.available(1), Some(dec!(3.0)));
+    assert_eq!(engine.held(1), Some(dec!(7.0)));
+
+    // an amount is invalid unless partial settlements are enabled
+    let mut strict = TransactionEngine::new(ProcessConfig::default());
+    assert!(matches!(
+        strict.feed(resolve),
+        Err(Error::Validation { .. })
+    ));
+}
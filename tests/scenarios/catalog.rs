@@ -3,7 +3,7 @@
 //! Add new shapes here as new transaction types are implemented.
 
 use rust_decimal::Decimal;
-use tx_engine_rs::AccountRecord;
+use tx_engine_rs::{AccountRecord, LockReason};
 
 use super::scenario::{Scenario, ScenarioShape};
 
@@ -16,7 +16,7 @@ impl ScenarioShape for SingleDeposit {
         1
     }
 
-    fn build(&self, client_id: u16, tx_id_offset: u32, random_parameters: &[Decimal]) -> Scenario {
+    fn build(&self, client_id: u16, tx_id_offset: u64, random_parameters: &[Decimal]) -> Scenario {
         let amount = random_parameters[0];
         let tx_id = tx_id_offset + 1;
 
@@ -30,6 +30,8 @@ impl ScenarioShape for SingleDeposit {
                 held: Decimal::ZERO,
                 total: amount,
                 locked: false,
+                lock_reason: None,
+                num_transactions: None,
             },
             expected_successes: vec![tx_id],
             expected_errors: vec![],
@@ -42,7 +44,7 @@ impl ScenarioShape for SingleDeposit {
 pub struct TwoDeposits;
 
 impl ScenarioShape for TwoDeposits {
-    fn build(&self, client_id: u16, tx_id_offset: u32, random_parameters: &[Decimal]) -> Scenario {
+    fn build(&self, client_id: u16, tx_id_offset: u64, random_parameters: &[Decimal]) -> Scenario {
         let amount_a = random_parameters[0];
         let amount_b = random_parameters[1];
 
@@ -65,6 +67,8 @@ impl ScenarioShape for TwoDeposits {
                 held: Decimal::ZERO,
                 total: amount_a + amount_b,
                 locked: false,
+                lock_reason: None,
+                num_transactions: None,
             },
 
             expected_successes: vec![tx_id_offset + 1, tx_id_offset + 2],
@@ -86,7 +90,7 @@ impl ScenarioShape for DepositsWithInvalidAmounts {
         2 // [0] = valid deposit amount, [1] = base for the negative deposit
     }
 
-    fn build(&self, client_id: u16, tx_id_offset: u32, random_parameters: &[Decimal]) -> Scenario {
+    fn build(&self, client_id: u16, tx_id_offset: u64, random_parameters: &[Decimal]) -> Scenario {
         let valid_amount = random_parameters[0];
         let negative_amount = -random_parameters[1];
 
@@ -108,6 +112,8 @@ impl ScenarioShape for DepositsWithInvalidAmounts {
                 held: Decimal::ZERO,
                 total: valid_amount,
                 locked: false,
+                lock_reason: None,
+                num_transactions: None,
             },
             expected_successes: vec![tx_valid],
             expected_errors: vec![tx_zero, tx_negative],
@@ -124,7 +130,7 @@ impl ScenarioShape for DepositThenWithdraw {
         2
     }
 
-    fn build(&self, client_id: u16, tx_id_offset: u32, random_parameters: &[Decimal]) -> Scenario {
+    fn build(&self, client_id: u16, tx_id_offset: u64, random_parameters: &[Decimal]) -> Scenario {
         let deposit = random_parameters[0] + random_parameters[1];
         let withdrawal = random_parameters[1];
         let remaining = deposit - withdrawal; // = random_parameters[0], always positive
@@ -145,6 +151,8 @@ impl ScenarioShape for DepositThenWithdraw {
                 held: Decimal::ZERO,
                 total: remaining,
                 locked: false,
+                lock_reason: None,
+                num_transactions: None,
             },
             expected_successes: vec![tx_dep, tx_wdr],
             expected_errors: vec![],
@@ -162,7 +170,7 @@ impl ScenarioShape for DepositThenTwoWithdrawals {
         3
     }
 
-    fn build(&self, client_id: u16, tx_id_offset: u32, random_parameters: &[Decimal]) -> Scenario {
+    fn build(&self, client_id: u16, tx_id_offset: u64, random_parameters: &[Decimal]) -> Scenario {
         let wdr_a = random_parameters[1];
         let wdr_b = random_parameters[2];
         let deposit = random_parameters[0] + wdr_a + wdr_b;
@@ -186,6 +194,8 @@ impl ScenarioShape for DepositThenTwoWithdrawals {
                 held: Decimal::ZERO,
                 total: remaining,
                 locked: false,
+                lock_reason: None,
+                num_transactions: None,
             },
             expected_successes: vec![tx_dep, tx_wdr_a, tx_wdr_b],
             expected_errors: vec![],
@@ -203,7 +213,7 @@ impl ScenarioShape for OverdraftThenDepositThenWithdraw {
         3
     }
 
-    fn build(&self, client_id: u16, tx_id_offset: u32, random_parameters: &[Decimal]) -> Scenario {
+    fn build(&self, client_id: u16, tx_id_offset: u64, random_parameters: &[Decimal]) -> Scenario {
         let overdraft = random_parameters[0];
         let deposit = random_parameters[1] + random_parameters[2];
         let valid_wdr = random_parameters[2];
@@ -227,6 +237,8 @@ impl ScenarioShape for OverdraftThenDepositThenWithdraw {
                 held: Decimal::ZERO,
                 total: remaining,
                 locked: false,
+                lock_reason: None,
+                num_transactions: None,
             },
             expected_successes: vec![tx_dep, tx_wdr],
             expected_errors: vec![tx_overdraft],
@@ -243,7 +255,7 @@ impl ScenarioShape for DepositThenOverdraft {
         2
     }
 
-    fn build(&self, client_id: u16, tx_id_offset: u32, random_parameters: &[Decimal]) -> Scenario {
+    fn build(&self, client_id: u16, tx_id_offset: u64, random_parameters: &[Decimal]) -> Scenario {
         let deposit = random_parameters[0];
         let withdrawal = random_parameters[0] + random_parameters[1]; // always > deposit
 
@@ -263,6 +275,8 @@ impl ScenarioShape for DepositThenOverdraft {
                 held: Decimal::ZERO,
                 total: deposit,
                 locked: false,
+                lock_reason: None,
+                num_transactions: None,
             },
             expected_successes: vec![tx_dep],
             expected_errors: vec![tx_wdr],
@@ -279,7 +293,7 @@ impl ScenarioShape for ExactBalanceWithdrawal {
         1
     }
 
-    fn build(&self, client_id: u16, tx_id_offset: u32, random_parameters: &[Decimal]) -> Scenario {
+    fn build(&self, client_id: u16, tx_id_offset: u64, random_parameters: &[Decimal]) -> Scenario {
         let amount = random_parameters[0];
 
         let tx_dep = tx_id_offset + 1;
@@ -298,6 +312,8 @@ impl ScenarioShape for ExactBalanceWithdrawal {
                 held: Decimal::ZERO,
                 total: Decimal::ZERO,
                 locked: false,
+                lock_reason: None,
+                num_transactions: None,
             },
             expected_successes: vec![tx_dep, tx_wdr],
             expected_errors: vec![],
@@ -314,7 +330,7 @@ impl ScenarioShape for DepositThenDispute {
         1
     }
 
-    fn build(&self, client_id: u16, tx_id_offset: u32, random_parameters: &[Decimal]) -> Scenario {
+    fn build(&self, client_id: u16, tx_id_offset: u64, random_parameters: &[Decimal]) -> Scenario {
         let amount = random_parameters[0];
         let tx_dep = tx_id_offset + 1;
 
@@ -331,6 +347,8 @@ impl ScenarioShape for DepositThenDispute {
                 held: amount,
                 total: amount,
                 locked: false,
+                lock_reason: None,
+                num_transactions: None,
             },
             expected_successes: vec![tx_dep, tx_dep],
             expected_errors: vec![],
@@ -347,7 +365,7 @@ impl ScenarioShape for DisputeNonexistentTx {
         1
     }
 
-    fn build(&self, client_id: u16, tx_id_offset: u32, random_parameters: &[Decimal]) -> Scenario {
+    fn build(&self, client_id: u16, tx_id_offset: u64, random_parameters: &[Decimal]) -> Scenario {
         let amount = random_parameters[0];
         let tx_dep = tx_id_offset + 1;
         let tx_fake = tx_id_offset + 2;
@@ -365,6 +383,8 @@ impl ScenarioShape for DisputeNonexistentTx {
                 held: Decimal::ZERO,
                 total: amount,
                 locked: false,
+                lock_reason: None,
+                num_transactions: None,
             },
             expected_successes: vec![tx_dep],
             expected_errors: vec![tx_fake],
@@ -382,7 +402,7 @@ impl ScenarioShape for DisputeAWithdrawal {
         2
     }
 
-    fn build(&self, client_id: u16, tx_id_offset: u32, random_parameters: &[Decimal]) -> Scenario {
+    fn build(&self, client_id: u16, tx_id_offset: u64, random_parameters: &[Decimal]) -> Scenario {
         let withdrawal = random_parameters[1];
         let deposit = random_parameters[0] + withdrawal;
         let remaining = deposit - withdrawal;
@@ -404,6 +424,8 @@ impl ScenarioShape for DisputeAWithdrawal {
                 held: Decimal::ZERO,
                 total: remaining,
                 locked: false,
+                lock_reason: None,
+                num_transactions: None,
             },
             expected_successes: vec![tx_dep, tx_wdr],
             expected_errors: vec![tx_wdr],
@@ -422,7 +444,7 @@ impl ScenarioShape for DisputeInsufficientFunds {
         2
     }
 
-    fn build(&self, client_id: u16, tx_id_offset: u32, random_parameters: &[Decimal]) -> Scenario {
+    fn build(&self, client_id: u16, tx_id_offset: u64, random_parameters: &[Decimal]) -> Scenario {
         let remaining = random_parameters[0];
         let withdrawal = random_parameters[1];
         let deposit = remaining + withdrawal;
@@ -444,6 +466,8 @@ impl ScenarioShape for DisputeInsufficientFunds {
                 held: Decimal::ZERO,
                 total: remaining,
                 locked: false,
+                lock_reason: None,
+                num_transactions: None,
             },
             expected_successes: vec![tx_dep, tx_wdr],
             expected_errors: vec![tx_dep],
@@ -460,7 +484,7 @@ impl ScenarioShape for DoubleDispute {
         1
     }
 
-    fn build(&self, client_id: u16, tx_id_offset: u32, random_parameters: &[Decimal]) -> Scenario {
+    fn build(&self, client_id: u16, tx_id_offset: u64, random_parameters: &[Decimal]) -> Scenario {
         let amount = random_parameters[0];
         let tx_dep = tx_id_offset + 1;
 
@@ -478,6 +502,8 @@ impl ScenarioShape for DoubleDispute {
                 held: amount,
                 total: amount,
                 locked: false,
+                lock_reason: None,
+                num_transactions: None,
             },
             expected_successes: vec![tx_dep, tx_dep],
             expected_errors: vec![tx_dep],
@@ -494,7 +520,7 @@ impl ScenarioShape for TwoDepositsDisputeFirst {
         2
     }
 
-    fn build(&self, client_id: u16, tx_id_offset: u32, random_parameters: &[Decimal]) -> Scenario {
+    fn build(&self, client_id: u16, tx_id_offset: u64, random_parameters: &[Decimal]) -> Scenario {
         let first = random_parameters[0];
         let second = random_parameters[1];
 
@@ -515,6 +541,8 @@ impl ScenarioShape for TwoDepositsDisputeFirst {
                 held: first,
                 total: first + second,
                 locked: false,
+                lock_reason: None,
+                num_transactions: None,
             },
             expected_successes: vec![tx_dep_1, tx_dep_2, tx_dep_1],
             expected_errors: vec![],
@@ -535,7 +563,7 @@ impl ScenarioShape for DepositDisputeResolve {
         1
     }
 
-    fn build(&self, client_id: u16, tx_id_offset: u32, random_parameters: &[Decimal]) -> Scenario {
+    fn build(&self, client_id: u16, tx_id_offset: u64, random_parameters: &[Decimal]) -> Scenario {
         let amount = random_parameters[0];
         let tx_dep = tx_id_offset + 1;
 
@@ -553,6 +581,8 @@ impl ScenarioShape for DepositDisputeResolve {
                 held: Decimal::ZERO,
                 total: amount,
                 locked: false,
+                lock_reason: None,
+                num_transactions: None,
             },
             expected_successes: vec![tx_dep, tx_dep, tx_dep],
             expected_errors: vec![],
@@ -569,7 +599,7 @@ impl ScenarioShape for ResolveNonexistentTx {
         1
     }
 
-    fn build(&self, client_id: u16, tx_id_offset: u32, random_parameters: &[Decimal]) -> Scenario {
+    fn build(&self, client_id: u16, tx_id_offset: u64, random_parameters: &[Decimal]) -> Scenario {
         let amount = random_parameters[0];
         let tx_dep = tx_id_offset + 1;
         let tx_fake = tx_id_offset + 2;
@@ -587,6 +617,8 @@ impl ScenarioShape for ResolveNonexistentTx {
                 held: Decimal::ZERO,
                 total: amount,
                 locked: false,
+                lock_reason: None,
+                num_transactions: None,
             },
             expected_successes: vec![tx_dep],
             expected_errors: vec![tx_fake],
@@ -603,7 +635,7 @@ impl ScenarioShape for ResolveUndisputedDeposit {
         1
     }
 
-    fn build(&self, client_id: u16, tx_id_offset: u32, random_parameters: &[Decimal]) -> Scenario {
+    fn build(&self, client_id: u16, tx_id_offset: u64, random_parameters: &[Decimal]) -> Scenario {
         let amount = random_parameters[0];
         let tx_dep = tx_id_offset + 1;
 
@@ -620,6 +652,8 @@ impl ScenarioShape for ResolveUndisputedDeposit {
                 held: Decimal::ZERO,
                 total: amount,
                 locked: false,
+                lock_reason: None,
+                num_transactions: None,
             },
             expected_successes: vec![tx_dep],
             expected_errors: vec![tx_dep],
@@ -636,7 +670,7 @@ impl ScenarioShape for DoubleResolve {
         1
     }
 
-    fn build(&self, client_id: u16, tx_id_offset: u32, random_parameters: &[Decimal]) -> Scenario {
+    fn build(&self, client_id: u16, tx_id_offset: u64, random_parameters: &[Decimal]) -> Scenario {
         let amount = random_parameters[0];
         let tx_dep = tx_id_offset + 1;
 
@@ -655,6 +689,8 @@ impl ScenarioShape for DoubleResolve {
                 held: Decimal::ZERO,
                 total: amount,
                 locked: false,
+                lock_reason: None,
+                num_transactions: None,
             },
             expected_successes: vec![tx_dep, tx_dep, tx_dep],
             expected_errors: vec![tx_dep],
@@ -671,7 +707,7 @@ impl ScenarioShape for ResolveThenReDispute {
         1
     }
 
-    fn build(&self, client_id: u16, tx_id_offset: u32, random_parameters: &[Decimal]) -> Scenario {
+    fn build(&self, client_id: u16, tx_id_offset: u64, random_parameters: &[Decimal]) -> Scenario {
         let amount = random_parameters[0];
         let tx_dep = tx_id_offset + 1;
 
@@ -690,6 +726,8 @@ impl ScenarioShape for ResolveThenReDispute {
                 held: amount,
                 total: amount,
                 locked: false,
+                lock_reason: None,
+                num_transactions: None,
             },
             expected_successes: vec![tx_dep, tx_dep, tx_dep, tx_dep],
             expected_errors: vec![],
@@ -706,7 +744,7 @@ impl ScenarioShape for TwoDepositsDisputeAndResolveFirst {
         2
     }
 
-    fn build(&self, client_id: u16, tx_id_offset: u32, random_parameters: &[Decimal]) -> Scenario {
+    fn build(&self, client_id: u16, tx_id_offset: u64, random_parameters: &[Decimal]) -> Scenario {
         let amount1 = random_parameters[0];
         let amount2 = random_parameters[1];
         let tx1 = tx_id_offset + 1;
@@ -727,6 +765,8 @@ impl ScenarioShape for TwoDepositsDisputeAndResolveFirst {
                 held: Decimal::ZERO,
                 total: amount1 + amount2,
                 locked: false,
+                lock_reason: None,
+                num_transactions: None,
             },
             expected_successes: vec![tx1, tx2, tx1, tx1],
             expected_errors: vec![],
@@ -743,7 +783,7 @@ impl ScenarioShape for DepositDisputeChargeback {
         1
     }
 
-    fn build(&self, client_id: u16, tx_id_offset: u32, random_parameters: &[Decimal]) -> Scenario {
+    fn build(&self, client_id: u16, tx_id_offset: u64, random_parameters: &[Decimal]) -> Scenario {
         let amount = random_parameters[0];
         let tx_dep = tx_id_offset + 1;
 
@@ -761,6 +801,8 @@ impl ScenarioShape for DepositDisputeChargeback {
                 held: Decimal::ZERO,
                 total: Decimal::ZERO,
                 locked: true,
+                lock_reason: Some(LockReason::Chargeback { tx_id: tx_dep }),
+                num_transactions: None,
             },
             expected_successes: vec![tx_dep, tx_dep, tx_dep],
             expected_errors: vec![],
@@ -777,7 +819,7 @@ impl ScenarioShape for ChargebackNonexistentTx {
         1
     }
 
-    fn build(&self, client_id: u16, tx_id_offset: u32, random_parameters: &[Decimal]) -> Scenario {
+    fn build(&self, client_id: u16, tx_id_offset: u64, random_parameters: &[Decimal]) -> Scenario {
         let amount = random_parameters[0];
         let tx_dep = tx_id_offset + 1;
         let tx_bad = tx_id_offset + 2;
@@ -795,6 +837,8 @@ impl ScenarioShape for ChargebackNonexistentTx {
                 held: Decimal::ZERO,
                 total: amount,
                 locked: false,
+                lock_reason: None,
+                num_transactions: None,
             },
             expected_successes: vec![tx_dep],
             expected_errors: vec![tx_bad],
@@ -811,7 +855,7 @@ impl ScenarioShape for ChargebackUndisputedDeposit {
         1
     }
 
-    fn build(&self, client_id: u16, tx_id_offset: u32, random_parameters: &[Decimal]) -> Scenario {
+    fn build(&self, client_id: u16, tx_id_offset: u64, random_parameters: &[Decimal]) -> Scenario {
         let amount = random_parameters[0];
         let tx_dep = tx_id_offset + 1;
 
@@ -828,6 +872,8 @@ impl ScenarioShape for ChargebackUndisputedDeposit {
                 held: Decimal::ZERO,
                 total: amount,
                 locked: false,
+                lock_reason: None,
+                num_transactions: None,
             },
             expected_successes: vec![tx_dep],
             expected_errors: vec![tx_dep],
@@ -844,7 +890,7 @@ impl ScenarioShape for DoubleChargeback {
         1
     }
 
-    fn build(&self, client_id: u16, tx_id_offset: u32, random_parameters: &[Decimal]) -> Scenario {
+    fn build(&self, client_id: u16, tx_id_offset: u64, random_parameters: &[Decimal]) -> Scenario {
         let amount = random_parameters[0];
         let tx_dep = tx_id_offset + 1;
 
@@ -863,6 +909,8 @@ impl ScenarioShape for DoubleChargeback {
                 held: Decimal::ZERO,
                 total: Decimal::ZERO,
                 locked: true,
+                lock_reason: Some(LockReason::Chargeback { tx_id: tx_dep }),
+                num_transactions: None,
             },
             expected_successes: vec![tx_dep, tx_dep, tx_dep],
             expected_errors: vec![tx_dep],
@@ -879,7 +927,7 @@ impl ScenarioShape for FrozenAccountRejectsDeposit {
         2
     }
 
-    fn build(&self, client_id: u16, tx_id_offset: u32, random_parameters: &[Decimal]) -> Scenario {
+    fn build(&self, client_id: u16, tx_id_offset: u64, random_parameters: &[Decimal]) -> Scenario {
         let amount1 = random_parameters[0];
         let tx1 = tx_id_offset + 1;
         let tx2 = tx_id_offset + 2;
@@ -899,6 +947,8 @@ impl ScenarioShape for FrozenAccountRejectsDeposit {
                 held: Decimal::ZERO,
                 total: Decimal::ZERO,
                 locked: true,
+                lock_reason: Some(LockReason::Chargeback { tx_id: tx1 }),
+                num_transactions: None,
             },
             expected_successes: vec![tx1, tx1, tx1],
             expected_errors: vec![tx2],
@@ -915,7 +965,7 @@ impl ScenarioShape for FrozenAccountRejectsWithdrawal {
         2
     }
 
-    fn build(&self, client_id: u16, tx_id_offset: u32, random_parameters: &[Decimal]) -> Scenario {
+    fn build(&self, client_id: u16, tx_id_offset: u64, random_parameters: &[Decimal]) -> Scenario {
         let amount1 = random_parameters[0];
         let amount2 = random_parameters[1];
         let tx1 = tx_id_offset + 1;
@@ -938,6 +988,8 @@ impl ScenarioShape for FrozenAccountRejectsWithdrawal {
                 held: Decimal::ZERO,
                 total: amount2,
                 locked: true,
+                lock_reason: Some(LockReason::Chargeback { tx_id: tx1 }),
+                num_transactions: None,
             },
             expected_successes: vec![tx1, tx2, tx1, tx1],
             expected_errors: vec![tx3],
@@ -954,7 +1006,7 @@ impl ScenarioShape for FrozenAccountRejectsDispute {
         2
     }
 
-    fn build(&self, client_id: u16, tx_id_offset: u32, random_parameters: &[Decimal]) -> Scenario {
+    fn build(&self, client_id: u16, tx_id_offset: u64, random_parameters: &[Decimal]) -> Scenario {
         let amount1 = random_parameters[0];
         let amount2 = random_parameters[1];
         let tx1 = tx_id_offset + 1;
@@ -976,6 +1028,8 @@ impl ScenarioShape for FrozenAccountRejectsDispute {
                 held: Decimal::ZERO,
                 total: amount2,
                 locked: true,
+                lock_reason: Some(LockReason::Chargeback { tx_id: tx1 }),
+                num_transactions: None,
             },
             expected_successes: vec![tx1, tx2, tx1, tx1],
             expected_errors: vec![tx2],
@@ -992,7 +1046,7 @@ impl ScenarioShape for TwoDepositsDisputeAndChargebackFirst {
         2
     }
 
-    fn build(&self, client_id: u16, tx_id_offset: u32, random_parameters: &[Decimal]) -> Scenario {
+    fn build(&self, client_id: u16, tx_id_offset: u64, random_parameters: &[Decimal]) -> Scenario {
         let amount1 = random_parameters[0];
         let amount2 = random_parameters[1];
         let tx1 = tx_id_offset + 1;
@@ -1013,6 +1067,8 @@ impl ScenarioShape for TwoDepositsDisputeAndChargebackFirst {
                 held: Decimal::ZERO,
                 total: amount2,
                 locked: true,
+                lock_reason: Some(LockReason::Chargeback { tx_id: tx1 }),
+                num_transactions: None,
             },
             expected_successes: vec![tx1, tx2, tx1, tx1],
             expected_errors: vec![],
@@ -1030,7 +1086,7 @@ impl ScenarioShape for FrozenAccountRejectsChargebackOnOtherDispute {
         2
     }
 
-    fn build(&self, client_id: u16, tx_id_offset: u32, random_parameters: &[Decimal]) -> Scenario {
+    fn build(&self, client_id: u16, tx_id_offset: u64, random_parameters: &[Decimal]) -> Scenario {
         let amount1 = random_parameters[0];
         let amount2 = random_parameters[1];
         let tx1 = tx_id_offset + 1;
@@ -1053,6 +1109,8 @@ impl ScenarioShape for FrozenAccountRejectsChargebackOnOtherDispute {
                 held: amount2,
                 total: amount2,
                 locked: true,
+                lock_reason: Some(LockReason::Chargeback { tx_id: tx1 }),
+                num_transactions: None,
             },
             expected_successes: vec![tx1, tx2, tx1, tx2, tx1],
             expected_errors: vec![tx2],
@@ -1060,6 +1118,41 @@ impl ScenarioShape for FrozenAccountRejectsChargebackOnOtherDispute {
     }
 }
 
+/// Open an account twice without ever depositing. The second open is a no-op; both succeed.
+/// Expected: a zero-balance account
+pub struct OpenWithoutDeposit;
+
+impl ScenarioShape for OpenWithoutDeposit {
+    fn num_random_parameters(&self) -> usize {
+        0
+    }
+
+    fn build(&self, client_id: u16, tx_id_offset: u64, _random_parameters: &[Decimal]) -> Scenario {
+        let tx_open = tx_id_offset + 1;
+        let tx_reopen = tx_id_offset + 2;
+
+        Scenario {
+            name: "OpenWithoutDeposit",
+            client_id,
+            transactions: vec![
+                format!("open, {client_id}, {tx_open},"),
+                format!("open, {client_id}, {tx_reopen}, 0"),
+            ],
+            expected_account: AccountRecord {
+                client: client_id,
+                available: Decimal::ZERO,
+                held: Decimal::ZERO,
+                total: Decimal::ZERO,
+                locked: false,
+                lock_reason: None,
+                num_transactions: None,
+            },
+            expected_successes: vec![tx_open, tx_reopen],
+            expected_errors: vec![],
+        }
+    }
+}
+
 /// Returns all available scenario shapes.
 pub fn all_shapes() -> Vec<Box<dyn ScenarioShape>> {
     vec![
@@ -1092,5 +1185,6 @@ pub fn all_shapes() -> Vec<Box<dyn ScenarioShape>> {
         Box::new(FrozenAccountRejectsDispute),
         Box::new(TwoDepositsDisputeAndChargebackFirst),
         Box::new(FrozenAccountRejectsChargebackOnOtherDispute),
+        Box::new(OpenWithoutDeposit),
     ]
 }
@@ -5,18 +5,30 @@
 pub(crate) mod catalog;
 pub(crate) mod scenario;
 
+use std::sync::Mutex;
+
 use proptest::prelude::*;
 use rust_decimal::Decimal;
 use scenario::{Scenario, assert_scenarios, interleave, run_process};
 
-use crate::scenarios::scenario::{ProcessResult, run_process_parallel};
+use tx_engine_rs::{DisputePolicy, Error, ProcessConfig, process_with_config};
+
+use crate::scenarios::scenario::{ProcessResult, run_process_parallel, run_process_with_config};
 
 const CHANNEL_CAPACITY: usize = 256;
+/// The number of shapes in the catalog, from which the scenarios are drawn
+const NUM_SHAPES: usize = 30;
+
+/// Keeps the drawn shape indices in sync with the catalog, so that every shape is covered (and none twice as often)
+#[test]
+fn shape_indices_cover_the_catalog() {
+    assert_eq!(catalog::all_shapes().len(), NUM_SHAPES);
+}
 
 proptest! {
     #[test]
     fn interleaved_scenarios_produce_correct_results(
-        shape_indices in prop::collection::vec(0usize..29, 2..=6),
+        shape_indices in prop::collection::vec(0usize..NUM_SHAPES, 2..=6),
         random_parameters in prop::collection::vec(1u64..100_000, 50),
         seed in any::<u64>(),
     ) {
@@ -25,7 +37,7 @@ proptest! {
 
     #[test]
     fn interleaved_scenarios_produce_correct_results_parallel(
-        shape_indices in prop::collection::vec(0usize..29, 2..=6),
+        shape_indices in prop::collection::vec(0usize..NUM_SHAPES, 2..=6),
         random_parameters in prop::collection::vec(1u64..100_000, 50),
         seed in any::<u64>(),
     ) {
@@ -36,6 +48,99 @@ proptest! {
             run_process_parallel(csv, n_workers, CHANNEL_CAPACITY)
         });
     }
+
+    /// Pins the scheduling of the parallel engine, so that a failing case reproduces identically
+    #[test]
+    fn interleaved_scenarios_produce_correct_results_parallel_deterministic(
+        shape_indices in prop::collection::vec(0usize..NUM_SHAPES, 2..=6),
+        random_parameters in prop::collection::vec(1u64..100_000, 50),
+        seed in any::<u64>(),
+    ) {
+        run_scenario_test(shape_indices, random_parameters, seed, |csv| {
+            let config = ProcessConfig::default().workers(3).deterministic(true);
+            run_process_with_config(csv, config)
+        });
+    }
+
+    /// Every applied transaction is accounted for: the accounts hold the deposits minus the withdrawals minus the
+    /// chargebacks
+    #[test]
+    fn interleaved_scenarios_conserve_the_funds(
+        shape_indices in prop::collection::vec(0usize..NUM_SHAPES, 2..=6),
+        random_parameters in prop::collection::vec(1u64..100_000, 50),
+        seed in any::<u64>(),
+        workers in prop::option::of(1usize..4),
+    ) {
+        run_scenario_test(shape_indices, random_parameters, seed, |csv| {
+            let config = || {
+                let config = ProcessConfig::default().verify_conservation(true);
+                match workers {
+                    Some(workers) => config.workers(workers),
+                    None => config,
+                }
+            };
+            let not_conserved = Mutex::new(Vec::new());
+            let _ = process_with_config(
+                csv.as_bytes(),
+                config(),
+                |e| {
+                    if matches!(e, Error::NotConserved { .. }) {
+                        not_conserved.lock().unwrap().push(e.to_string());
+                    }
+                },
+                |_| {},
+            )
+            .count();
+            assert_eq!(not_conserved.into_inner().unwrap(), Vec::<String>::new());
+
+            run_process_with_config(csv, config())
+        });
+    }
+
+    /// The funds are also conserved if withdrawals are disputed (holding funds in addition to the available ones) and
+    /// disputes are settled partially
+    #[test]
+    fn random_disputes_of_withdrawals_and_partial_settlements_conserve_the_funds(
+        rows in prop::collection::vec((0u8..5, 1u16..=2, 1u64..=40, 1u64..2_000, any::<bool>()), 1..40),
+        workers in prop::option::of(1usize..4),
+    ) {
+        let mut csv = String::from("type, client, tx, amount\n");
+        for (index, (kind, client, reference, cents, with_amount)) in rows.into_iter().enumerate() {
+            let amount = Decimal::new(cents.try_into().unwrap(), 2);
+            let settled = if with_amount { amount.to_string() } else { String::new() };
+            let row = match kind {
+                0 => format!("deposit, {client}, {}, {amount}", index + 1),
+                1 => format!("withdrawal, {client}, {}, {amount}", index + 1),
+                2 => format!("dispute, {client}, {reference},"),
+                3 => format!("resolve, {client}, {reference}, {settled}"),
+                _ => format!("chargeback, {client}, {reference}, {settled}"),
+            };
+            csv.push_str(&row);
+            csv.push('\n');
+        }
+        let mut config = ProcessConfig::default()
+            .dispute_withdrawals(true)
+            .partial_settlements(true)
+            .dispute_policy(DisputePolicy::PartialHold)
+            .verify_conservation(true);
+        if let Some(workers) = workers {
+            config = config.workers(workers);
+        }
+
+        let not_conserved = Mutex::new(Vec::new());
+        let _ = process_with_config(
+            csv.as_bytes(),
+            config,
+            |e| {
+                if matches!(e, Error::NotConserved { .. }) {
+                    not_conserved.lock().unwrap().push(e.to_string());
+                }
+            },
+            |_| {},
+        )
+        .count();
+        prop_assert_eq!(not_conserved.into_inner().unwrap(), Vec::<String>::new());
+    }
 }
 
 fn run_scenario_test(
@@ -47,7 +152,7 @@ fn run_scenario_test(
     let catalog = catalog::all_shapes();
 
     let mut param_cursor = 0;
-    let mut tx_id_offset = 1u32;
+    let mut tx_id_offset = 1u64;
     let mut scenarios = Vec::new();
 
     for (i, &idx) in shape_indices.iter().enumerate() {
@@ -64,7 +169,7 @@ fn run_scenario_test(
         param_cursor += n;
 
         let scenario = shape.build(client_id, tx_id_offset, &params);
-        tx_id_offset += scenario.transactions.len() as u32;
+        tx_id_offset += scenario.transactions.len() as u64;
         scenarios.push(scenario);
     }
 
@@ -104,17 +209,17 @@ pub(crate) fn build_schedule(scenarios: &[Scenario], seed: u64) -> Vec<usize> {
 
 mod tests {
     use super::*;
-    use catalog::{SingleDeposit, TwoDeposits};
+    use catalog::{DepositThenOverdraft, DisputeInsufficientFunds, SingleDeposit, TwoDeposits};
     use rust_decimal_macros::dec;
     use scenario::ScenarioShape;
 
     /// Convenience: build a SingleDeposit scenario with a given client, offset, and amount.
-    fn single(client_id: u16, offset: u32, amount: Decimal) -> Scenario {
+    fn single(client_id: u16, offset: u64, amount: Decimal) -> Scenario {
         SingleDeposit.build(client_id, offset, &[amount])
     }
 
     /// Convenience: build a TwoDeposits scenario with a given client, offset, and amounts.
-    fn two(client_id: u16, offset: u32, amounts: [Decimal; 2]) -> Scenario {
+    fn two(client_id: u16, offset: u64, amounts: [Decimal; 2]) -> Scenario {
         TwoDeposits.build(client_id, offset, &amounts)
     }
 
@@ -268,4 +373,22 @@ mod tests {
         let result = run_process(&csv);
         assert_scenarios(&scenarios, &result);
     }
+
+    #[test]
+    fn processing_errors_are_captured_by_both_engines() {
+        let scenarios = [
+            DepositThenOverdraft.build(1, 0, &[dec!(1.0), dec!(0.5)]),
+            DisputeInsufficientFunds.build(2, 2, &[dec!(2.0), dec!(1.5)]),
+        ];
+        assert!(
+            scenarios
+                .iter()
+                .all(|scenario| !scenario.expected_errors.is_empty())
+        );
+        let schedule = build_schedule(&scenarios, 7);
+        let csv = interleave(&scenarios, &schedule);
+
+        assert_scenarios(&scenarios, &run_process(&csv));
+        assert_scenarios(&scenarios, &run_process_parallel(&csv, 2, CHANNEL_CAPACITY));
+    }
 }
@@ -3,7 +3,7 @@
 use std::collections::HashMap;
 
 use rust_decimal::Decimal;
-use tx_engine_rs::{AccountRecord, Error, TransactionRecord};
+use tx_engine_rs::{AccountRecord, Error, ProcessConfig, TransactionRecord};
 
 /// A self-contained per-client test story.
 pub struct Scenario {
@@ -16,9 +16,9 @@ pub struct Scenario {
     /// The expected final account state after all transactions
     pub expected_account: AccountRecord,
     /// Transaction IDs that should be processed successfully
-    pub expected_successes: Vec<u32>,
+    pub expected_successes: Vec<u64>,
     /// Transaction IDs that should produce errors
-    pub expected_errors: Vec<u32>,
+    pub expected_errors: Vec<u64>,
 }
 
 /// A trait for scenario shapes that can be instantiated with random parameters.
@@ -28,7 +28,7 @@ pub trait ScenarioShape {
     /// Random parameters are independent values from proptest — the shape's `build`
     /// method may combine or transform them to establish required relationships
     /// (e.g., ensuring a withdrawal exceeds a deposit).
-    fn build(&self, client_id: u16, tx_id_offset: u32, random_parameters: &[Decimal]) -> Scenario;
+    fn build(&self, client_id: u16, tx_id_offset: u64, random_parameters: &[Decimal]) -> Scenario;
 
     /// How many random parameters this shape needs from proptest.
     fn num_random_parameters(&self) -> usize;
@@ -71,31 +71,23 @@ pub fn interleave(scenarios: &[Scenario], schedule: &[usize]) -> String {
     rows.join("\n")
 }
 
-/// Runs `process` and collects results keyed by client_id for easy assertion.
+/// The results of a run keyed by client_id for easy assertion. The errors hold the transactions rejected with a
+/// validation, processing, or account-locked error.
 pub struct ProcessResult {
     pub accounts: HashMap<u16, AccountRecord>,
-    pub successes: HashMap<u16, Vec<u32>>,
-    pub errors: HashMap<u16, Vec<u32>>,
+    pub successes: HashMap<u16, Vec<u64>>,
+    pub errors: HashMap<u16, Vec<u64>>,
 }
 
 pub fn run_process(csv_input: &str) -> ProcessResult {
-    let mut successes: HashMap<u16, Vec<u32>> = HashMap::new();
-    let mut errors: HashMap<u16, Vec<u32>> = HashMap::new();
+    let mut successes = HashMap::new();
+    let mut errors = HashMap::new();
 
-    let accounts: HashMap<u16, AccountRecord> = tx_engine_rs::process(
+    let accounts = tx_engine_rs::process_to_map(
         csv_input.as_bytes(),
-        |e| {
-            if let Some((client_id, tx_id)) = error_fields(&e) {
-                errors.entry(client_id).or_default().push(tx_id);
-            }
-        },
-        |tx| {
-            let (client, tx_id) = tx_record_fields(&tx);
-            successes.entry(client).or_default().push(tx_id);
-        },
-    )
-    .map(|a| (a.client, a))
-    .collect();
+        |e| record_error(&mut errors, &e),
+        |tx| record_success(&mut successes, &tx),
+    );
 
     ProcessResult {
         accounts,
@@ -109,22 +101,22 @@ pub fn run_process_parallel(
     n_workers: usize,
     channel_capacity: usize,
 ) -> ProcessResult {
-    let mut successes: HashMap<u16, Vec<u32>> = HashMap::new();
-    let mut errors: HashMap<u16, Vec<u32>> = HashMap::new();
+    let config = ProcessConfig::default()
+        .workers(n_workers)
+        .channel_capacity(channel_capacity);
+    run_process_with_config(csv_input, config)
+}
+
+/// Runs `process_with_config`, e.g., to pin the scheduling of the parallel engine
+pub fn run_process_with_config(csv_input: &str, config: ProcessConfig<'_>) -> ProcessResult {
+    let mut successes: HashMap<u16, Vec<u64>> = HashMap::new();
+    let mut errors: HashMap<u16, Vec<u64>> = HashMap::new();
 
-    let accounts: HashMap<u16, AccountRecord> = tx_engine_rs::process_parallel(
+    let accounts: HashMap<u16, AccountRecord> = tx_engine_rs::process_with_config(
         csv_input.as_bytes(),
-        |e| {
-            if let Some((client_id, tx_id)) = error_fields(&e) {
-                errors.entry(client_id).or_default().push(tx_id);
-            }
-        },
-        |tx| {
-            let (client, tx_id) = tx_record_fields(&tx);
-            successes.entry(client).or_default().push(tx_id);
-        },
-        n_workers,
-        channel_capacity,
+        config,
+        |e| record_error(&mut errors, &e),
+        |tx| record_success(&mut successes, &tx),
     )
     .map(|a| (a.client, a))
     .collect();
@@ -174,25 +166,48 @@ pub fn assert_scenarios(scenarios: &[Scenario], result: &ProcessResult) {
     }
 }
 
+fn record_error(errors: &mut HashMap<u16, Vec<u64>>, e: &Error) {
+    if let Some((client_id, tx_id)) = error_fields(e) {
+        errors.entry(client_id).or_default().push(tx_id);
+    }
+}
+
+fn record_success(successes: &mut HashMap<u16, Vec<u64>>, tx: &TransactionRecord) {
+    let (client, tx_id) = tx_record_fields(tx);
+    successes.entry(client).or_default().push(tx_id);
+}
+
 /// Extracts (client, tx_id) from any TransactionRecord variant.
-fn tx_record_fields(tx: &TransactionRecord) -> (u16, u32) {
+fn tx_record_fields(tx: &TransactionRecord) -> (u16, u64) {
     match tx {
         TransactionRecord::Deposit { client, tx, .. } => (*client, *tx),
         TransactionRecord::Withdrawal { client, tx, .. } => (*client, *tx),
         TransactionRecord::Dispute { client, tx } => (*client, *tx),
-        TransactionRecord::Resolve { client, tx } => (*client, *tx),
-        TransactionRecord::Chargeback { client, tx } => (*client, *tx),
+        TransactionRecord::Resolve { client, tx, .. } => (*client, *tx),
+        TransactionRecord::Chargeback { client, tx, .. } => (*client, *tx),
+        TransactionRecord::Open { client, tx } => (*client, *tx),
+        TransactionRecord::Unlock { client, tx } => (*client, *tx),
     }
 }
 
-fn error_fields(err: &Error) -> Option<(u16, u32)> {
+fn error_fields(err: &Error) -> Option<(u16, u64)> {
     match err {
-        Error::Csv(..) => None,
+        Error::Csv { .. }
+        | Error::Binary { .. }
+        | Error::Header { .. }
+        | Error::WorkerFailed { .. }
+        | Error::Truncated { .. }
+        | Error::NotConserved { .. } => None,
         Error::Validation {
             client_id, tx_id, ..
         } => Some((*client_id, *tx_id)),
         Error::Processing {
             client_id, tx_id, ..
         } => Some((*client_id, *tx_id)),
+        Error::AccountLocked { client_id, tx_id } => Some((*client_id, *tx_id)),
+        Error::Malformed { source, .. } => error_fields(source),
+        Error::ShardUnavailable {
+            client_id, tx_id, ..
+        } => Some((*client_id, *tx_id)),
     }
 }
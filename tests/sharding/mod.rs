@@ -0,0 +1,66 @@
+//! Integration tests for the consistency check of pre-sharded inputs
+
+use tx_engine_rs::{process, verify_sharding};
+
+const SHARD_A: &str = "\
+type, client, tx, amount
+deposit, 1, 1, 10.0
+deposit, 3, 2, 5.0
+withdrawal, 1, 3, 2.0";
+
+const SHARD_B: &str = "\
+type, client, tx, amount
+deposit, 2, 4, 1.0
+deposit, 4, 5, 1.0";
+
+#[test]
+fn disjoint_shards_are_accepted() {
+    assert_eq!(
+        verify_sharding([SHARD_A.as_bytes(), SHARD_B.as_bytes()]),
+        Ok(())
+    );
+}
+
+#[test]
+fn clients_in_several_shards_are_listed() {
+    let shard_c = "\
+type, client, tx, amount
+dispute, 3, 2,
+deposit, 4, 6, 1.0
+deposit, 5, 7, 1.0
+deposit, 1, 8, 1.0";
+
+    assert_eq!(
+        verify_sharding([SHARD_A.as_bytes(), SHARD_B.as_bytes(), shard_c.as_bytes()]),
+        Err(vec![1, 3, 4])
+    );
+}
+
+#[test]
+fn a_split_client_would_yield_partial_accounts() {
+    let shard_c = "\
+type, client, tx, amount
+deposit, 1, 8, 1.0";
+    let shards = [SHARD_A, shard_c];
+
+    assert_eq!(verify_sharding(shards.map(str::as_bytes)), Err(vec![1]));
+    let client_1_accounts = shards
+        .iter()
+        .flat_map(|shard| process(shard.as_bytes(), |_| {}, |_| {}))
+        .filter(|record| record.client == 1)
+        .count();
+    assert_eq!(client_1_accounts, 2);
+}
+
+#[test]
+fn invalid_rows_are_not_considered() {
+    let shard_c = "\
+type, client, tx, amount
+deposit, 1, 8, -1.0
+deposit, 5, 9, 1.0";
+
+    assert_eq!(
+        verify_sharding([SHARD_A.as_bytes(), shard_c.as_bytes()]),
+        Ok(())
+    );
+}
@@ -0,0 +1,194 @@
+//! Integration tests for the statistics returned by `process_with_stats`
+
+use std::{sync::Mutex, thread, time::Duration};
+
+use rstest::rstest;
+use tx_engine_rs::{ProcessConfig, ProcessStats, process_with_stats};
+
+const MIXED_INPUT: &str = "\
+type, client, tx, amount
+deposit, 1, 1, 10.0
+deposit, 2, 2, 5.0
+deposit, 3, not-a-number, 1.0
+deposit, 3, 4, -1.0
+withdrawal, 2, 5, 50.0
+dispute, 1, 1,
+resolve, 1, 1,
+withdrawal, 1, 6, 1.0";
+
+#[rstest]
+#[case::sequential(ProcessConfig::default(), 0)]
+#[case::parallel(ProcessConfig::default().workers(3).channel_capacity(2), 3)]
+fn stats_count_each_outcome(#[case] config: ProcessConfig<'static>, #[case] config_workers: u64) {
+    let mut errors = 0;
+    let mut successes = 0;
+    let (records, stats) = process_with_stats(
+        MIXED_INPUT.as_bytes(),
+        config,
+        |_| errors += 1,
+        |_| successes += 1,
+    );
+    let records: Vec<_> = records.collect();
+
+    assert_eq!(
+        stats,
+        ProcessStats {
+            accepted: 5,
+            validation_errors: 1,
+            processing_errors: 1,
+            csv_errors: 1,
+            accounts: 2,
+            checksum: stats.checksum,
+            workers: config_workers,
+            // depends on the thread scheduling
+            backpressure_waits: stats.backpressure_waits,
+            worker_failures: 0,
+            truncated: false,
+        }
+    );
+    assert_eq!(stats.rejected(), 3);
+    assert_eq!(records.len() as u64, stats.accounts);
+
+    // the caller's callbacks are still invoked
+    assert_eq!(successes, stats.accepted);
+    assert_eq!(errors, stats.rejected());
+}
+
+#[test]
+fn stats_of_empty_input_are_zero() {
+    let (records, stats) = process_with_stats(
+        "type, client, tx, amount\n".as_bytes(),
+        ProcessConfig::default(),
+        |_| {},
+        |_| {},
+    );
+
+    assert_eq!(records.count(), 0);
+    assert_eq!(stats, ProcessStats::default());
+}
+
+#[test]
+fn backpressure_is_reported_per_worker_and_in_total() {
+    let input: String = std::iter::once("type, client, tx, amount".to_string())
+        .chain((1..=20).map(|tx| format!("deposit, 1, {tx}, 1.0")))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let per_worker = Mutex::new(None);
+    let config = ProcessConfig::default()
+        .workers(2)
+        .channel_capacity(1)
+        .on_backpressure(|counts| *per_worker.lock().unwrap() = Some(counts));
+    // a slow consumer stalls the worker of client 1, so that the dispatcher has to wait for it
+    let (records, stats) = process_with_stats(
+        input.as_bytes(),
+        config,
+        |e| panic!("unexpected error: {e}"),
+        |_| thread::sleep(Duration::from_millis(1)),
+    );
+    assert_eq!(records.count(), 1);
+
+    let per_worker = per_worker.into_inner().unwrap().expect("hook was invoked");
+    assert_eq!(per_worker.len(), 2);
+    assert_eq!(per_worker[0], 0, "the worker without clients is never full");
+    assert!(per_worker[1] > 0);
+    assert_eq!(per_worker.iter().sum::<u64>(), stats.backpressure_waits);
+}
+
+#[test]
+fn sequential_processing_has_no_backpressure() {
+    let (_, stats) = process_with_stats(
+        MIXED_INPUT.as_bytes(),
+        ProcessConfig::default().on_backpressure(|_| panic!("not invoked sequentially")),
+        |_| {},
+        |_| {},
+    );
+
+    assert_eq!(stats.backpressure_waits, 0);
+}
+
+#[rstest]
+#[case::sequential(ProcessConfig::default(), None)]
+#[case::zero_raised_to_one(ProcessConfig::default().workers(0), Some(1))]
+#[case::as_configured(ProcessConfig::default().workers(5), Some(5))]
+#[case::clamped(ProcessConfig::default().workers(usize::MAX).clamp_workers(true), Some(available_parallelism()))]
+#[case::below_the_clamp(ProcessConfig::default().workers(1).clamp_workers(true), Some(1))]
+fn effective_workers_are_resolved_from_the_config(
+    #[case] config: ProcessConfig<'static>,
+    #[case] expected: Option<usize>,
+) {
+    assert_eq!(config.effective_workers(), expected);
+
+    let (_, stats) = process_with_stats(MIXED_INPUT.as_bytes(), config, |_| {}, |_| {});
+    assert_eq!(stats.workers, expected.unwrap_or(0) as u64);
+}
+
+fn available_parallelism() -> usize {
+    thread::available_parallelism().map_or(1, |n| n.get())
+}
+
+fn checksum_of(input: &str, config: ProcessConfig<'_>) -> u64 {
+    let (_, stats) = process_with_stats(input.as_bytes(), config, |_| {}, |_| {});
+    stats.checksum
+}
+
+#[test]
+fn checksum_of_sequential_and_parallel_runs_agree() {
+    let input = std::fs::read_to_string(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/tests/data/representative.csv"
+    ))
+    .unwrap();
+
+    let sequential = checksum_of(&input, ProcessConfig::default());
+    let parallel = checksum_of(
+        &input,
+        ProcessConfig::default().workers(4).channel_capacity(8),
+    );
+
+    assert_ne!(sequential, 0);
+    assert_eq!(sequential, parallel);
+}
+
+#[test]
+fn checksum_depends_on_the_applied_transactions_only() {
+    let base = checksum_of(MIXED_INPUT, ProcessConfig::default());
+
+    // the applied transactions of different clients in a different order, with an equivalent amount notation
+    let reordered = "\
+type, client, tx, amount
+deposit, 2, 2, 5.00
+deposit, 1, 1, 10
+withdrawal, 2, 5, 50.0
+dispute, 1, 1,
+resolve, 1, 1,
+withdrawal, 1, 6, 1.0";
+    assert_eq!(checksum_of(reordered, ProcessConfig::default()), base);
+
+    let changed = MIXED_INPUT.replace("withdrawal, 1, 6, 1.0", "withdrawal, 1, 6, 1.5");
+    assert_ne!(checksum_of(&changed, ProcessConfig::default()), base);
+
+    // a partial resolve (of all held funds) differs from a full one
+    let partial = MIXED_INPUT.replace("resolve, 1, 1,", "resolve, 1, 1, 10.0");
+    let config = || ProcessConfig::default().partial_settlements(true);
+    assert_eq!(checksum_of(MIXED_INPUT, config()), base);
+    assert_ne!(checksum_of(&partial, config()), base);
+}
+
+#[rstest]
+fn on_complete_fires_once_before_the_records_are_produced(
+    #[values(MIXED_INPUT, "type, client, tx, amount\n", "")] input: &str,
+    #[values(None, Some(3))] workers: Option<usize>,
+) {
+    let completed = Mutex::new(Vec::new());
+    let mut config =
+        ProcessConfig::default().on_complete(|stats| completed.lock().unwrap().push(stats));
+    if let Some(workers) = workers {
+        config = config.workers(workers);
+    }
+    let (records, stats) = process_with_stats(input.as_bytes(), config, |_| {}, |_| {});
+
+    assert_eq!(*completed.lock().unwrap(), vec![stats]);
+    assert_eq!(records.count() as u64, stats.accounts);
+    assert_eq!(completed.into_inner().unwrap().len(), 1);
+}
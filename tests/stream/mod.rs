@@ -0,0 +1,136 @@
+//! Integration tests for the async entry point consuming a stream of transactions
+
+use futures_util::stream;
+use rust_decimal_macros::dec;
+use tx_engine_rs::{AccountRecord, Error, ProcessConfig, TransactionRecord, process_stream};
+
+#[tokio::test]
+async fn stream_is_processed_like_csv_input() {
+    let transactions = stream::iter([
+        Ok(TransactionRecord::Deposit {
+            client: 1,
+            tx: 1,
+            amount: dec!(10.0),
+        }),
+        Ok(TransactionRecord::Deposit {
+            client: 2,
+            tx: 2,
+            amount: dec!(3.0),
+        }),
+        Ok(TransactionRecord::Withdrawal {
+            client: 1,
+            tx: 3,
+            amount: dec!(4.0),
+        }),
+        Ok(TransactionRecord::Dispute { client: 2, tx: 2 }),
+    ]);
+
+    let mut successes = Vec::new();
+    let mut records: Vec<AccountRecord> = process_stream(
+        transactions,
+        ProcessConfig::default(),
+        |e| panic!("unexpected error: {e}"),
+        |tx| successes.push(tx),
+    )
+    .await
+    .collect();
+    records.sort_by_key(|r| r.client);
+
+    assert_eq!(successes.len(), 4);
+    assert_eq!(
+        records,
+        vec![
+            AccountRecord {
+                client: 1,
+                available: dec!(6.0),
+                held: dec!(0),
+                total: dec!(6.0),
+                locked: false,
+                lock_reason: None,
+                num_transactions: None,
+            },
+            AccountRecord {
+                client: 2,
+                available: dec!(0),
+                held: dec!(3.0),
+                total: dec!(3.0),
+                locked: false,
+                lock_reason: None,
+                num_transactions: None,
+            },
+        ]
+    );
+}
+
+#[tokio::test]
+async fn invalid_records_and_stream_errors_are_reported() {
+    let decoding_error = Error::Csv {
+        line: Some(3),
+        source: csv::Error::from(std::io::Error::other("connection reset")),
+    };
+    let transactions = stream::iter([
+        Ok(TransactionRecord::Deposit {
+            client: 1,
+            tx: 1,
+            amount: dec!(-1.0),
+        }),
+        Err(decoding_error),
+        Ok(TransactionRecord::Deposit {
+            client: 1,
+            tx: 2,
+            amount: dec!(1.0),
+        }),
+    ]);
+
+    let mut errors = Vec::new();
+    let records: Vec<AccountRecord> = process_stream(
+        transactions,
+        ProcessConfig::default(),
+        |e| errors.push(e),
+        |_| {},
+    )
+    .await
+    .collect();
+
+    assert_eq!(records.len(), 1);
+    assert_eq!(records[0].available, dec!(1.0));
+    assert_eq!(errors.len(), 2);
+    assert!(matches!(
+        errors[0],
+        Error::Validation {
+            client_id: 1,
+            tx_id: 1,
+            ..
+        }
+    ));
+    assert!(matches!(errors[1], Error::Csv { line: Some(3), .. }));
+}
+
+#[tokio::test]
+async fn partial_resolve_is_applied_with_its_amount() {
+    let transactions = stream::iter([
+        Ok(TransactionRecord::Deposit {
+            client: 1,
+            tx: 1,
+            amount: dec!(10.0),
+        }),
+        Ok(TransactionRecord::Dispute { client: 1, tx: 1 }),
+        Ok(TransactionRecord::Resolve {
+            client: 1,
+            tx: 1,
+            amount: Some(dec!(3.0)),
+        }),
+    ]);
+
+    let records: Vec<AccountRecord> = process_stream(
+        transactions,
+        ProcessConfig::default().partial_settlements(true),
+        |e| panic!("unexpected error: {e}"),
+        |_| {},
+    )
+    .await
+    .collect();
+
+    assert_eq!(records[0].available, dec!(3.0));
+    assert_eq!(records[0].held, dec!(7.0));
+}
@@ -1,7 +1,11 @@
 //! Integration tests for withdrawal transactions
 
+use rstest::rstest;
 use rust_decimal_macros::dec;
-use tx_engine_rs::{AccountRecord, Error, TransactionRecord, process};
+use tx_engine_rs::{
+    AccountRecord, Error, ProcessConfig, ProcessingKind, TransactionRecord, process,
+    process_with_config,
+};
 
 #[test]
 fn deposit_then_withdraw() {
@@ -16,6 +20,8 @@ withdrawal, 1, 2, 4.0";
         held: dec!(0),
         total: dec!(6.0),
         locked: false,
+        lock_reason: None,
+        num_transactions: None,
     };
 
     let mut successful_txs: Vec<TransactionRecord> = Vec::new();
@@ -62,6 +68,8 @@ withdrawal, 1, 3, 3.0";
         held: dec!(0),
         total: dec!(10.0),
         locked: false,
+        lock_reason: None,
+        num_transactions: None,
     };
 
     let mut successful_txs: Vec<TransactionRecord> = Vec::new();
@@ -91,6 +99,8 @@ withdrawal, 1, 3, 3.0";
         held: dec!(0),
         total: dec!(7.0),
         locked: false,
+        lock_reason: None,
+        num_transactions: None,
     };
 
     let mut errors: Vec<Error> = Vec::new();
@@ -204,3 +214,137 @@ withdrawal, 1, 2, -5.0";
         _ => panic!("Expected a Validation error"),
     }
 }
+
+/// Runs the input with an overdraft limit of 5.0, collecting the records and errors.
+fn process_with_overdraft(input: &str) -> (Vec<AccountRecord>, Vec<Error>) {
+    let mut errors: Vec<Error> = Vec::new();
+    let config = ProcessConfig::default().overdraft_limit(dec!(5.0));
+    let records =
+        process_with_config(input.as_bytes(), config, |e| errors.push(e), |_| {}).collect();
+    (records, errors)
+}
+
+#[test]
+fn withdrawal_exactly_at_overdraft_limit_is_accepted() {
+    let input = "\
+type, client, tx, amount
+deposit, 1, 1, 10.0
+withdrawal, 1, 2, 15.0";
+
+    let (records, errors) = process_with_overdraft(input);
+
+    assert!(errors.is_empty(), "expected no errors, got: {errors:?}");
+    assert_eq!(records.len(), 1);
+    assert_eq!(records[0].available, dec!(-5.0));
+    assert_eq!(records[0].total, dec!(-5.0));
+}
+
+#[test]
+fn withdrawal_one_cent_past_overdraft_limit_is_rejected() {
+    let input = "\
+type, client, tx, amount
+deposit, 1, 1, 10.0
+withdrawal, 1, 2, 15.01";
+
+    let (records, errors) = process_with_overdraft(input);
+
+    assert_eq!(records.len(), 1);
+    assert_eq!(records[0].available, dec!(10.0));
+    assert_eq!(errors.len(), 1);
+    assert!(
+        matches!(
+            &errors[0],
+            Error::Processing {
+                client_id: 1,
+                tx_id: 2,
+                ..
+            }
+        ),
+        "expected a processing error for exceeding the overdraft limit"
+    );
+}
+
+#[test]
+fn deposit_after_overdraft_restores_positive_balance() {
+    let input = "\
+type, client, tx, amount
+deposit, 1, 1, 10.0
+withdrawal, 1, 2, 13.0
+deposit, 1, 3, 5.0";
+
+    let expected = AccountRecord {
+        client: 1,
+        available: dec!(2.0),
+        held: dec!(0),
+        total: dec!(2.0),
+        locked: false,
+        lock_reason: None,
+        num_transactions: None,
+    };
+
+    let (records, errors) = process_with_overdraft(input);
+
+    assert!(errors.is_empty(), "expected no errors, got: {errors:?}");
+    assert_eq!(records, vec![expected]);
+}
+
+const WITHDRAWAL_FIRST_INPUT: &str = "\
+type, client, tx, amount
+withdrawal, 1, 1, 1.0
+dispute, 2, 7,
+deposit, 1, 2, 3.0
+withdrawal, 1, 3, 1.0";
+
+#[rstest]
+#[case::strict(false, ProcessingKind::NoAccount, 1)]
+#[case::auto_create(true, ProcessingKind::InsufficientFunds, 2)]
+fn withdrawal_first_for_client(
+    #[case] auto_create: bool,
+    #[case] first_error: ProcessingKind,
+    #[case] accounts: usize,
+) {
+    let mut errors: Vec<Error> = Vec::new();
+    let config = ProcessConfig::default().auto_create_accounts(auto_create);
+    let records: Vec<AccountRecord> = process_with_config(
+        WITHDRAWAL_FIRST_INPUT.as_bytes(),
+        config,
+        |e| errors.push(e),
+        |_| {},
+    )
+    .collect();
+
+    // the withdrawal and the dispute are rejected in both modes, for different reasons
+    let kinds: Vec<_> = errors.iter().map(Error::processing_kind).collect();
+    let dispute_error = if auto_create {
+        ProcessingKind::UnknownTransaction
+    } else {
+        ProcessingKind::NoAccount
+    };
+    assert_eq!(kinds, vec![Some(first_error), Some(dispute_error)]);
+
+    // the later deposit and withdrawal are applied either way
+    assert_eq!(records.len(), accounts);
+    let client_1 = records.iter().find(|r| r.client == 1).unwrap();
+    assert_eq!(client_1.available, dec!(2.0));
+}
+
+#[rstest]
+#[case::rejected_by_default(false, 1)]
+#[case::allowed(true, 0)]
+fn zero_withdrawal_follows_the_zero_amount_policy(
+    #[case] allow_zero_amount: bool,
+    #[case] expected_errors: usize,
+) {
+    let input = "\
+type, client, tx, amount
+deposit, 1, 1, 5.0
+withdrawal, 1, 2, 0";
+
+    let mut errors: Vec<Error> = Vec::new();
+    let config = ProcessConfig::default().allow_zero_amount(allow_zero_amount);
+    let records: Vec<AccountRecord> =
+        process_with_config(input.as_bytes(), config, |e| errors.push(e), |_| {}).collect();
+
+    assert_eq!(errors.len(), expected_errors, "{errors:?}");
+    assert_eq!(records[0].available, dec!(5.0));
+}